@@ -0,0 +1,134 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::time::Duration;
+
+use git2::Repository;
+
+use crate::{
+    cli::{Args, OutputFormat},
+    forge_releases::ReleaseApi,
+    git::{commit_is_within_duration, get_release_tags, matches_tag_pattern},
+    releases::TagCommitsError,
+};
+
+/// Gaps between the release tags and the releases published on the forge.
+#[derive(Serialize)]
+pub struct ReleaseAudit {
+    /// The release tags within `--age`
+    pub tags: usize,
+    /// Tags which have never been published as a release
+    pub unpublished: Vec<String>,
+    /// Tags published as a release without any release notes
+    pub empty_notes: Vec<String>,
+    /// Published releases of tags which aren't in the local repository, because they have been
+    /// deleted or haven't been fetched
+    pub missing_tags: Vec<String>,
+}
+
+impl ReleaseAudit {
+    pub fn passed(&self) -> bool {
+        self.unpublished.is_empty() && self.empty_notes.is_empty() && self.missing_tags.is_empty()
+    }
+}
+
+/// Compare the release tags within `--age` with the releases published on the GitHub or
+/// GitLab origin remote, in both directions. Releases of tags which don't match
+/// `--tag-pattern` are ignored.
+pub fn audit_releases(
+    repo: &Repository,
+    max_age: Duration,
+    args: &Args,
+) -> Result<ReleaseAudit, TagCommitsError> {
+    let api = ReleaseApi::required(repo, args, "rels audit releases")?;
+    let published = api
+        .releases()
+        .map_err(|err| TagCommitsError::Api("list releases", err))?;
+
+    let mut tags = get_release_tags(repo, args)
+        .into_iter()
+        .filter(|tag| {
+            repo.find_commit(tag.target_id())
+                .is_ok_and(|commit| commit_is_within_duration(&commit, max_age))
+        })
+        .filter_map(|tag| tag.name().map(str::to_owned))
+        .collect::<Vec<_>>();
+    tags.sort();
+
+    let mut audit = ReleaseAudit {
+        tags: tags.len(),
+        unpublished: Vec::new(),
+        empty_notes: Vec::new(),
+        missing_tags: published
+            .iter()
+            .map(|release| release.tag.clone())
+            .filter(|tag| matches_tag_pattern(args, tag))
+            .filter(|tag| repo.find_reference(&format!("refs/tags/{}", tag)).is_err())
+            .collect(),
+    };
+    audit.missing_tags.sort();
+    for tag in tags {
+        match published.iter().find(|release| release.tag == tag) {
+            None => audit.unpublished.push(tag),
+            Some(release)
+                if release
+                    .body
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim()
+                    .is_empty() =>
+            {
+                audit.empty_notes.push(tag)
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(audit)
+}
+
+pub fn print_release_audit(audit: &ReleaseAudit, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(audit).expect("audit should serialize to JSON")
+        );
+        return;
+    }
+
+    if audit.passed() {
+        let checked = match audit.tags {
+            1 => "1 tag".to_owned(),
+            tags => format!("{} tags", tags),
+        };
+        println!(
+            "{}",
+            format!(
+                "Every tag has been published as a release with notes ({} checked)",
+                checked
+            )
+            .green()
+        );
+        return;
+    }
+    if !audit.unpublished.is_empty() {
+        println!("{}", "Tags which have never been published:".bold());
+        for tag in &audit.unpublished {
+            println!("  {}", tag.red());
+        }
+    }
+    if !audit.empty_notes.is_empty() {
+        println!("{}", "Releases without any notes:".bold());
+        for tag in &audit.empty_notes {
+            println!("  {}", tag.yellow());
+        }
+    }
+    if !audit.missing_tags.is_empty() {
+        println!(
+            "{} {}",
+            "Releases whose tags are missing locally:".bold(),
+            "(fetch them with git fetch --tags, or delete the releases)".dimmed()
+        );
+        for tag in &audit.missing_tags {
+            println!("  {}", tag.red());
+        }
+    }
+}
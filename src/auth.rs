@@ -0,0 +1,83 @@
+use clap::ValueEnum;
+use keyring::Entry;
+use std::{
+    env, fmt,
+    io::{self, BufRead, IsTerminal},
+};
+
+use crate::releases::TagCommitsError;
+
+/// The service name tokens are stored under in the OS keyring.
+const KEYRING_SERVICE: &str = "rels";
+
+/// The services whose API tokens can be stored in the OS keyring with `rels auth login`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum AuthService {
+    Jira,
+    Linear,
+    Youtrack,
+    Github,
+    Gitlab,
+    AzureDevops,
+}
+
+impl fmt::Display for AuthService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self
+            .to_possible_value()
+            .expect("services should not be skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+fn keyring_entry(service: AuthService) -> keyring::Result<Entry> {
+    Entry::new(KEYRING_SERVICE, &service.to_string())
+}
+
+/// The token stored in the OS keyring for a service, if there is one. Keyrings which can't be
+/// accessed, e.g. on a server without one, are treated as having no token.
+pub fn stored_token(service: AuthService) -> Option<String> {
+    keyring_entry(service).ok()?.get_password().ok()
+}
+
+/// The token for a service from an environment variable, or the OS keyring if the variable
+/// isn't set.
+pub fn token(var: &str, service: AuthService) -> Option<String> {
+    env::var(var).ok().or_else(|| stored_token(service))
+}
+
+/// Read a token from the terminal without echoing it, or from stdin if it is piped.
+fn read_token(service: AuthService) -> io::Result<String> {
+    if io::stdin().is_terminal() {
+        return rpassword::prompt_password(format!("Token for {}: ", service));
+    }
+    let mut token = String::new();
+    io::stdin().lock().read_line(&mut token)?;
+    Ok(token)
+}
+
+/// Store a token for a service in the OS keyring, reading it from the terminal if it isn't given.
+pub fn login(service: AuthService, token: Option<String>) -> Result<(), TagCommitsError> {
+    let token = match token {
+        Some(token) => token,
+        None => read_token(service).map_err(|err| TagCommitsError::Io("read token", err))?,
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(TagCommitsError::Io(
+            "read token",
+            io::Error::new(io::ErrorKind::InvalidInput, "the token is empty"),
+        ));
+    }
+    keyring_entry(service)?.set_password(token)?;
+    Ok(())
+}
+
+/// Remove a service's token from the OS keyring, returning whether there was one.
+pub fn logout(service: AuthService) -> Result<bool, TagCommitsError> {
+    match keyring_entry(service)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,246 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::json;
+
+use git2::Repository;
+
+use crate::{
+    auth::{token, AuthService},
+    cli::{Args, AzureWikiArgs},
+    config::ConfigError,
+    forge::origin_remote,
+    forge_api::encode,
+    http::HttpClient,
+    lookups::LookupError,
+    markdown::{release_notes, releases_markdown},
+    releases::{TagCommits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// The environment variable holding a personal access token for Azure DevOps, with the Wiki
+/// (Read & Write) scope.
+const TOKEN_VAR: &str = "AZURE_DEVOPS_TOKEN";
+
+const API_VERSION: &str = "7.1";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WikiPage {
+    content: Option<String>,
+    remote_url: Option<String>,
+}
+
+/// The pages API of an Azure DevOps wiki.
+struct WikiApi {
+    http: HttpClient,
+    /// The URL of the wiki's pages, e.g.
+    /// `https://dev.azure.com/org/project/_apis/wiki/wikis/project.wiki/pages`
+    pages_url: String,
+    authorization: String,
+}
+
+impl WikiApi {
+    fn new(
+        repo: &Repository,
+        args: &Args,
+        wiki_args: &AzureWikiArgs,
+    ) -> Result<WikiApi, TagCommitsError> {
+        let project_url = wiki_args
+            .project_url
+            .clone()
+            .or_else(|| origin_remote(repo, args).and_then(|remote| remote.azure_devops_project_url()))
+            .ok_or_else(|| {
+                ConfigError::Invalid(
+                    "rels publish azure-wiki needs --project-url, or an origin remote on Azure DevOps"
+                        .to_owned(),
+                )
+            })?;
+        let project_url = project_url.trim_end_matches('/');
+        let token = token(TOKEN_VAR, AuthService::AzureDevops).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "rels publish azure-wiki needs a token in {}, or stored with rels auth login {}",
+                TOKEN_VAR,
+                AuthService::AzureDevops
+            ))
+        })?;
+        // The wiki created for a project is named after it
+        let wiki = match &wiki_args.wiki {
+            Some(wiki) => encode(wiki),
+            None => format!(
+                "{}.wiki",
+                project_url.rsplit('/').next().unwrap_or_default()
+            ),
+        };
+
+        Ok(WikiApi {
+            http: HttpClient::new(args)?,
+            pages_url: format!("{}/_apis/wiki/wikis/{}/pages", project_url, wiki),
+            // Personal access tokens are given as the password, without a user name
+            authorization: format!("Basic {}", BASE64_STANDARD.encode(format!(":{}", token))),
+        })
+    }
+
+    fn page_url(&self, path: &str) -> String {
+        format!(
+            "{}?path={}&includeContent=true&api-version={}",
+            self.pages_url,
+            encode(path),
+            API_VERSION
+        )
+    }
+
+    /// The page at a path with its ETag, which is needed to update it, or `None` if there
+    /// isn't a page there.
+    fn page(&self, path: &str) -> Result<Option<(WikiPage, String)>, LookupError> {
+        let url = self.page_url(path);
+        let mut response = match self.http.send(|agent| {
+            agent
+                .get(&url)
+                .header("Authorization", &self.authorization)
+                .call()
+        }) {
+            Ok(response) => response,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|etag| etag.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        Ok(Some((response.body_mut().read_json()?, etag)))
+    }
+
+    /// Create a page, or update it if the ETag of its current version is given.
+    fn put_page(
+        &self,
+        path: &str,
+        content: &str,
+        etag: Option<&str>,
+    ) -> Result<WikiPage, LookupError> {
+        let url = self.page_url(path);
+        Ok(self
+            .http
+            .send(|agent| {
+                let request = agent.put(&url).header("Authorization", &self.authorization);
+                match etag {
+                    Some(etag) => request.header("If-Match", etag),
+                    None => request,
+                }
+                .send_json(json!({ "content": content }))
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+}
+
+/// What happened to the wiki page of a release.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WikiPageOutcome {
+    Created,
+    Updated,
+    /// The page already had the same notes, e.g. from an earlier run
+    Unchanged,
+}
+
+/// The wiki page of a release.
+pub struct WikiPageReport {
+    pub tag: String,
+    pub path: String,
+    pub outcome: WikiPageOutcome,
+    /// The page in the wiki's web UI
+    pub url: Option<String>,
+}
+
+/// Create or update a page in an Azure DevOps wiki with the release notes of the tag given, or
+/// of every release matching `--filter`, as `--format markdown` prints them. Pages are
+/// published under `--path`, which is created if it doesn't exist, and pages which already
+/// have the same notes are left alone so the wiki history only shows real changes.
+pub fn publish_wiki_pages(
+    repo: &Repository,
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    wiki_args: &AzureWikiArgs,
+) -> Result<Vec<WikiPageReport>, TagCommitsError> {
+    let api = WikiApi::new(repo, args, wiki_args)?;
+    let releases = match &wiki_args.tag {
+        Some(tag) => {
+            if !tag_commits.tag_names.contains(tag) {
+                return Err(TagCommitsError::NotARelease(tag.clone()));
+            }
+            release_notes(args, trackers, tag_commits, tag)
+                .map(|notes| (tag.clone(), notes))
+                .into_iter()
+                .collect::<Vec<_>>()
+        }
+        None => releases_markdown(args, trackers, tag_commits)
+            .into_iter()
+            .map(|release| (release.tag_name.to_owned(), release.body))
+            .collect(),
+    };
+    let api_error = |err| TagCommitsError::Api("publish Azure DevOps wiki page", err);
+
+    // Pages can only be created under pages which exist
+    let parent = format!("/{}", wiki_args.path.trim_matches('/'));
+    let mut ancestor = String::new();
+    for segment in parent.split('/').filter(|segment| !segment.is_empty()) {
+        ancestor = format!("{}/{}", ancestor, segment);
+        if api.page(&ancestor).map_err(api_error)?.is_none() {
+            api.put_page(&ancestor, "", None).map_err(api_error)?;
+        }
+    }
+
+    let mut reports = Vec::new();
+    for (tag, notes) in releases {
+        let path = format!("{}/{}", parent.trim_end_matches('/'), tag);
+        let content = format!("{}\n", notes);
+        let (outcome, page) = match api.page(&path).map_err(api_error)? {
+            Some((page, _)) if page.content.as_deref().map(str::trim) == Some(content.trim()) => {
+                (WikiPageOutcome::Unchanged, page)
+            }
+            Some((_, etag)) => (
+                WikiPageOutcome::Updated,
+                api.put_page(&path, &content, Some(&etag))
+                    .map_err(api_error)?,
+            ),
+            None => (
+                WikiPageOutcome::Created,
+                api.put_page(&path, &content, None).map_err(api_error)?,
+            ),
+        };
+        reports.push(WikiPageReport {
+            tag,
+            path,
+            outcome,
+            url: page.remote_url,
+        });
+    }
+    Ok(reports)
+}
+
+/// Print what happened to each wiki page, only mentioning pages which were published if
+/// `quiet`.
+pub fn print_wiki_reports(reports: &[WikiPageReport], quiet: bool) {
+    if reports.is_empty() {
+        if !quiet {
+            println!("{}", "No releases to publish".dimmed());
+        }
+        return;
+    }
+
+    for report in reports {
+        let url = report.url.as_deref().unwrap_or(&report.path);
+        match report.outcome {
+            WikiPageOutcome::Created => println!("Created {}: {}", report.tag.bold(), url),
+            WikiPageOutcome::Updated => println!("Updated {}: {}", report.tag.bold(), url),
+            WikiPageOutcome::Unchanged if quiet => {}
+            WikiPageOutcome::Unchanged => println!(
+                "{}",
+                format!("{} is already up to date", report.tag).dimmed()
+            ),
+        }
+    }
+}
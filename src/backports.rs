@@ -0,0 +1,141 @@
+use colored::Colorize;
+use std::collections::BTreeSet;
+
+use git2::{Oid, Repository};
+
+use crate::{
+    cli::{Args, BackportsArgs},
+    git::{commit_is_within_duration, resolve_ref},
+    releases::TagCommitsError,
+    tickets::Trackers,
+};
+
+/// Tickets compared between a release line and mainline.
+pub struct BackportReport {
+    pub release: String,
+    pub mainline: String,
+    /// Tickets with commits on both the release line and mainline
+    pub backported: BTreeSet<String>,
+    /// Tickets only on mainline
+    pub mainline_only: BTreeSet<String>,
+    /// Tickets only on the release line, e.g. hotfixes which never made it back to mainline
+    pub not_merged_back: BTreeSet<String>,
+}
+
+/// Find the tickets referenced by commits reachable from `from` but not from `hide`.
+fn get_exclusive_tickets(
+    repo: &Repository,
+    from: Oid,
+    hide: Oid,
+    trackers: &Trackers,
+    max_age: std::time::Duration,
+    args: &Args,
+) -> Result<BTreeSet<String>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    if args.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.push(from)?;
+    revwalk.hide(hide)?;
+
+    let mut tickets = BTreeSet::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if !commit_is_within_duration(&commit, max_age) || !args.includes_commit(&commit) {
+            continue;
+        }
+        let message = commit.message().unwrap_or_default();
+        tickets.extend(
+            trackers
+                .find_tickets(message)
+                .into_iter()
+                .map(|ticket| ticket.id),
+        );
+    }
+    Ok(tickets)
+}
+
+pub fn get_backport_reports(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    trackers: &Trackers,
+    args: &Args,
+    backports_args: &BackportsArgs,
+) -> Result<Vec<BackportReport>, TagCommitsError> {
+    let mainline_candidates = match &backports_args.mainline {
+        Some(mainline) => vec![mainline.clone()],
+        None => vec!["main".to_owned(), "master".to_owned()],
+    };
+    let Some((mainline, mainline_id)) = mainline_candidates
+        .iter()
+        .find_map(|name| resolve_ref(repo, name).map(|id| (name.clone(), id)))
+    else {
+        return Err(TagCommitsError::NoBranches(mainline_candidates));
+    };
+
+    let mut reports = Vec::new();
+    for release in &backports_args.releases {
+        let release_id =
+            resolve_ref(repo, release).ok_or(TagCommitsError::RefNotFound(release.clone()))?;
+
+        let release_tickets =
+            get_exclusive_tickets(repo, release_id, mainline_id, trackers, max_age, args)?;
+        let mainline_tickets =
+            get_exclusive_tickets(repo, mainline_id, release_id, trackers, max_age, args)?;
+
+        reports.push(BackportReport {
+            release: release.clone(),
+            mainline: mainline.clone(),
+            backported: release_tickets
+                .intersection(&mainline_tickets)
+                .cloned()
+                .collect(),
+            mainline_only: mainline_tickets
+                .difference(&release_tickets)
+                .cloned()
+                .collect(),
+            not_merged_back: release_tickets
+                .difference(&mainline_tickets)
+                .cloned()
+                .collect(),
+        });
+    }
+    Ok(reports)
+}
+
+fn print_section(title: &str, tickets: &BTreeSet<String>, quiet: bool) {
+    if tickets.is_empty() {
+        if !quiet {
+            println!("  {}", format!("{} (none)", title).dimmed());
+        }
+        return;
+    }
+
+    println!("  {}", title.bold());
+    for ticket in tickets {
+        println!("    {}", ticket.bold().italic());
+    }
+}
+
+/// Print each comparison, leaving out sections without tickets if `quiet`.
+pub fn print_backport_reports(reports: &[BackportReport], quiet: bool) {
+    for report in reports {
+        println!(
+            "{}",
+            format!("{} vs {}", report.release, report.mainline)
+                .green()
+                .bold()
+        );
+        print_section("Backported", &report.backported, quiet);
+        print_section(
+            &format!("Only on {}", report.mainline),
+            &report.mainline_only,
+            quiet,
+        );
+        print_section(
+            &format!("Not merged back to {}", report.mainline),
+            &report.not_merged_back,
+            quiet,
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use git2::{Oid, Repository};
+
+use crate::git::CommitDepthInfo;
+
+const CACHE_VERSION: u32 = 3;
+const CACHE_FILE: &str = "releases.json";
+
+/// An on-disk cache of the commits in each release, keyed by the OID of the tagged commit, so
+/// repeated runs only need to walk the history of new tags.
+#[derive(Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    releases: HashMap<String, CachedRelease>,
+    #[serde(skip)]
+    modified: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedRelease {
+    /// Identifies the settings and boundaries used to walk the release, which must match for the
+    /// cached commits to be reused.
+    key: String,
+    /// The SHA and depth of each commit in the release.
+    commits: Vec<(String, usize)>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Cache {
+            version: CACHE_VERSION,
+            releases: HashMap::new(),
+            modified: false,
+        }
+    }
+}
+
+fn cache_dir(repo: &Repository) -> PathBuf {
+    repo.path().join("rels-cache")
+}
+
+/// The key identifying how a release was walked: the boundaries it hid, depth, and traversal mode.
+pub fn release_key(boundaries: &[Oid], depth: usize, first_parent: bool) -> String {
+    let mut sorted_boundaries = boundaries.to_vec();
+    sorted_boundaries.sort();
+
+    // The key is saved, so is hashed with an algorithm which is the same in every build
+    let mut hasher = Sha256::new();
+    for boundary in &sorted_boundaries {
+        hasher.update(boundary.as_bytes());
+    }
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("{}:{}:{}", depth, first_parent, hash)
+}
+
+impl Cache {
+    /// Load the cache for a repository. A missing or unreadable cache is treated as empty.
+    pub fn load(repo: &Repository) -> Cache {
+        fs::read(cache_dir(repo).join(CACHE_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<Cache>(&contents).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// Save the cache if it has changed, dropping releases whose commit is no longer tagged.
+    pub fn save(&mut self, repo: &Repository, tagged: &HashSet<Oid>) -> std::io::Result<()> {
+        let tagged = tagged
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<HashSet<_>>();
+        let count = self.releases.len();
+        self.releases.retain(|id, _| tagged.contains(id));
+        if !self.modified && count == self.releases.len() {
+            return Ok(());
+        }
+
+        let dir = cache_dir(repo);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(CACHE_FILE), serde_json::to_vec(self)?)
+    }
+
+    /// Get the cached commits of a release, if they were walked with the same key.
+    pub fn get(&self, id: Oid, key: &str) -> Option<Vec<CommitDepthInfo>> {
+        let release = self.releases.get(&id.to_string())?;
+        if release.key != key {
+            return None;
+        }
+
+        release
+            .commits
+            .iter()
+            .map(|(sha, depth)| {
+                Some(CommitDepthInfo {
+                    id: Oid::from_str(sha).ok()?,
+                    depth: *depth,
+                })
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, id: Oid, key: String, commits: &[CommitDepthInfo]) {
+        let commits = commits
+            .iter()
+            .map(|info| (info.id.to_string(), info.depth))
+            .collect();
+        self.releases
+            .insert(id.to_string(), CachedRelease { key, commits });
+        self.modified = true;
+    }
+}
+
+pub fn clear_cache(repo: &Repository) -> std::io::Result<()> {
+    let dir = cache_dir(repo);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    println!("{}", format!("Cleared cache at {}", dir.display()).green());
+    Ok(())
+}
+
+pub fn print_cache_stats(repo: &Repository) {
+    let dir = cache_dir(repo);
+    let path = dir.join(CACHE_FILE);
+    let Ok(metadata) = fs::metadata(&path) else {
+        println!("{}", format!("No cache at {}", dir.display()).dimmed());
+        return;
+    };
+
+    let cache = Cache::load(repo);
+    let commit_count: usize = cache
+        .releases
+        .values()
+        .map(|release| release.commits.len())
+        .sum();
+    println!("{}", dir.display().to_string().bold());
+    println!("  {: <16} {}", "releases", cache.releases.len());
+    println!("  {: <16} {}", "commits", commit_count);
+    println!("  {: <16} {} KiB", "size", metadata.len() / 1024);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(hex: &str) -> Oid {
+        Oid::from_str(hex).expect("the OID should be valid")
+    }
+
+    #[test]
+    fn release_keys_do_not_depend_on_the_order_of_boundaries() {
+        let first = oid("1111111111111111111111111111111111111111");
+        let second = oid("2222222222222222222222222222222222222222");
+
+        assert_eq!(
+            release_key(&[first, second], 10, false),
+            release_key(&[second, first], 10, false)
+        );
+    }
+
+    #[test]
+    fn release_keys_differ_by_boundaries_depth_and_traversal_mode() {
+        let first = oid("1111111111111111111111111111111111111111");
+        let second = oid("2222222222222222222222222222222222222222");
+        let key = release_key(&[first], 10, false);
+
+        assert_ne!(key, release_key(&[first, second], 10, false));
+        assert_ne!(key, release_key(&[second], 10, false));
+        assert_ne!(key, release_key(&[first], 0, false));
+        assert_ne!(key, release_key(&[first], 10, true));
+    }
+
+    #[test]
+    fn cached_commits_are_only_reused_with_the_same_key() {
+        let tagged = oid("1111111111111111111111111111111111111111");
+        let mut cache = Cache::default();
+        cache.insert(
+            tagged,
+            "key".to_owned(),
+            &[CommitDepthInfo {
+                id: tagged,
+                depth: 0,
+            }],
+        );
+
+        let cached = cache
+            .get(tagged, "key")
+            .expect("the release should be cached");
+
+        assert_eq!(cached.len(), 1);
+        assert_eq!((cached[0].id, cached[0].depth), (tagged, 0));
+        assert!(cache.get(tagged, "other key").is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate};
+use colored::Colorize;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{
+    cli::{OutputFormat, Period},
+    metrics::{now, releases_within, window_start},
+    releases::TagCommits,
+};
+
+/// The widest a bar in the chart can be, in characters.
+const MAX_BAR_WIDTH: usize = 40;
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The number of releases tagged in a week or month.
+#[derive(Serialize)]
+pub struct PeriodCount {
+    /// The first day of the period, e.g. `2024-03-01`
+    pub start: String,
+    pub releases: usize,
+}
+
+/// The UTC date of a Unix timestamp.
+pub fn date(time: i64) -> NaiveDate {
+    DateTime::from_timestamp(time, 0)
+        .unwrap_or_default()
+        .date_naive()
+}
+
+/// The first day of the period containing a date.
+pub fn period_start(date: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Day => date,
+        Period::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+        Period::Month => date.with_day(1).unwrap_or(date),
+    }
+}
+
+/// The first day of each period within `--age` of now, oldest first.
+pub fn periods(max_age: Duration, period: Period) -> Vec<NaiveDate> {
+    let end = date(now());
+    let mut start = period_start(date(window_start(max_age)), period);
+    let mut starts = Vec::new();
+    while start <= end {
+        starts.push(start);
+        start = next_period(start, period);
+    }
+    starts
+}
+
+fn next_period(start: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Day => start + Days::new(1),
+        Period::Week => start + Days::new(7),
+        Period::Month => start + Months::new(1),
+    }
+}
+
+/// Count the releases tagged in each period within `--age` of now, including periods without
+/// any releases, oldest first.
+pub fn get_cadence(
+    tag_commits: &TagCommits,
+    max_age: Duration,
+    period: Period,
+) -> Vec<PeriodCount> {
+    let release_starts = releases_within(tag_commits, max_age)
+        .into_iter()
+        .map(|tag_name| period_start(date(tag_commits.tag_times[tag_name]), period))
+        .collect::<Vec<_>>();
+
+    periods(max_age, period)
+        .into_iter()
+        .map(|start| PeriodCount {
+            start: start.format("%Y-%m-%d").to_string(),
+            releases: release_starts.iter().filter(|date| **date == start).count(),
+        })
+        .collect()
+}
+
+/// The label of a period: its first day, or the month without its day.
+pub fn format_period(start: &str, period: Period) -> &str {
+    match period {
+        Period::Day | Period::Week => start,
+        Period::Month => start.get(..7).unwrap_or(start),
+    }
+}
+
+/// Print a bar for each period, followed by a sparkline of the whole window.
+pub fn print_cadence(counts: &[PeriodCount], period: Period, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(counts).expect("cadence should serialize to JSON")
+        );
+        return;
+    }
+
+    let most = counts
+        .iter()
+        .map(|count| count.releases)
+        .max()
+        .unwrap_or_default();
+    for count in counts {
+        let width = if most > MAX_BAR_WIDTH {
+            (count.releases * MAX_BAR_WIDTH).div_ceil(most)
+        } else {
+            count.releases
+        };
+        println!(
+            "{}  {} {}",
+            format_period(&count.start, period).dimmed(),
+            "█".repeat(width).green(),
+            count.releases
+        );
+    }
+
+    let sparkline = counts
+        .iter()
+        .map(|count| match most {
+            0 => SPARKS[0],
+            _ => SPARKS[count.releases * (SPARKS.len() - 1) / most],
+        })
+        .collect::<String>();
+    let total = counts.iter().map(|count| count.releases).sum::<usize>();
+    println!();
+    println!("{} {} releases", sparkline.green(), total);
+}
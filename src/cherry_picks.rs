@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use git2::{Commit, Oid, Repository};
+
+use crate::releases::TagCommits;
+
+/// Compute a `git patch-id` style identifier for the change introduced by a commit, which stays
+/// the same when the commit is cherry-picked. Merge commits and empty commits have no patch ID.
+fn patch_id(repo: &Repository, commit: &Commit) -> Result<Option<Oid>, git2::Error> {
+    if commit.parent_count() > 1 {
+        return Ok(None);
+    }
+
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+    diff.patchid(None).map(Some)
+}
+
+/// Link commits introducing the same change in different releases, so that a cherry-picked
+/// change isn't counted as unrelated work. The copy in the earliest release is treated as the
+/// original, and every other copy is marked as a cherry-pick.
+pub fn link_cherry_picks(
+    repo: &Repository,
+    tag_commits: &mut TagCommits,
+) -> Result<(), git2::Error> {
+    let mut commits_by_patch_id: HashMap<Oid, Vec<Oid>> = HashMap::new();
+    for id in tag_commits.commit_to_tag.keys() {
+        if let Some(patch_id) = patch_id(repo, &repo.find_commit(*id)?)? {
+            commits_by_patch_id.entry(patch_id).or_default().push(*id);
+        }
+    }
+
+    for ids in commits_by_patch_id
+        .into_values()
+        .filter(|ids| ids.len() > 1)
+    {
+        let releases = ids
+            .iter()
+            .map(|id| tag_commits.commit_to_tag[id].tag_name.clone())
+            .collect::<Vec<_>>();
+        let Some(original) = ids
+            .iter()
+            .min_by_key(|id| tag_commits.release_order(&tag_commits.commit_to_tag[*id].tag_name))
+            .copied()
+        else {
+            continue;
+        };
+
+        for id in &ids {
+            let Some(info) = tag_commits.commit_to_tag.get_mut(id) else {
+                continue;
+            };
+            let mut other_releases = releases
+                .iter()
+                .filter(|tag_name| **tag_name != info.tag_name)
+                .cloned()
+                .collect::<Vec<_>>();
+            other_releases.sort();
+            other_releases.dedup();
+
+            info.cherry_picked_in = other_releases;
+            info.is_cherry_pick = *id != original;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1224 @@
+use clap::{builder::FalseyValueParser, Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCompleter;
+use git2::Commit;
+use regex::Regex;
+use std::{ops::Range, path::PathBuf, sync::OnceLock, time::Duration};
+use ureq::Proxy;
+
+use crate::{
+    auth::AuthService,
+    completions::{complete_refs, CompletionShell},
+    environments::{parse_environment, Environment},
+    exit_codes::EXIT_CODES_HELP,
+    forge_api::Forge,
+    http::parse_proxy,
+    presets::TrackerPreset,
+    regex_engine::RegexEngine,
+    releases::{InvalidInput, TagCommitsError},
+    watch::parse_interval,
+};
+
+/// How to print the output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Coloured text for reading in a terminal
+    #[default]
+    Text,
+    /// JSON for scripts, with errors also printed as JSON on stderr
+    Json,
+    /// JSON Lines, with an object on each line for each commit and the tag of its release,
+    /// printed as each release is found
+    Jsonl,
+    /// Markdown, e.g. for release notes or a changelog
+    Markdown,
+    /// An iCalendar feed with an event for each release, for `rels feed`
+    Ics,
+    /// A markdown summary of the releases, appended to the job summary of GitHub Actions in
+    /// GITHUB_STEP_SUMMARY, or printed outside of Actions
+    GhSummary,
+    /// A JUnit XML report of `rels lint`, for the test reports of CI systems, e.g. GitLab's
+    /// artifacts:reports:junit
+    Junit,
+    /// TeamCity service messages: build problems for `rels lint` failures, and build statistics
+    /// for `rels metrics`
+    Teamcity,
+}
+
+impl OutputFormat {
+    /// The name of the format, as given to `--format`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Ics => "ics",
+            OutputFormat::GhSummary => "gh-summary",
+            OutputFormat::Junit => "junit",
+            OutputFormat::Teamcity => "teamcity",
+        }
+    }
+}
+
+/// Details of looked up tickets which can be shown with `--show`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TicketColumn {
+    /// The kind of ticket, e.g. Bug or Story
+    Type,
+    /// Who the ticket is assigned to
+    Assignee,
+}
+
+/// The columns of each commit which can be chosen with `--columns`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CommitColumn {
+    /// The release containing the commit
+    Tag,
+    /// The abbreviated commit hash
+    Sha,
+    /// The date the commit was made
+    Date,
+    Author,
+    /// The tickets referenced by the commit
+    Tickets,
+    /// The commit's release note, which is its summary unless it has a Release-Note trailer or
+    /// pull request title
+    Subject,
+    /// The links to the tickets
+    Url,
+    /// The link to the pull request the commit was merged from
+    Pr,
+    /// How many commits away from the tag the commit was found
+    Depth,
+}
+
+/// How releases and commits must match when `--filter` is given more than once.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum FilterMode {
+    /// Match any of the filters
+    #[default]
+    Any,
+    /// Match every filter
+    All,
+}
+
+/// The periods `rels cadence` and `rels timeline` group releases into.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Period {
+    /// Days, in UTC
+    Day,
+    /// Weeks starting on Monday
+    #[default]
+    Week,
+    /// Calendar months
+    Month,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about, after_help = EXIT_CODES_HELP)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value_t = 10,
+        env = "RELS_DEPTH",
+        help = "Maximum depth to search commits from tags. 0 searches until the previous tag (or the root commit)."
+    )]
+    pub depth: usize,
+
+    #[arg(
+        short = 't',
+        long,
+        global = true,
+        default_value = "1y",
+        env = "RELS_AGE",
+        help = "The maximum age ([t]ime) of tags to show, in the format 1y 2mon 3w 4d 5h 6m 7s"
+    )]
+    pub age: String,
+
+    #[arg(
+        short = 'u',
+        long,
+        global = true,
+        env = "RELS_JIRA_URL",
+        help = "The base URL for JIRA tickets, e.g. `https://jira.example.com/browse/`. If not specified, JIRA ticket numbers will not be linked. If {ticket} is included in the URL, it will be replaced with the ticket number, otherwise it will be appended to end of the URL. Can be given multiple times to link each --jira-regex in the same position to a different URL."
+    )]
+    pub jira_url: Vec<String>,
+
+    #[arg(
+        short = 'r',
+        long,
+        global = true,
+        env = "RELS_JIRA_REGEX",
+        help = "The regex to use to match JIRA ticket numbers [default: [A-Z]+-[0-9]+]. Can be given multiple times to match several patterns, each as a separate tracker."
+    )]
+    pub jira_regex: Vec<String>,
+
+    #[arg(
+        short = 'i',
+        long,
+        global = true,
+        env = "RELS_IGNORE_CASE",
+        value_parser = FalseyValueParser::new(),
+        help = "Match ticket regexes case-insensitively, e.g. abc-123 for ABC-123, showing and linking matched tickets in uppercase."
+    )]
+    pub ignore_case: bool,
+
+    #[arg(
+        long = "ignore-ticket",
+        global = true,
+        env = "RELS_IGNORE_TICKET",
+        help = "A placeholder ticket to leave out of ticket lists and links, e.g. NO-TICKET, in addition to ignored_tickets in the config file. Can be given multiple times."
+    )]
+    pub ignored_tickets: Vec<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = RegexEngine::Standard,
+        env = "RELS_REGEX_ENGINE",
+        help = "The regex engine for ticket regexes. fancy supports lookaround and backreferences, e.g. to avoid matching inside URLs, but can be slow for some patterns."
+    )]
+    pub regex_engine: RegexEngine,
+
+    #[arg(
+        long = "tracker",
+        global = true,
+        value_enum,
+        env = "RELS_TRACKER",
+        help = "Built-in issue trackers to find ticket references for, in addition to any in the config file, or to Jira tickets if the config file has no trackers. Can be given multiple times."
+    )]
+    pub trackers: Vec<TrackerPreset>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_LOOKUP",
+        value_parser = FalseyValueParser::new(),
+        help = "Look up the title and status of tickets from the APIs of trackers which support it, e.g. Jira with JIRA_API_TOKEN set."
+    )]
+    pub lookup: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_FETCH_PRS",
+        value_parser = FalseyValueParser::new(),
+        help = "Find tickets for squash-merged commits without any, e.g. \"Squashed commit (#482)\", in the title and description of their pull request, from GitHub with GITHUB_TOKEN or GitLab with GITLAB_TOKEN set."
+    )]
+    pub fetch_prs: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_SHOW_PR",
+        value_parser = FalseyValueParser::new(),
+        help = "Link to the pull or merge request each commit was merged from, on the GitHub or GitLab origin remote."
+    )]
+    pub show_pr: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_CREDITS",
+        value_parser = FalseyValueParser::new(),
+        help = "Show who wrote each commit, including co-authors from Co-authored-by trailers, and who reviewed it from Reviewed-by trailers."
+    )]
+    pub credits: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_CONTRIBUTORS",
+        value_parser = FalseyValueParser::new(),
+        help = "List the contributors to each release after its commits: the authors and co-authors of its commits, with how many commits each contributed to."
+    )]
+    pub contributors: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_DIFFSTAT",
+        value_parser = FalseyValueParser::new(),
+        help = "Show the files changed, insertions and deletions in each release since the release it follows."
+    )]
+    pub diffstat: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_DIRSTAT",
+        value_parser = FalseyValueParser::new(),
+        help = "Also break down the lines changed in each release by top-level directory, like git diff --dirstat. Implies --diffstat."
+    )]
+    pub dirstat: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_COMPARE_LINKS",
+        value_parser = FalseyValueParser::new(),
+        help = "Link to the diff of each release against the release it follows, on the GitHub, GitLab or Bitbucket origin remote."
+    )]
+    pub compare_links: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_ASSETS",
+        value_parser = FalseyValueParser::new(),
+        help = "List the files published with each release on GitHub or GitLab, e.g. binaries uploaded by CI, using the token in GITHUB_TOKEN or GITLAB_TOKEN."
+    )]
+    pub assets: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_NOTES",
+        value_parser = FalseyValueParser::new(),
+        help = "Show the release note of each commit from its Release-Note trailer, or else the title of its pull request (with --fetch-prs) or its summary."
+    )]
+    pub notes: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_GIT_NOTES",
+        value_parser = FalseyValueParser::new(),
+        help = "Show the git notes attached to each release's tag and commits, e.g. deployment or QA sign-off details."
+    )]
+    pub git_notes: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "REF",
+        env = "RELS_NOTES_REF",
+        help = "The notes ref --git-notes reads, e.g. refs/notes/deployments or just deployments. Defaults to refs/notes/commits, as for git notes. Overrides notes_ref in the config file."
+    )]
+    pub notes_ref: Option<String>,
+
+    #[arg(
+        long = "environment",
+        global = true,
+        value_name = "NAME=REF",
+        value_parser = parse_environment,
+        value_delimiter = ',',
+        env = "RELS_ENVIRONMENT",
+        help = "An environment releases are deployed to, and the branch or tag pointing at what is deployed there, e.g. prod=deployed/prod, to show which environments each release has reached. Can be given multiple times, in the order releases are deployed. Overrides environments in the config file."
+    )]
+    pub environments: Vec<Environment>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        requires = "lookup",
+        env = "RELS_SHOW",
+        help = "Extra details of looked up tickets to show, e.g. type,assignee to tell bug fixes from features and name their owners. JSON output always includes them. Needs --lookup."
+    )]
+    pub show: Vec<TicketColumn>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        env = "RELS_COLUMNS",
+        help = "The columns to show for each commit, in order, e.g. sha,date,author,tickets,subject. Only these columns are shown, instead of the tickets and any links."
+    )]
+    pub columns: Vec<CommitColumn>,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "columns",
+        env = "RELS_SHOW_DEPTH",
+        value_parser = FalseyValueParser::new(),
+        help = "Show how many commits away from the tag each commit was found, to help tune --depth."
+    )]
+    pub show_depth: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_GRAPH",
+        value_parser = FalseyValueParser::new(),
+        help = "Draw the ancestry of the commits in each release, like git log --graph, to show which branch each change came in on."
+    )]
+    pub graph: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_parser = parse_proxy,
+        env = "RELS_PROXY",
+        help = "The proxy for requests to tracker and forge APIs, e.g. http://proxy.example.com:8080. Defaults to HTTPS_PROXY, HTTP_PROXY or ALL_PROXY, and hosts in NO_PROXY are always reached directly."
+    )]
+    pub proxy: Option<Proxy>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_CA_CERT",
+        help = "A PEM file of certificates to trust for HTTPS requests to tracker and forge APIs, in addition to the usual ones, e.g. for a self-hosted Jira using an internal CA."
+    )]
+    pub ca_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_INSECURE",
+        value_parser = FalseyValueParser::new(),
+        help = "Don't verify the certificates of tracker and forge APIs. This lets anyone on the network read API keys, so only use it to diagnose certificate problems, and prefer --ca-cert."
+    )]
+    pub insecure: bool,
+
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 3,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "RELS_MAX_ATTEMPTS",
+        help = "How many times to try requests to tracker and forge APIs which fail with a rate limit, server error, or network problem, waiting longer between each attempt. 1 doesn't retry."
+    )]
+    pub max_attempts: u32,
+
+    #[cfg(feature = "async")]
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 8,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "RELS_MAX_CONCURRENT_REQUESTS",
+        help = "The most tickets to look up from tracker APIs at the same time with --lookup. Lower it if the API's rate limit is hit."
+    )]
+    pub max_concurrent_requests: u32,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_CONFIG",
+        help = "The config file to use, which can configure multiple issue trackers. Defaults to .rels.toml in the root of the repository."
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_PROFILE",
+        help = "The profile in the config file to use, e.g. frontend for [profile.frontend], which can set its own trackers and tag pattern."
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_TAG_PATTERN",
+        help = "Only treat tags matching this pattern as releases, where * matches any text, e.g. frontend-v*. Overrides tag_pattern in the config file."
+    )]
+    pub tag_pattern: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "KEY",
+        env = "RELS_RELEASE_NOTE_TRAILER",
+        help = "The commit trailer holding a commit's release note, which --notes shows instead of its summary. Defaults to Release-Note. Overrides release_note_trailer in the config file."
+    )]
+    pub release_note_trailer: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "RELS_FORGE",
+        help = "The forge hosting the origin remote, for self-hosted forges whose host name doesn't say, e.g. gitlab for git.example.com. Overrides forge in the config file."
+    )]
+    pub forge: Option<Forge>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        env = "RELS_FORMAT",
+        help = "The output format for releases. With json, errors are also printed as JSON on stderr."
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        short = 'z',
+        long,
+        global = true,
+        conflicts_with = "format",
+        env = "RELS_PORCELAIN",
+        value_parser = FalseyValueParser::new(),
+        help = "Print a record for each commit for scripts, ended by a NUL byte, with the fields tag, sha, time, author, tickets and release note in that order, separated by tabs."
+    )]
+    pub porcelain: bool,
+
+    #[arg(
+        short,
+        long,
+        env = "RELS_ALL",
+        value_parser = FalseyValueParser::new(),
+        help = "Show all commits, not just those matching the JIRA regex."
+    )]
+    pub all: bool,
+
+    #[arg(
+        short,
+        long = "filter",
+        value_name = "FILTER",
+        env = "RELS_FILTER",
+        help = "Filter by tag name or commit message. Can be given multiple times, see --filter-mode."
+    )]
+    pub filters: Vec<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = FilterMode::Any,
+        env = "RELS_FILTER_MODE",
+        help = "Whether releases and commits must match any of the --filter values, or all of them."
+    )]
+    pub filter_mode: FilterMode,
+
+    #[arg(
+        long = "exclude",
+        value_name = "TEXT",
+        env = "RELS_EXCLUDE",
+        help = "Leave out releases whose tag name contains this, and commits whose tickets, message, release note or author contain it, e.g. dependabot. Can be given multiple times."
+    )]
+    pub excludes: Vec<String>,
+
+    #[arg(
+        long = "ticket-status",
+        value_name = "STATUS",
+        value_delimiter = ',',
+        requires = "lookup",
+        env = "RELS_TICKET_STATUS",
+        help = "Only show commits with a ticket in one of these statuses, e.g. \"Done,Closed\", ignoring case. The status categories to-do, in-progress, and done can also be given. Needs --lookup."
+    )]
+    pub ticket_statuses: Vec<String>,
+
+    #[arg(
+        long = "exclude-ticket-status",
+        value_name = "STATUS",
+        value_delimiter = ',',
+        requires = "lookup",
+        env = "RELS_EXCLUDE_TICKET_STATUS",
+        help = "Leave out commits with a ticket in any of these statuses, e.g. done to find unfinished tickets before signing off a release. Takes the same statuses as --ticket-status. Needs --lookup."
+    )]
+    pub excluded_ticket_statuses: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "COMMIT",
+        num_args = 0..=1,
+        env = "RELS_EXPLAIN",
+        add = ArgValueCompleter::new(complete_refs),
+        help = "Instead of listing releases, explain why each commit up to the previous tag was or wasn't included, e.g. because it is beyond --depth or has no ticket. Give a commit to explain just that commit."
+    )]
+    pub explain: Option<Option<String>>,
+
+    #[arg(
+        long,
+        env = "RELS_FIRST_RELEASE",
+        value_parser = FalseyValueParser::new(),
+        help = "Annotate each ticket with the earliest release containing it, e.g. for writing \"fixed in\" notes."
+    )]
+    pub first_release: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_CHERRY_PICKS",
+        value_parser = FalseyValueParser::new(),
+        help = "Detect cherry-picked commits by comparing patch IDs, linking copies of the same change across releases."
+    )]
+    pub cherry_picks: bool,
+
+    #[arg(
+        long,
+        env = "RELS_EXCLUDE_REVERTS",
+        value_parser = FalseyValueParser::new(),
+        help = "Exclude commits which were reverted within the same release, along with their reverts, since the net change is nothing."
+    )]
+    pub exclude_reverts: bool,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "merges_only",
+        env = "RELS_NO_MERGES",
+        value_parser = FalseyValueParser::new(),
+        help = "Exclude merge commits. Their parents are still searched."
+    )]
+    pub no_merges: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_MERGES_ONLY",
+        value_parser = FalseyValueParser::new(),
+        help = "Only include merge commits."
+    )]
+    pub merges_only: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_FIRST_PARENT",
+        value_parser = FalseyValueParser::new(),
+        help = "Only follow the first parent of merge commits, showing what was merged to the main branch rather than descending into feature branches."
+    )]
+    pub first_parent: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_TIMINGS",
+        value_parser = FalseyValueParser::new(),
+        help = "Print how long each phase of the run took, and how much of it was spent matching ticket regexes, to stderr."
+    )]
+    pub timings: bool,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        env = "RELS_VERBOSE",
+        help = "Log what rels is doing to stderr, such as why commits were skipped. Give twice for more detail, or three times for every commit. RUST_LOG overrides this."
+    )]
+    pub verbose: u8,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "RELS_QUIET",
+        value_parser = FalseyValueParser::new(),
+        help = "Only print results, leaving out releases without entries, headers, and messages such as \"No tickets span multiple releases\"."
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["graph", "columns", "show_depth"],
+        env = "RELS_SUMMARY",
+        value_parser = FalseyValueParser::new(),
+        help = "Print a line for each release with its date and how many commits and different tickets it has, instead of listing its commits. Other formats, such as json, still list every commit."
+    )]
+    pub summary: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        env = "RELS_MAX_COMMITS",
+        help = "Only show the first N commits of each release, followed by how many more there are. Other formats, such as json, still list every commit."
+    )]
+    pub max_commits: Option<usize>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RELS_NO_CACHE",
+        value_parser = FalseyValueParser::new(),
+        help = "Don't read or write the cache of commits in each release."
+    )]
+    pub no_cache: bool,
+
+    #[arg(
+        long,
+        env = "RELS_SINCE_LAST_RUN",
+        value_parser = FalseyValueParser::new(),
+        help = "Only show releases added since the last run with this flag, e.g. for posting new releases from CI."
+    )]
+    pub since_last_run: bool,
+
+    #[arg(
+        long,
+        requires = "since_last_run",
+        env = "RELS_STATE_FILE",
+        help = "Where to store the releases seen by --since-last-run. Defaults to a file in the .git directory, which CI jobs may need to change so it persists between runs."
+    )]
+    pub state_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with = "since_last_run",
+        env = "RELS_WATCH",
+        value_parser = FalseyValueParser::new(),
+        help = "Keep showing the releases, refreshing them every --interval or as soon as a tag is created or moved, e.g. on a release dashboard. Stop with Ctrl-C."
+    )]
+    pub watch: bool,
+
+    #[arg(
+        long,
+        requires = "watch",
+        default_value = "5m",
+        value_parser = parse_interval,
+        env = "RELS_INTERVAL",
+        help = "How often --watch refreshes the releases, in the format 1h 2m 3s."
+    )]
+    pub interval: Duration,
+
+    #[arg(
+        long,
+        requires = "watch",
+        env = "RELS_FETCH",
+        value_parser = FalseyValueParser::new(),
+        help = "Run git fetch --tags before each refresh of --watch, to show releases tagged elsewhere."
+    )]
+    pub fetch: bool,
+
+    #[arg(
+        long,
+        env = "RELS_HOOKS",
+        value_parser = FalseyValueParser::new(),
+        help = "Run the pre and post commands in the [hooks] section of the config file before and after listing the releases."
+    )]
+    pub hooks: bool,
+
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        conflicts_with = "porcelain",
+        env = "RELS_OUTPUT",
+        help = "Write the releases to a file instead of printing them, as markdown, json, jsonl or html depending on its extension (or --format otherwise). Directories are created as needed."
+    )]
+    pub output: Option<PathBuf>,
+}
+
+/// The parts of a duration string, e.g. `1y` and `2monx` for `1y 2monx`, with their positions.
+fn duration_segments(duration: &str) -> Vec<Range<usize>> {
+    static SEGMENT_REGEX: OnceLock<Regex> = OnceLock::new();
+    SEGMENT_REGEX
+        .get_or_init(|| {
+            Regex::new(r"[0-9.]+\s*[^\s0-9.]*|[^\s0-9.]+").expect("segment regex should be valid")
+        })
+        .find_iter(duration)
+        .map(|segment| segment.range())
+        .collect()
+}
+
+/// The commit trailer holding release notes if no other is configured.
+const DEFAULT_RELEASE_NOTE_TRAILER: &str = "Release-Note";
+
+/// The notes ref git uses if no other is configured.
+const DEFAULT_NOTES_REF: &str = "refs/notes/commits";
+
+impl Args {
+    /// The maximum age of tags from `--age`. If it is invalid, the error points at the first
+    /// part which isn't a valid duration.
+    pub fn max_age(&self) -> Result<Duration, TagCommitsError> {
+        duration_str::parse(&self.age).map_err(|_| {
+            let span = duration_segments(&self.age)
+                .into_iter()
+                .find(|segment| duration_str::parse(&self.age[segment.clone()]).is_err());
+            TagCommitsError::InvalidDuration(InvalidInput {
+                input: self.age.clone(),
+                message: "expected a number followed by a unit such as y, mon, w, d, h, m, or s"
+                    .to_owned(),
+                span,
+            })
+        })
+    }
+
+    /// Whether the filters match according to `--filter-mode`, given whether a single filter
+    /// matches. Anything matches when there are no filters.
+    pub fn filters_match(&self, matches: impl Fn(&str) -> bool) -> bool {
+        match self.filter_mode {
+            FilterMode::Any => {
+                self.filters.is_empty() || self.filters.iter().any(|filter| matches(filter))
+            }
+            FilterMode::All => self.filters.iter().all(|filter| matches(filter)),
+        }
+    }
+
+    /// Whether releases can be printed as soon as they have been searched, which isn't possible
+    /// for options which need every release to be known first, or for formats such as JSON
+    /// which are a single document.
+    pub fn can_stream(&self) -> bool {
+        !(self.first_release || self.cherry_picks || self.summary)
+            && matches!(self.format, OutputFormat::Text | OutputFormat::Jsonl)
+            && self.output.is_none()
+    }
+
+    /// Whether to show the changes in each release, with `--diffstat` or `--dirstat`.
+    pub fn show_diffstat(&self) -> bool {
+        self.diffstat || self.dirstat
+    }
+
+    /// The key of the commit trailer holding release notes, e.g. `Release-Note`.
+    pub fn release_note_trailer(&self) -> &str {
+        self.release_note_trailer
+            .as_deref()
+            .unwrap_or(DEFAULT_RELEASE_NOTE_TRAILER)
+    }
+
+    /// The notes ref read by `--git-notes`, e.g. `refs/notes/commits`.
+    pub fn notes_ref(&self) -> &str {
+        self.notes_ref.as_deref().unwrap_or(DEFAULT_NOTES_REF)
+    }
+
+    /// Whether a commit should be included based on the merge commit flags.
+    pub fn includes_commit(&self, commit: &Commit) -> bool {
+        let is_merge = commit.parent_count() > 1;
+        !(self.no_merges && is_merge || self.merges_only && !is_merge)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List commits on mainline branches that are not contained in any release
+    Orphans(OrphansArgs),
+    /// List tickets whose commits are split across more than one release
+    Spanning,
+    /// Compare release branches or tags against mainline to track backported tickets
+    Backports(BackportsArgs),
+    /// Check that commits reference a ticket and follow the lint rules in the config, exiting
+    /// with an error if any don't
+    Lint(LintArgs),
+    /// Report the DORA metrics which can be measured from releases within --age: deployment
+    /// frequency, and lead time for changes from each commit to its release
+    Metrics,
+    /// Summarize each release within --age: its commits, tickets and authors, and the time
+    /// since the previous release, with totals across them all
+    Stats,
+    /// Chart the number of releases in each week or month within --age, to see trends in the
+    /// release cadence at a glance
+    Cadence(CadenceArgs),
+    /// Print a feed of the releases, described by the release notes of their commits. With
+    /// --format ics this is a calendar with an event for each release, for a team calendar.
+    Feed,
+    /// Print the release notes of each release as markdown, as --format markdown does, or write
+    /// them to a file per release with --split-dir
+    Notes(NotesArgs),
+    /// Lay out the releases within --age chronologically, one line per day or week, to show
+    /// gaps and bursts in shipping
+    Timeline(TimelineArgs),
+    /// Print a JQL query for the Jira tickets in a release, to continue triage in Jira
+    Jql(JqlArgs),
+    /// Check the releases published on the GitHub or GitLab origin remote against the tags
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Serve the releases over HTTP as JSON, or HTML for browsers, for dashboards and chat bots:
+    /// /releases, /releases/<tag>, and /tickets/<id> for the releases containing a ticket
+    Serve(ServeArgs),
+    /// Serve the releases to coding assistants over the Model Context Protocol on stdin and
+    /// stdout, with tools to list releases, find the releases containing a ticket, compare tags,
+    /// and list unreleased commits
+    Mcp,
+    /// Publish releases on the forge hosting the origin remote, with their release notes
+    #[command(subcommand)]
+    Publish(PublishCommand),
+    /// Manage Jira versions for releases
+    #[command(subcommand)]
+    Jira(JiraCommand),
+    /// Manage the cache of commits in each release
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Print a script which completes arguments, including tag and branch names, e.g. for
+    /// `source <(rels completions bash)` in ~/.bashrc
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+    /// Store or remove API tokens in the OS keyring, instead of environment variables
+    #[command(subcommand)]
+    Auth(AuthCommand),
+    /// Create or show the config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Check that rels works in this repository, e.g. that there are tags, the config is valid,
+    /// and API keys are accepted, suggesting how to fix any problems
+    Doctor,
+    /// Replace rels with the latest release from GitHub, if there is a newer one
+    SelfUpdate {
+        #[arg(
+            long,
+            env = "RELS_CHECK",
+            value_parser = FalseyValueParser::new(),
+            help = "Only check whether there is a newer release."
+        )]
+        check: bool,
+    },
+    /// Print the JSON Schema of the releases printed by --format json, e.g. for validating
+    /// them or generating types
+    Schema,
+    /// Print the man page, e.g. for `rels man | man -l -`
+    Man {
+        #[arg(
+            long,
+            env = "RELS_OUT_DIR",
+            help = "Write a man page for rels and each subcommand to this directory instead, e.g. for packaging."
+        )]
+        out_dir: Option<PathBuf>,
+    },
+    /// Run a plugin, an executable named rels-<name> on the PATH, with the releases on stdin as
+    /// JSON
+    #[command(external_subcommand)]
+    Plugin(Vec<String>),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Delete the cache
+    Clear,
+    /// Show the size of the cache
+    Stats,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthCommand {
+    /// Store the API token for a service, read from the terminal (or stdin) if not given
+    Login {
+        #[arg(value_enum)]
+        service: AuthService,
+        #[arg(
+            long,
+            env = "RELS_TOKEN",
+            help = "The token to store. Prefer entering it when prompted, so it isn't saved in the shell history."
+        )]
+        token: Option<String>,
+    },
+    /// Remove the API token for a service
+    Logout {
+        #[arg(value_enum)]
+        service: AuthService,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuditCommand {
+    /// List the release tags within --age which have never been published as a release or
+    /// whose release has no notes, and published releases whose tags are missing locally, using
+    /// the token in GITHUB_TOKEN or GITLAB_TOKEN
+    Releases,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PublishCommand {
+    /// Create a release on GitHub for a tag, with notes generated as --format markdown prints
+    /// them, using the token in GITHUB_TOKEN
+    Github(GithubPublishArgs),
+    /// Create a release on GitLab for a tag, with notes generated as --format markdown prints
+    /// them, using the token in GITLAB_TOKEN
+    Gitlab(PublishArgs),
+    /// Create or update a page for each release in an Azure DevOps wiki, with notes generated
+    /// as --format markdown prints them, using the token in AZURE_DEVOPS_TOKEN
+    AzureWiki(AzureWikiArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PublishArgs {
+    #[arg(
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The tag of the release, e.g. v1.5.0, defaulting to the latest release"
+    )]
+    pub tag: Option<String>,
+    #[arg(
+        long = "asset",
+        value_name = "PATH[:NAME]",
+        env = "RELS_ASSET",
+        help = "A file to upload with the release, e.g. a build artifact, or a glob pattern like dist/*.tar.gz. A single file can be uploaded under another name given after a colon. Can be given multiple times."
+    )]
+    pub asset_files: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct GithubPublishArgs {
+    #[command(flatten)]
+    pub release: PublishArgs,
+    #[arg(
+        long,
+        env = "RELS_DRAFT",
+        value_parser = FalseyValueParser::new(),
+        help = "Create the release as a draft, so the notes can be reviewed and edited on GitHub before publishing it with --finalize."
+    )]
+    pub draft: bool,
+    #[arg(
+        long,
+        value_name = "TAG",
+        env = "RELS_FINALIZE",
+        conflicts_with_all = ["tag", "draft", "asset_files"],
+        add = ArgValueCompleter::new(complete_refs),
+        help = "Publish the draft release of a tag, once it has been reviewed."
+    )]
+    pub finalize: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AzureWikiArgs {
+    #[arg(
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The tag of a release to publish, e.g. v1.5.0, defaulting to every release"
+    )]
+    pub tag: Option<String>,
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "RELS_PROJECT_URL",
+        help = "The URL of the Azure DevOps project with the wiki, e.g. https://dev.azure.com/org/project. Defaults to the project of the origin remote."
+    )]
+    pub project_url: Option<String>,
+    #[arg(
+        long,
+        env = "RELS_WIKI",
+        help = "The name of the wiki. Defaults to the project wiki, which is named after the project with .wiki on the end."
+    )]
+    pub wiki: Option<String>,
+    #[arg(
+        long,
+        default_value = "/Releases",
+        env = "RELS_WIKI_PATH",
+        help = "The page to publish the page of each release under, which is created if it doesn't exist."
+    )]
+    pub path: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JiraCommand {
+    /// Create a version named after a release in each Jira project with tickets in it, and add
+    /// it to the fix versions of those tickets
+    CreateVersion {
+        #[arg(
+            add = ArgValueCompleter::new(complete_refs),
+            help = "The tag of the release, e.g. v1.5.0"
+        )]
+        tag: String,
+        #[arg(
+            long,
+            env = "RELS_RELEASED",
+            value_parser = FalseyValueParser::new(),
+            help = "Also mark the versions as released, on the date of the tag."
+        )]
+        released: bool,
+    },
+    /// Comment on each Jira ticket in a release, e.g. "Shipped in v1.5.0, see <release page>",
+    /// skipping tickets which already have the same comment
+    Comment {
+        #[arg(
+            add = ArgValueCompleter::new(complete_refs),
+            help = "The tag of the release, e.g. v1.5.0"
+        )]
+        tag: String,
+        #[arg(
+            long,
+            env = "RELS_COMMENT_TEMPLATE",
+            help = "The comment to post, where {tag} is replaced with the tag, {ticket} with the ticket, and {url} with the release's page on GitHub or GitLab. Defaults to \"Shipped in {tag}, see {url}\", or \"Shipped in {tag}\" if there isn't a release page."
+        )]
+        template: Option<String>,
+        #[arg(
+            long,
+            env = "RELS_JIRA_COMMENT_DRY_RUN",
+            value_parser = FalseyValueParser::new(),
+            help = "Print the comments which would be posted, without posting them."
+        )]
+        dry_run: bool,
+    },
+    /// Move each Jira ticket in a release to the release status configured for its project, e.g.
+    /// Released. The transitions must be previewed with --dry-run first.
+    Transition(TransitionArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TransitionArgs {
+    #[arg(
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The tag of the release, e.g. v1.5.0"
+    )]
+    pub tag: String,
+
+    #[arg(
+        long,
+        value_name = "STATUS",
+        env = "RELS_TO",
+        help = "The status to move every ticket to, instead of the release_status in the [jira] section of the config."
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        env = "RELS_JIRA_TRANSITION_DRY_RUN",
+        value_parser = FalseyValueParser::new(),
+        help = "Print the transitions which would be made, without making them. Required before the transitions can be made."
+    )]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write a commented config file to .rels.toml (or --config), filled in from the tracker
+    /// options given, e.g. --jira-url
+    Init {
+        #[arg(
+            long,
+            env = "RELS_FORCE",
+            value_parser = FalseyValueParser::new(),
+            help = "Overwrite the config file if it already exists."
+        )]
+        force: bool,
+    },
+    /// Print the configuration which applies, including presets and command line options, with
+    /// credentials in URLs redacted
+    Show,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct OrphansArgs {
+    #[arg(
+        short,
+        long = "branch",
+        default_values_t = ["main".to_owned(), "master".to_owned()],
+        add = ArgValueCompleter::new(complete_refs),
+        env = "RELS_BRANCH",
+        help = "The mainline branches to check. Remote branches (e.g. origin/main) are used if there is no local branch of the same name."
+    )]
+    pub branches: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LintArgs {
+    #[arg(
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The commits to check, e.g. main..HEAD, or a single revision such as origin/main to check the commits since it. Defaults to the commits which aren't in any tag."
+    )]
+    pub range: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    #[arg(
+        long,
+        default_value = "127.0.0.1:8080",
+        env = "RELS_ADDRESS",
+        help = "The address to listen on. Use 0.0.0.0:8080 to accept requests from other machines."
+    )]
+    pub address: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CadenceArgs {
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        env = "RELS_CADENCE_PERIOD",
+        help = "The period to count releases in."
+    )]
+    pub period: Period,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TimelineArgs {
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Period::Day,
+        env = "RELS_TIMELINE_PERIOD",
+        help = "The period of each line."
+    )]
+    pub period: Period,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct NotesArgs {
+    #[arg(
+        long,
+        value_name = "DIR",
+        env = "RELS_SPLIT_DIR",
+        help = "Write the notes of each release to a markdown file named after its tag in this directory instead, e.g. docs/releases/v1.5.0.md. Only files which are new or whose notes have changed are written."
+    )]
+    pub split_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct JqlArgs {
+    #[arg(
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The tag of the release, e.g. v1.5.0"
+    )]
+    pub tag: String,
+
+    #[arg(
+        long = "and",
+        value_name = "CLAUSE",
+        env = "RELS_JQL_AND",
+        help = "A JQL clause the tickets must also match, e.g. \"status != Done\". Can be given multiple times."
+    )]
+    pub clauses: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BackportsArgs {
+    #[arg(
+        required = true,
+        add = ArgValueCompleter::new(complete_refs),
+        help = "The release branches or tags to compare against mainline, e.g. release/1.x"
+    )]
+    pub releases: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        add = ArgValueCompleter::new(complete_refs),
+        env = "RELS_MAINLINE",
+        help = "The mainline branch to compare against. Defaults to main, or master if there is no main branch."
+    )]
+    pub mainline: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_age(age: &str) -> Result<Duration, TagCommitsError> {
+        Args::try_parse_from(["rels", "--age", age])
+            .expect("the arguments should be valid")
+            .max_age()
+    }
+
+    #[test]
+    fn duration_segments_split_numbers_with_their_units() {
+        let duration = "1y 2monx";
+        let segments = duration_segments(duration)
+            .into_iter()
+            .map(|segment| &duration[segment])
+            .collect::<Vec<_>>();
+
+        assert_eq!(segments, ["1y", "2monx"]);
+    }
+
+    #[test]
+    fn valid_ages_are_parsed() {
+        let Ok(max_age) = max_age("1d 2h") else {
+            panic!("the age should be valid");
+        };
+
+        assert_eq!(max_age, Duration::from_secs(26 * 60 * 60));
+    }
+
+    #[test]
+    fn invalid_ages_point_at_the_first_invalid_part() {
+        let Err(TagCommitsError::InvalidDuration(invalid)) = max_age("1y 2x 3d") else {
+            panic!("the age should be invalid");
+        };
+
+        assert_eq!(invalid.input, "1y 2x 3d");
+        assert_eq!(invalid.span, Some(3..5));
+    }
+}
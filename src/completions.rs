@@ -0,0 +1,68 @@
+use clap::ValueEnum;
+use clap_complete::{
+    env::{Bash, EnvCompleter, Fish, Powershell, Zsh},
+    CompletionCandidate,
+};
+use std::{ffi::OsStr, io};
+
+use git2::BranchType;
+
+use crate::git::{get_repo, get_tags};
+
+/// The environment variable the completion scripts set when asking rels for completions.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Print the script which registers completions for a shell. The script calls back into rels
+/// to complete each argument, so tag and branch names are completed from the current repository.
+pub fn print_completions(shell: CompletionShell) -> io::Result<()> {
+    let completer: &dyn EnvCompleter = match shell {
+        CompletionShell::Bash => &Bash,
+        CompletionShell::Zsh => &Zsh,
+        CompletionShell::Fish => &Fish,
+        CompletionShell::Powershell => &Powershell,
+    };
+    completer.write_registration(COMPLETE_VAR, "rels", "rels", "rels", &mut io::stdout())
+}
+
+/// Complete the names of tags and branches in the current repository. Both sides of a range
+/// such as `v1.0.0..main` are completed.
+pub fn complete_refs(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let Ok(repo) = get_repo() else {
+        return Vec::new();
+    };
+    let (range_start, prefix) = match current.rsplit_once("..") {
+        Some((from, to)) => (&current[..from.len() + 2], to.trim_start_matches('.')),
+        None => ("", current),
+    };
+
+    let mut names = get_tags(&repo)
+        .iter()
+        .filter_map(|tag| tag.name().map(str::to_owned))
+        .collect::<Vec<_>>();
+    if let Ok(branches) = repo.branches(None) {
+        names.extend(branches.flatten().filter_map(|(branch, branch_type)| {
+            let name = branch.name().ok().flatten()?;
+            // HEAD of a remote isn't a useful branch to compare against
+            (branch_type == BranchType::Local || !name.ends_with("/HEAD")).then(|| name.to_owned())
+        }));
+    }
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| CompletionCandidate::new(format!("{}{}", range_start, name)))
+        .collect()
+}
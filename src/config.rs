@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use git2::Repository;
+
+use crate::{
+    cli::Args, environments::Environment, forge::Remote, forge_api::Forge, presets::TrackerPreset,
+};
+
+const CONFIG_FILE: &str = ".rels.toml";
+
+/// Configuration loaded from `.rels.toml` in the root of the repository, or `--config`.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The issue trackers to find ticket references for. If none are configured, a single Jira
+    /// tracker is configured from the command line arguments.
+    pub trackers: Vec<TrackerConfig>,
+    /// Placeholder tickets which match a tracker's regex but aren't real tickets, e.g.
+    /// NO-TICKET, which are left out of ticket lists and links.
+    pub ignored_tickets: Vec<String>,
+    /// Only tags matching this glob pattern are releases, e.g. `v*`
+    pub tag_pattern: Option<String>,
+    /// The commit trailer holding a commit's release note, e.g. `User-Facing-Change`. Defaults
+    /// to `Release-Note`.
+    pub release_note_trailer: Option<String>,
+    /// The notes ref `--git-notes` reads, e.g. `refs/notes/deployments`. Defaults to
+    /// `refs/notes/commits`.
+    pub notes_ref: Option<String>,
+    /// The forge hosting the origin remote, e.g. `gitlab` for a self-hosted GitLab whose host
+    /// name doesn't contain gitlab
+    pub forge: Option<Forge>,
+    /// The environments releases are deployed to, in the order they are deployed, e.g. staging
+    /// then prod
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<Environment>,
+    /// The rules checked by `rels lint`
+    pub lint: LintConfig,
+    /// Settings for the `rels jira` commands
+    pub jira: JiraConfig,
+    /// Shell commands run around the release listing with `--hooks`
+    pub hooks: HooksConfig,
+    /// Named sets of settings selected with `--profile`, e.g. `[profile.frontend]`, for
+    /// repositories with different conventions
+    #[serde(rename = "profile", skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings selected with `--profile`, which override the top-level settings.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Trackers which replace the top-level trackers, if any are set
+    pub trackers: Vec<TrackerConfig>,
+    /// Placeholder tickets to ignore, in addition to the top-level ones
+    pub ignored_tickets: Vec<String>,
+    pub tag_pattern: Option<String>,
+}
+
+/// Where `rels lint` requires ticket references.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TicketRequirement {
+    /// Every commit must reference a ticket
+    #[default]
+    Commit,
+    /// Each pull request must reference a ticket in at least one of its commits, including the
+    /// merge commit
+    PullRequest,
+    /// Tickets aren't required
+    None,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    pub require_ticket: TicketRequirement,
+    /// Text which can be used instead of a ticket reference, e.g. NO-TICKET for changes which
+    /// don't need one
+    pub allowed_placeholders: Vec<String>,
+    /// Authors whose commits aren't checked, matching their name or email, e.g. bots
+    pub exempt_authors: Vec<String>,
+    /// If set, commit summaries must start with one of these conventional commit types, e.g.
+    /// `feat: ` or `fix(parser)!: `
+    pub conventional_types: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct JiraConfig {
+    /// The status `rels jira transition` moves the tickets in a release to, e.g. Released.
+    /// Tickets aren't transitioned unless this or a project's status is set.
+    pub release_status: Option<String>,
+    /// The statuses to move tickets in specific projects to, keyed by project, e.g. ABC for
+    /// ABC-123, for projects whose workflows differ. Tickets in other projects use
+    /// `release_status`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_release_statuses: BTreeMap<String, String>,
+}
+
+/// Shell commands run before and after rels lists the releases, for steps specific to a team's
+/// pipeline. They only run with `--hooks`, so cloning a repository and running rels in it
+/// doesn't run commands from its config file.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run before the releases are read, e.g. `git fetch --tags`
+    pub pre: Option<String>,
+    /// Run once for each release listed, with `{tag}` replaced with its tag and the release on
+    /// stdin as JSON, e.g. `./notify.sh {tag}`
+    pub post: Option<String>,
+}
+
+impl JiraConfig {
+    /// The status to move a project's tickets to on release, if there is one.
+    pub fn release_status(&self, project: &str) -> Option<&str> {
+        self.project_release_statuses
+            .get(project)
+            .or(self.release_status.as_ref())
+            .map(String::as_str)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackerConfig {
+    /// The name of the tracker, used to label its tickets. Required unless using a preset.
+    pub name: Option<String>,
+    /// The regex matching ticket references. Required unless using a preset.
+    pub regex: Option<String>,
+    /// The URL template for tickets, in the same format as `--jira-url`. `{number}` is replaced
+    /// with the number at the end of the ticket reference, e.g. 123 for #123, and `{name}` with
+    /// the named capture group `name` of the regex.
+    pub url: Option<String>,
+    /// URL templates for tickets in specific projects, keyed by the `project` capture group of
+    /// the regex, or everything before the number, e.g. ABC for ABC-123. Tickets in other
+    /// projects use `url`.
+    pub project_urls: Option<BTreeMap<String, String>>,
+    /// A built-in tracker to use, which the other settings override. Trackers named jira use the
+    /// jira preset if none is set.
+    pub preset: Option<TrackerPreset>,
+    /// The web URL of the repository for forge presets, e.g. for self-hosted GitLab, of the
+    /// project for Azure Boards, or of the Jira or YouTrack instance. Defaults to the URL of the
+    /// origin remote for forge presets, and the instance `--jira-url` links to for Jira.
+    pub base_url: Option<String>,
+    /// The Azure DevOps organization, for the Azure Boards preset
+    pub organization: Option<String>,
+    /// The Azure DevOps project, for the Azure Boards preset
+    pub project: Option<String>,
+    /// The workspace, for the Linear and Shortcut presets
+    pub workspace: Option<String>,
+}
+
+impl TrackerConfig {
+    /// Whether any settings which only apply to a single tracker are set.
+    pub fn has_tracker_settings(&self) -> bool {
+        self.name.is_some()
+            || self.regex.is_some()
+            || self.url.is_some()
+            || self.project_urls.is_some()
+    }
+
+    /// Apply the settings of `overrides` on top of this configuration.
+    pub fn merge(self, overrides: &TrackerConfig) -> TrackerConfig {
+        TrackerConfig {
+            name: overrides.name.clone().or(self.name),
+            regex: overrides.regex.clone().or(self.regex),
+            url: overrides.url.clone().or(self.url),
+            project_urls: overrides.project_urls.clone().or(self.project_urls),
+            preset: overrides.preset.or(self.preset),
+            base_url: overrides.base_url.clone().or(self.base_url),
+            organization: overrides.organization.clone().or(self.organization),
+            project: overrides.project.clone().or(self.project),
+            workspace: overrides.workspace.clone().or(self.workspace),
+        }
+    }
+}
+
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    /// `rels config init` would overwrite an existing config file
+    AlreadyExists(PathBuf),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl Config {
+    /// The path of the config file, which may not exist: `--config`, or `.rels.toml` in the
+    /// root of the repository. Bare repositories have no default config file.
+    pub fn path(repo: &Repository, args: &Args) -> Option<PathBuf> {
+        args.config
+            .clone()
+            .or_else(|| repo.workdir().map(|workdir| workdir.join(CONFIG_FILE)))
+    }
+
+    /// The forge hosting a remote, from the `forge` setting, or the preset of a forge tracker
+    /// whose `base_url` is on the remote's host, e.g. a self-hosted GitLab.
+    pub fn forge(&self, remote: &Remote) -> Option<Forge> {
+        self.forge.or_else(|| {
+            self.trackers.iter().find_map(|tracker| {
+                let forge = match tracker.preset? {
+                    TrackerPreset::Github => Forge::Github,
+                    TrackerPreset::Gitlab => Forge::Gitlab,
+                    _ => return None,
+                };
+                let base_url = Remote::parse(tracker.base_url.as_deref()?)?;
+                (base_url.host == remote.host).then_some(forge)
+            })
+        })
+    }
+
+    /// Load the configuration from `--config`, or `.rels.toml` in the root of the repository if
+    /// it exists, applying the `--profile` if one is given.
+    pub fn load(repo: &Repository, args: &Args) -> Result<Config, ConfigError> {
+        let mut config = match Config::path(repo, args) {
+            Some(path) if args.config.is_some() || path.exists() => {
+                let contents = fs::read_to_string(&path)
+                    .map_err(|err| ConfigError::Read(path.clone(), err))?;
+                toml::from_str(&contents).map_err(|err| ConfigError::Parse(path, err))?
+            }
+            _ => Config::default(),
+        };
+        if let Some(name) = &args.profile {
+            config.apply_profile(name)?;
+        }
+        Ok(config)
+    }
+
+    /// Apply the settings of a profile on top of the top-level settings.
+    fn apply_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        let Some(profile) = self.profiles.remove(name) else {
+            let names = self.profiles.keys().cloned().collect::<Vec<_>>();
+            return Err(ConfigError::Invalid(if names.is_empty() {
+                format!(
+                    "there is no profile named {}, since no profiles are configured",
+                    name
+                )
+            } else {
+                format!(
+                    "there is no profile named {}, the profiles are {}",
+                    name,
+                    names.join(", ")
+                )
+            }));
+        };
+        if !profile.trackers.is_empty() {
+            self.trackers = profile.trackers;
+        }
+        self.ignored_tickets.extend(profile.ignored_tickets);
+        self.tag_pattern = profile.tag_pattern.or(self.tag_pattern.take());
+        self.profiles.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use git2::{Commit, Oid, Patch, Repository};
+
+/// Directories with less than this percentage of the changes are left out of `--dirstat`, as
+/// `git diff --dirstat` does by default.
+pub const DIRSTAT_THRESHOLD: f64 = 3.0;
+
+/// The changes since the previous release, for `--diffstat`.
+#[derive(Serialize, Clone, Debug)]
+pub struct DiffStat {
+    pub previous_tag: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// The lines changed in each top-level directory, most first, with changes to files at the
+    /// top level under `./`
+    pub directories: Vec<DirStat>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DirStat {
+    pub path: String,
+    pub changes: usize,
+}
+
+impl DiffStat {
+    /// The percentage of the changed lines which are in a directory.
+    pub fn percentage(&self, dir: &DirStat) -> f64 {
+        let total = self.insertions + self.deletions;
+        if total == 0 {
+            return 0.0;
+        }
+        dir.changes as f64 * 100.0 / total as f64
+    }
+
+    /// The summary line, e.g. `12 files changed, 340 insertions(+), 25 deletions(-) since v1.4.0`.
+    pub fn summary(&self) -> String {
+        let plural = |count: usize, singular: &str, plural: &str| {
+            format!("{} {}", count, if count == 1 { singular } else { plural })
+        };
+        format!(
+            "{}, {}, {} since {}",
+            plural(self.files_changed, "file changed", "files changed"),
+            plural(self.insertions, "insertion(+)", "insertions(+)"),
+            plural(self.deletions, "deletion(-)", "deletions(-)"),
+            self.previous_tag
+        )
+    }
+
+    /// The directories with at least [`DIRSTAT_THRESHOLD`] percent of the changes.
+    pub fn top_directories(&self) -> impl Iterator<Item = (f64, &DirStat)> {
+        self.directories
+            .iter()
+            .map(|dir| (self.percentage(dir), dir))
+            .filter(|(percentage, _)| *percentage >= DIRSTAT_THRESHOLD)
+    }
+}
+
+/// Count the files and lines changed from the previous release's commit to a release's commit.
+pub fn diff_stat(
+    repo: &Repository,
+    previous: Oid,
+    previous_tag: &str,
+    commit: &Commit,
+) -> Result<DiffStat, git2::Error> {
+    let previous_tree = repo.find_commit(previous)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&previous_tree), Some(&commit.tree()?), None)?;
+    let stats = diff.stats()?;
+
+    let mut directories: HashMap<String, usize> = HashMap::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let Some(patch) = Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        let (_, insertions, deletions) = patch.line_stats()?;
+        let path = delta.new_file().path().or(delta.old_file().path());
+        let dir = match path.map(|path| path.components().collect::<Vec<_>>()) {
+            Some(components) if components.len() > 1 => {
+                format!("{}/", components[0].as_os_str().to_string_lossy())
+            }
+            _ => "./".to_owned(),
+        };
+        *directories.entry(dir).or_default() += insertions + deletions;
+    }
+    let mut directories = directories
+        .into_iter()
+        .filter(|(_, changes)| *changes > 0)
+        .map(|(path, changes)| DirStat { path, changes })
+        .collect::<Vec<_>>();
+    directories.sort_by(|a, b| b.changes.cmp(&a.changes).then(a.path.cmp(&b.path)));
+
+    Ok(DiffStat {
+        previous_tag: previous_tag.to_owned(),
+        files_changed: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+        directories,
+    })
+}
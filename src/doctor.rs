@@ -0,0 +1,327 @@
+use colored::Colorize;
+
+use git2::{Repository, Sort};
+
+use crate::{
+    cli::Args,
+    config::{Config, ConfigError, TrackerConfig},
+    forge::Remote,
+    git::{commit_is_within_duration, get_release_tags, get_tags},
+    http::HttpClient,
+    lookups::LookupError,
+    releases::TagCommitsError,
+    tickets::Trackers,
+};
+
+/// How many recent commits to search for ticket references.
+const RECENT_COMMITS: usize = 100;
+
+/// A ticket which shouldn't exist, looked up to check API keys are accepted.
+const PROBE_TICKET: &str = "RELS-0";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of one of the checks made by `rels doctor`, with how to fix any problem.
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+impl Finding {
+    fn ok(message: impl Into<String>) -> Finding {
+        Finding {
+            severity: Severity::Ok,
+            message: message.into(),
+            help: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>, help: impl Into<String>) -> Finding {
+        Finding {
+            severity: Severity::Warning,
+            message: message.into(),
+            help: Some(help.into()),
+        }
+    }
+
+    fn error(message: impl Into<String>, help: impl Into<String>) -> Finding {
+        Finding {
+            severity: Severity::Error,
+            message: message.into(),
+            help: Some(help.into()),
+        }
+    }
+}
+
+fn check_repository(repo: &Repository) -> Finding {
+    if repo.is_shallow() {
+        return Finding::warning(
+            "The repository is a shallow clone, so releases may be missing commits",
+            "Fetch the full history with git fetch --unshallow --tags, or fetch-depth: 0 in GitHub Actions",
+        );
+    }
+    Finding::ok(format!("Found the repository at {}", repo.path().display()))
+}
+
+fn check_tags(repo: &Repository, max_age: std::time::Duration, args: &Args) -> Vec<Finding> {
+    let tags = get_tags(repo);
+    let all_tags = repo.tag_names(None).map(|names| names.len()).unwrap_or(0);
+    let lightweight = all_tags.saturating_sub(tags.len());
+
+    let mut findings = Vec::new();
+    if tags.is_empty() {
+        let help = if lightweight > 0 {
+            format!(
+                "Only annotated tags are releases, but the {} tag(s) are lightweight. Recreate them with git tag -a",
+                lightweight
+            )
+        } else {
+            "Each annotated tag is a release, which can be created with git tag -a. Tags may also need to be fetched with git fetch --tags".to_owned()
+        };
+        findings.push(Finding::error("No annotated tags found", help));
+        return findings;
+    }
+
+    findings.push(Finding::ok(format!(
+        "Found {} annotated tag(s)",
+        tags.len()
+    )));
+    let tags = match &args.tag_pattern {
+        Some(pattern) => {
+            let matching = get_release_tags(repo, args);
+            if matching.is_empty() {
+                findings.push(Finding::error(
+                    format!("None of the tags match the tag pattern {}", pattern),
+                    "Check --tag-pattern or tag_pattern in the config, where * matches any text",
+                ));
+                return findings;
+            }
+            findings.push(Finding::ok(format!(
+                "{} tag(s) match the tag pattern {}",
+                matching.len(),
+                pattern
+            )));
+            matching
+        }
+        None => tags,
+    };
+    if lightweight > 0 {
+        findings.push(Finding::warning(
+            format!("{} lightweight tag(s) are ignored", lightweight),
+            "Only annotated tags are releases. Recreate them with git tag -a if they are releases",
+        ));
+    }
+
+    let recent = tags
+        .iter()
+        .filter_map(|tag| tag.target().ok()?.peel_to_commit().ok())
+        .filter(|commit| commit_is_within_duration(commit, max_age))
+        .count();
+    if recent == 0 {
+        findings.push(Finding::warning(
+            format!("None of the tags are newer than --age {}", args.age),
+            "Pass a longer --age to see older releases",
+        ));
+    }
+    findings
+}
+
+fn check_remotes(repo: &Repository) -> Vec<Finding> {
+    let names = repo.remotes().map(|names| {
+        names
+            .iter()
+            .flatten()
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    });
+    let names = names.unwrap_or_default();
+    if names.is_empty() {
+        return vec![Finding::warning(
+            "The repository has no remotes",
+            "Forge presets such as github link to the origin remote, so need base_url in the config instead",
+        )];
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            let url = repo
+                .find_remote(name)
+                .ok()
+                .and_then(|remote| remote.url().map(str::to_owned));
+            match url.as_deref().and_then(Remote::parse) {
+                Some(remote) => Finding::ok(format!(
+                    "Remote {} is {}/{}",
+                    name, remote.host, remote.path
+                )),
+                None => Finding::warning(
+                    format!(
+                        "Couldn't parse the URL of remote {}: {}",
+                        name,
+                        url.unwrap_or_default()
+                    ),
+                    "Forge presets need base_url in the config to link tickets for this remote",
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Check that tickets are found in recent commits, which catches regexes that don't match the
+/// team's ticket references.
+fn check_recent_tickets(repo: &Repository, trackers: &Trackers) -> Finding {
+    let commits = repo.revwalk().and_then(|mut revwalk| {
+        revwalk.set_sorting(Sort::TIME)?;
+        revwalk.push_head()?;
+        Ok(revwalk
+            .take(RECENT_COMMITS)
+            .flatten()
+            .filter_map(|id| repo.find_commit(id).ok())
+            .collect::<Vec<_>>())
+    });
+    let Ok(commits) = commits else {
+        return Finding::warning(
+            "Couldn't read the recent commits",
+            "The repository may have no commits yet, or HEAD may not point at a branch",
+        );
+    };
+
+    let with_tickets = commits
+        .iter()
+        .filter(|commit| {
+            !trackers
+                .find_tickets(commit.message().unwrap_or_default())
+                .is_empty()
+        })
+        .count();
+    if with_tickets == 0 {
+        return Finding::warning(
+            format!(
+                "None of the last {} commits reference a ticket",
+                commits.len()
+            ),
+            "Check --jira-regex or the regexes in the config match how tickets are referenced",
+        );
+    }
+    Finding::ok(format!(
+        "{} of the last {} commits reference a ticket",
+        with_tickets,
+        commits.len()
+    ))
+}
+
+/// Check the API key of each tracker which can look up tickets, by looking up a ticket which
+/// shouldn't exist.
+fn check_apis(config: &Config, args: &Args) -> Vec<Finding> {
+    let presets = config
+        .trackers
+        .iter()
+        .cloned()
+        .chain(args.trackers.iter().map(|preset| TrackerConfig {
+            preset: Some(*preset),
+            ..TrackerConfig::default()
+        }))
+        .filter_map(|tracker| Some((tracker.preset?, tracker)));
+
+    let mut findings = Vec::new();
+    let http = match HttpClient::new(args) {
+        Ok(http) => http,
+        Err(err) => {
+            findings.push(Finding::error(
+                err.message(),
+                "Check --ca-cert is a PEM file of certificates",
+            ));
+            return findings;
+        }
+    };
+    for (preset, tracker) in presets {
+        let api = match preset.api(&tracker, &http) {
+            Ok(Some(api)) => api,
+            Ok(None) => continue,
+            Err(err) => {
+                let help = match err {
+                    ConfigError::Invalid(message) => message,
+                    err => TagCommitsError::from(err).message(),
+                };
+                findings.push(Finding::warning(
+                    format!("Can't look up {} tickets with --lookup", preset),
+                    help,
+                ));
+                continue;
+            }
+        };
+        findings.push(match api.lookup(PROBE_TICKET) {
+            Ok(_) => Finding::ok(format!("The {} API accepted the API key", preset)),
+            Err(LookupError::Http(ureq::Error::StatusCode(status @ (401 | 403)))) => {
+                Finding::error(
+                    format!("The {} API rejected the API key ({})", preset, status),
+                    "Check the API key hasn't expired and can read tickets",
+                )
+            }
+            Err(err) => Finding::error(
+                format!("Couldn't reach the {} API: {}", preset, err),
+                "Check the base_url in the config, any proxy settings, and --ca-cert for an internal CA",
+            ),
+        });
+    }
+    findings
+}
+
+/// Check that rels can find releases and tickets in the repository, finding problems such as
+/// missing tags, an invalid config, or API keys which don't work.
+pub fn run_doctor(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    args: &Args,
+    config: Result<Config, ConfigError>,
+) -> Vec<Finding> {
+    let mut findings = vec![check_repository(repo)];
+    findings.extend(check_tags(repo, max_age, args));
+    findings.extend(check_remotes(repo));
+
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            findings.push(Finding::error(
+                TagCommitsError::from(err).message(),
+                "Fix the config file, or pass --config to use another one",
+            ));
+            return findings;
+        }
+    };
+    match Trackers::new(&config, args, repo) {
+        Ok(trackers) => {
+            findings.push(Finding::ok(format!(
+                "Configured trackers: {}",
+                trackers.names().join(", ")
+            )));
+            findings.push(check_recent_tickets(repo, &trackers));
+        }
+        Err(err) => findings.push(Finding::error(
+            err.message(),
+            "Fix the tracker settings in the config or on the command line",
+        )),
+    }
+    findings.extend(check_apis(&config, args));
+    findings
+}
+
+pub fn print_findings(findings: &[Finding]) {
+    for finding in findings {
+        let (symbol, message) = match finding.severity {
+            Severity::Ok => ("✓".green(), finding.message.normal()),
+            Severity::Warning => ("!".yellow().bold(), finding.message.yellow()),
+            Severity::Error => ("✗".red().bold(), finding.message.red()),
+        };
+        println!("{} {}", symbol, message);
+        if let Some(help) = &finding.help {
+            println!("  {}", help.dimmed());
+        }
+    }
+}
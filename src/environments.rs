@@ -0,0 +1,88 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use git2::{Oid, Repository};
+
+use crate::{git::resolve_ref, releases::TagCommits};
+
+/// An environment releases are deployed to, e.g. prod, tracked by a branch or tag which is
+/// moved to whatever was last deployed there.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    pub name: String,
+    /// The branch or tag pointing at what is deployed, e.g. `deployed/prod`
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+}
+
+/// Parse an `--environment` in the form `name=ref`, e.g. `prod=deployed/prod`.
+pub fn parse_environment(value: &str) -> Result<Environment, String> {
+    match value.split_once('=') {
+        Some((name, git_ref)) if !name.is_empty() && !git_ref.is_empty() => Ok(Environment {
+            name: name.to_owned(),
+            git_ref: git_ref.to_owned(),
+        }),
+        _ => Err("expected NAME=REF, e.g. prod=deployed/prod".to_owned()),
+    }
+}
+
+/// The commit deployed to each environment. Environments whose ref doesn't exist, e.g. because
+/// nothing has been deployed there yet, are left out with a warning.
+pub fn resolve_environments(repo: &Repository, environments: &[Environment]) -> Vec<(String, Oid)> {
+    environments
+        .iter()
+        .filter_map(
+            |environment| match resolve_ref(repo, &environment.git_ref) {
+                Some(id) => Some((environment.name.clone(), id)),
+                None => {
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "The {} environment's ref {} wasn't found",
+                            environment.name, environment.git_ref
+                        )
+                        .yellow()
+                    );
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// The environments a commit has been deployed to, since it is in their history.
+fn deployed_to(repo: &Repository, environments: &[(String, Oid)], id: Oid) -> Vec<String> {
+    environments
+        .iter()
+        .filter(|(_, deployed)| {
+            *deployed == id || repo.graph_descendant_of(*deployed, id).unwrap_or(false)
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Record the environments a release and the given commits added to it have been deployed to.
+pub fn link_environments(
+    repo: &Repository,
+    environments: &[(String, Oid)],
+    tag_commits: &mut TagCommits,
+    tag_name: &str,
+    ids: &[Oid],
+) {
+    if environments.is_empty() {
+        return;
+    }
+    if let Some(target) = tag_commits.tag_targets.get(tag_name) {
+        let deployed = deployed_to(repo, environments, *target);
+        tag_commits
+            .tag_environments
+            .insert(tag_name.to_owned(), deployed);
+    }
+    for id in ids {
+        let deployed = deployed_to(repo, environments, *id);
+        if let Some(info) = tag_commits.commit_to_tag.get_mut(id) {
+            info.environments = deployed;
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use miette::{Diagnostic, LabeledSpan, NamedSource, Report};
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::{
+    cli::OutputFormat,
+    config::ConfigError,
+    releases::{InvalidInput, TagCommitsError},
+};
+
+impl TagCommitsError {
+    /// A stable identifier for the kind of error, for machine-readable output.
+    fn code(&self) -> &'static str {
+        match self {
+            TagCommitsError::NotARepository(_) => "not_a_repository",
+            TagCommitsError::NoTags => "no_tags",
+            TagCommitsError::InvalidTagName(_) => "invalid_tag_name",
+            TagCommitsError::NoBranches(_) => "no_branches",
+            TagCommitsError::RefNotFound(_) => "ref_not_found",
+            TagCommitsError::NotARelease(_) => "not_a_release",
+            TagCommitsError::Git(_) => "git",
+            TagCommitsError::Io(..) => "io",
+            TagCommitsError::Api(..) => "api",
+            TagCommitsError::InvalidAsset(..) => "invalid_asset",
+            TagCommitsError::NoDraftRelease(_) => "no_draft_release",
+            TagCommitsError::PluginNotFound(_) => "unknown_command",
+            TagCommitsError::HookFailed(..) => "hook_failed",
+            TagCommitsError::PreviewRequired(_) => "preview_required",
+            TagCommitsError::UnreleasedVersion(..) => "unreleased_version",
+            TagCommitsError::UnsupportedFormat(..) => "unsupported_format",
+            TagCommitsError::UnknownOutputFormat(_) => "unknown_output_format",
+            TagCommitsError::Keyring(_) => "keyring",
+            TagCommitsError::SelfUpdate(_) => "self_update",
+            TagCommitsError::InvalidRegex(_) => "invalid_regex",
+            TagCommitsError::InvalidDuration(_) => "invalid_duration",
+            TagCommitsError::Config(ConfigError::Read(..)) => "config_read",
+            TagCommitsError::Config(ConfigError::Parse(..)) => "config_parse",
+            TagCommitsError::Config(ConfigError::AlreadyExists(_)) => "config_exists",
+            TagCommitsError::Config(ConfigError::Invalid(_)) => "invalid_config",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            TagCommitsError::NotARepository(path) => {
+                format!("{} is not a git repository!", path.display())
+            }
+            TagCommitsError::NoTags => "No tags found!".to_owned(),
+            TagCommitsError::InvalidTagName(name) => {
+                format!("The tag {} isn't valid UTF-8!", name)
+            }
+            TagCommitsError::NoBranches(branches) => {
+                format!("None of the branches {} were found!", branches.join(", "))
+            }
+            TagCommitsError::RefNotFound(name) => format!("{} is not a branch or tag!", name),
+            TagCommitsError::NotARelease(name) => format!("{} is not a release!", name),
+            TagCommitsError::Git(err) => format!("Git error: {}", err),
+            TagCommitsError::Io(action, err) => format!("Failed to {}: {}", action, err),
+            TagCommitsError::Api(action, err) => format!("Failed to {}: {}", action, err),
+            TagCommitsError::InvalidAsset(asset, reason) => {
+                format!("Invalid --asset {}: {}!", asset, reason)
+            }
+            TagCommitsError::NoDraftRelease(tag) => format!("{} has no draft release!", tag),
+            TagCommitsError::PluginNotFound(name) => {
+                format!(
+                    "{} isn't a command, and there is no rels-{} plugin!",
+                    name, name
+                )
+            }
+            TagCommitsError::HookFailed(hook, reason) => {
+                format!("The {} hook failed: {}!", hook, reason)
+            }
+            TagCommitsError::PreviewRequired(tag) => {
+                format!("The transitions for {} haven't been previewed!", tag)
+            }
+            TagCommitsError::UnreleasedVersion(tag, project) => {
+                format!("{} isn't a released version of {} in Jira!", tag, project)
+            }
+            TagCommitsError::UnsupportedFormat(command, format) => {
+                format!("{} doesn't support --format {}!", command, format.name())
+            }
+            TagCommitsError::UnknownOutputFormat(path) => {
+                format!("Can't tell which format to write {} in!", path.display())
+            }
+            TagCommitsError::Keyring(err) => format!("Failed to access the keyring: {}", err),
+            TagCommitsError::SelfUpdate(err) => format!("Failed to update rels: {}", err),
+            TagCommitsError::InvalidRegex(invalid) => {
+                format!("Invalid ticket regex: {}", invalid.message)
+            }
+            TagCommitsError::InvalidDuration(invalid) => {
+                format!("Invalid --age: {}", invalid.message)
+            }
+            TagCommitsError::Config(ConfigError::Read(path, err)) => {
+                format!("Failed to read config {}: {}", path.display(), err)
+            }
+            TagCommitsError::Config(ConfigError::Parse(path, err)) => {
+                format!("Invalid config {}: {}", path.display(), err)
+            }
+            TagCommitsError::Config(ConfigError::AlreadyExists(path)) => {
+                format!("{} already exists!", path.display())
+            }
+            TagCommitsError::Config(ConfigError::Invalid(message)) => {
+                format!("Invalid config: {}", message)
+            }
+        }
+    }
+
+    /// Details of the error for machine-readable output, such as the path or ref involved.
+    fn context(&self) -> Value {
+        match self {
+            TagCommitsError::NotARepository(path)
+            | TagCommitsError::Config(ConfigError::Read(path, _))
+            | TagCommitsError::Config(ConfigError::Parse(path, _))
+            | TagCommitsError::Config(ConfigError::AlreadyExists(path))
+            | TagCommitsError::UnknownOutputFormat(path) => {
+                json!({ "path": path.display().to_string() })
+            }
+            TagCommitsError::NoBranches(branches) => json!({ "branches": branches }),
+            TagCommitsError::RefNotFound(name) => json!({ "ref": name }),
+            TagCommitsError::InvalidTagName(name) => json!({ "tag": name }),
+            TagCommitsError::UnsupportedFormat(command, format) => {
+                json!({ "command": command, "format": format.name() })
+            }
+            TagCommitsError::InvalidAsset(asset, _) => json!({ "asset": asset }),
+            TagCommitsError::HookFailed(hook, _) => json!({ "hook": hook }),
+            TagCommitsError::PluginNotFound(name) => json!({ "command": name }),
+            TagCommitsError::NotARelease(name)
+            | TagCommitsError::NoDraftRelease(name)
+            | TagCommitsError::PreviewRequired(name) => {
+                json!({ "tag": name })
+            }
+            TagCommitsError::UnreleasedVersion(tag, project) => {
+                json!({ "tag": tag, "project": project })
+            }
+            TagCommitsError::InvalidRegex(invalid) | TagCommitsError::InvalidDuration(invalid) => {
+                json!({
+                    "input": invalid.input,
+                    "span": invalid.span.as_ref().map(|span| [span.start, span.end]),
+                })
+            }
+            _ => json!({}),
+        }
+    }
+
+    /// A suggestion for fixing the error, if there is one.
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            TagCommitsError::NotARepository(_) => Some("Run rels from inside a git repository"),
+            TagCommitsError::NoTags => {
+                Some("Each annotated tag is a release, which can be created with git tag -a. With --tag-pattern, only tags matching it are releases")
+            }
+            TagCommitsError::InvalidTagName(_) => {
+                Some("Rename the tag, or leave it out with --tag-pattern")
+            }
+            TagCommitsError::RefNotFound(_) | TagCommitsError::NoBranches(_) => {
+                Some("Remote branches may need to be fetched first")
+            }
+            TagCommitsError::NotARelease(_) => Some(
+                "Releases are annotated tags newer than --age which match the tag pattern, if there is one",
+            ),
+            TagCommitsError::InvalidRegex(invalid)
+                if invalid.message.contains("look-around")
+                    || invalid.message.contains("backreferences") =>
+            {
+                Some("Lookaround and backreferences need --regex-engine fancy")
+            }
+            TagCommitsError::Config(ConfigError::AlreadyExists(_)) => {
+                Some("Pass --force to overwrite it")
+            }
+            TagCommitsError::Keyring(_) => {
+                Some("Tokens can be set with environment variables instead, e.g. LINEAR_API_KEY")
+            }
+            TagCommitsError::SelfUpdate(_) => Some(
+                "Binaries for each platform can also be downloaded from https://github.com/matt-winfield/rels/releases",
+            ),
+            TagCommitsError::InvalidAsset(..) => Some(
+                "Give the path of a file to upload, or a glob pattern matching files, optionally followed by :name to upload a single file under another name",
+            ),
+            TagCommitsError::NoDraftRelease(_) => Some(
+                "Create one with rels publish github --draft, unless it has already been published",
+            ),
+            TagCommitsError::PluginNotFound(_) => Some(
+                "See rels --help for the commands. Plugins are executables named rels-<name> on the PATH",
+            ),
+            TagCommitsError::HookFailed(..) => Some(
+                "Check the command in the [hooks] section of the config file, or run rels without --hooks",
+            ),
+            TagCommitsError::PreviewRequired(_) => Some(
+                "Run the same command with --dry-run to check which tickets will be transitioned, then run it again without",
+            ),
+            TagCommitsError::UnreleasedVersion(..) => Some(
+                "Tickets are only transitioned once their release is out. Create and release the version with rels jira create-version --released",
+            ),
+            TagCommitsError::UnsupportedFormat(..) => {
+                Some("The formats are text, json, jsonl, markdown and gh-summary for releases, ics for rels feed, junit for rels lint, and teamcity for rels lint and rels metrics")
+            }
+            TagCommitsError::UnknownOutputFormat(_) => Some(
+                "Give a file ending in .md, .json, .jsonl or .html, or pass --format markdown, json or jsonl",
+            ),
+            TagCommitsError::InvalidDuration(_) => {
+                Some("Durations are in the format 1y 2mon 3w 4d 5h 6m 7s")
+            }
+            _ => None,
+        }
+    }
+
+    /// The input which failed to parse, for showing the part which failed.
+    fn invalid_input(&self) -> Option<(&'static str, &InvalidInput)> {
+        match self {
+            TagCommitsError::InvalidRegex(invalid) => Some(("regex", invalid)),
+            TagCommitsError::InvalidDuration(invalid) => Some(("--age", invalid)),
+            _ => None,
+        }
+    }
+}
+
+/// An error rendered as a diagnostic, with the part of the input which failed highlighted.
+struct ErrorDiagnostic {
+    code: String,
+    message: String,
+    help: Option<&'static str>,
+    source_code: Option<NamedSource<String>>,
+    label: Option<LabeledSpan>,
+}
+
+impl ErrorDiagnostic {
+    fn new(err: &TagCommitsError) -> ErrorDiagnostic {
+        let mut diagnostic = ErrorDiagnostic {
+            code: format!("rels::{}", err.code()),
+            message: err.message(),
+            help: err.help(),
+            source_code: None,
+            label: None,
+        };
+        if let Some((name, invalid)) = err.invalid_input() {
+            diagnostic.message = match err {
+                TagCommitsError::InvalidRegex(_) => "Invalid ticket regex".to_owned(),
+                _ => "Invalid --age".to_owned(),
+            };
+            diagnostic.source_code = Some(NamedSource::new(name, invalid.input.clone()));
+            // Errors at the end of the input point at the last character, so they're shown
+            let len = invalid.input.len();
+            let span = match invalid.span.clone() {
+                Some(span) if span.start >= len => len.saturating_sub(1)..len,
+                Some(span) => span.start..span.end.min(len),
+                None => 0..len,
+            };
+            diagnostic.label = Some(LabeledSpan::new_with_span(
+                Some(invalid.message.clone()),
+                span,
+            ));
+        }
+        diagnostic
+    }
+}
+
+impl fmt::Debug for ErrorDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Display for ErrorDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ErrorDiagnostic {}
+
+impl Diagnostic for ErrorDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(&self.code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.help
+            .map(|help| Box::new(help) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.source_code
+            .as_ref()
+            .map(|source| source as &dyn miette::SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.label
+            .clone()
+            .map(|label| Box::new(std::iter::once(label)) as Box<dyn Iterator<Item = _>>)
+    }
+}
+
+/// Print an error to stderr, as a diagnostic or as JSON if that is the output format.
+pub fn print_error(err: &TagCommitsError, format: OutputFormat) {
+    match format {
+        OutputFormat::Text
+        | OutputFormat::Markdown
+        | OutputFormat::Ics
+        | OutputFormat::GhSummary
+        | OutputFormat::Junit
+        | OutputFormat::Teamcity => {
+            eprintln!("{:?}", Report::new(ErrorDiagnostic::new(err)));
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let mut error = json!({
+                "code": err.code(),
+                "exit_code": err.exit_code() as i32,
+                "message": err.message(),
+                "context": err.context(),
+            });
+            if let Some(help) = err.help() {
+                error["help"] = json!(help);
+            }
+            eprintln!("{}", json!({ "error": error }));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{config::ConfigError, releases::TagCommitsError};
+
+/// The exit codes rels uses for each kind of failure, so scripts can tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExitCode {
+    /// An error not covered by another code, e.g. a git error
+    Error = 1,
+    /// Invalid command line arguments, also used by clap
+    Usage = 2,
+    NotARepository = 3,
+    NoTags = 4,
+    /// `--filter` didn't match any releases
+    NoMatchingReleases = 5,
+    /// `rels lint` found commits which break the commit policy
+    LintFailed = 6,
+    /// A tracker API lookup failed. The output is still printed, without the details.
+    ApiError = 7,
+    /// A branch or tag given on the command line doesn't exist, or a tag isn't a release
+    RefNotFound = 8,
+    /// The config file couldn't be read or is invalid, including invalid regexes
+    InvalidConfig = 9,
+    /// `rels doctor` found problems which stop rels from working
+    DoctorFailed = 10,
+    /// `rels audit` found gaps between the tags and the releases published on the forge
+    AuditFailed = 11,
+}
+
+/// The exit codes listed at the end of `--help`.
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Other error, e.g. a git error
+  2  Invalid arguments
+  3  Not a git repository
+  4  No tags found
+  5  --filter didn't match any releases
+  6  rels lint found commits breaking the commit policy
+  7  A tracker API lookup failed
+  8  A branch, tag, or release wasn't found
+  9  Invalid config or regex
+  10 rels doctor found problems
+  11 rels audit found gaps between tags and published releases";
+
+impl ExitCode {
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+impl TagCommitsError {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            TagCommitsError::NotARepository(_) => ExitCode::NotARepository,
+            TagCommitsError::NoTags => ExitCode::NoTags,
+            TagCommitsError::NoBranches(_)
+            | TagCommitsError::RefNotFound(_)
+            | TagCommitsError::NotARelease(_)
+            | TagCommitsError::NoDraftRelease(_)
+            | TagCommitsError::UnreleasedVersion(..) => ExitCode::RefNotFound,
+            TagCommitsError::Git(_)
+            | TagCommitsError::InvalidTagName(_)
+            | TagCommitsError::Io(..)
+            | TagCommitsError::Keyring(_)
+            | TagCommitsError::SelfUpdate(_)
+            | TagCommitsError::HookFailed(..)
+            | TagCommitsError::Config(ConfigError::AlreadyExists(_)) => ExitCode::Error,
+            TagCommitsError::InvalidRegex(_) | TagCommitsError::Config(_) => {
+                ExitCode::InvalidConfig
+            }
+            TagCommitsError::Api(..) => ExitCode::ApiError,
+            TagCommitsError::InvalidDuration(_)
+            | TagCommitsError::PreviewRequired(_)
+            | TagCommitsError::InvalidAsset(..)
+            | TagCommitsError::PluginNotFound(_)
+            | TagCommitsError::UnsupportedFormat(..)
+            | TagCommitsError::UnknownOutputFormat(_) => ExitCode::Usage,
+        }
+    }
+}
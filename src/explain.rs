@@ -0,0 +1,193 @@
+use colored::Colorize;
+
+use git2::{Oid, Repository};
+
+use crate::{
+    cli::Args,
+    git::{
+        commit_is_within_duration, get_release_commits, get_release_tags, resolve_ref,
+        tag_boundaries, tag_name_lossy,
+    },
+    releases::{short_sha, TagCommits, TagCommitsError},
+};
+
+/// Why a commit was or wasn't included in the release of a tag it is reachable from.
+pub enum Reason {
+    Included {
+        depth: usize,
+    },
+    /// One half of a revert pair hidden by `--exclude-reverts`
+    RevertedPair,
+    /// The tag is older than `--age`, so wasn't searched
+    TagTooOld,
+    BeyondDepth {
+        depth: usize,
+    },
+    /// The commit is also reachable from another tag at a lower depth, so belongs to that release
+    CloserToAnotherTag {
+        depth: usize,
+        tag_name: String,
+        closer_depth: usize,
+    },
+    ExcludedByMergeFilters,
+    NoTicket,
+}
+
+impl Reason {
+    fn describe(&self, args: &Args) -> String {
+        match self {
+            Reason::Included { depth } => format!("included at depth {}", depth),
+            Reason::RevertedPair => {
+                "hidden by --exclude-reverts, since it was reverted in the same release".to_owned()
+            }
+            Reason::TagTooOld => format!("tag is older than --age {}", args.age),
+            Reason::BeyondDepth { depth } => {
+                format!("depth {} is beyond --depth {}", depth, args.depth)
+            }
+            Reason::CloserToAnotherTag {
+                depth,
+                tag_name,
+                closer_depth,
+            } => format!(
+                "depth {} here, but closer to {} at depth {}",
+                depth, tag_name, closer_depth
+            ),
+            Reason::ExcludedByMergeFilters if args.no_merges => {
+                "merge commit excluded by --no-merges".to_owned()
+            }
+            Reason::ExcludedByMergeFilters => "excluded by --merges-only".to_owned(),
+            Reason::NoTicket => "no ticket reference; pass --all to include it".to_owned(),
+        }
+    }
+}
+
+pub struct CommitExplanation {
+    pub id: Oid,
+    pub summary: String,
+    pub reason: Reason,
+}
+
+/// The commits a tag's release was searched for. Releases of tags older than `--age` aren't
+/// searched, so have no commits unless a single commit is being explained.
+pub struct ReleaseExplanation {
+    pub tag_name: String,
+    pub too_old: bool,
+    pub commits: Vec<CommitExplanation>,
+}
+
+/// Explain why each commit in the release of each tag was or wasn't included, or just `commit`
+/// if it is given. Every commit up to the previous tags is explained, including those beyond
+/// `--depth`, so this walks more history than finding the releases does.
+pub fn explain_commits(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    args: &Args,
+    tag_commits: &TagCommits,
+    commit: Option<&str>,
+) -> Result<Vec<ReleaseExplanation>, TagCommitsError> {
+    let target = commit
+        .map(|name| {
+            resolve_ref(repo, name).ok_or_else(|| TagCommitsError::RefNotFound(name.to_owned()))
+        })
+        .transpose()?;
+
+    let mut tags = get_release_tags(repo, args);
+    if tags.is_empty() {
+        return Err(TagCommitsError::NoTags);
+    }
+    tags.sort_by(|a, b| a.name_bytes().cmp(b.name_bytes()));
+    let boundaries = tag_boundaries(repo, &tags);
+
+    let mut explanations = Vec::new();
+    for tag in tags {
+        let tag_name = tag
+            .name()
+            .ok_or_else(|| TagCommitsError::InvalidTagName(tag_name_lossy(&tag)))?
+            .to_owned();
+        let tag_commit = repo.find_commit(tag.target()?.id())?;
+        let too_old = !commit_is_within_duration(&tag_commit, max_age);
+        let mut release = ReleaseExplanation {
+            tag_name: tag_name.clone(),
+            too_old,
+            commits: Vec::new(),
+        };
+        if too_old && target.is_none() {
+            explanations.push(release);
+            continue;
+        }
+
+        let release_commits =
+            get_release_commits(repo, &tag_commit, &boundaries, 0, args.first_parent)?;
+        for release_commit in release_commits {
+            if target.is_some_and(|target| target != release_commit.id) {
+                continue;
+            }
+            let commit = repo.find_commit(release_commit.id)?;
+            let depth = release_commit.depth;
+            let reason = match tag_commits.commit_to_tag.get(&commit.id()) {
+                _ if too_old => Reason::TagTooOld,
+                _ if args.depth != 0 && depth > args.depth => Reason::BeyondDepth { depth },
+                Some(info) if info.tag_name != tag_name => Reason::CloserToAnotherTag {
+                    depth,
+                    tag_name: info.tag_name.clone(),
+                    closer_depth: info.depth,
+                },
+                Some(_) if args.exclude_reverts && tag_commits.is_reverted_pair(&commit.id()) => {
+                    Reason::RevertedPair
+                }
+                Some(_) => Reason::Included { depth },
+                None if !args.includes_commit(&commit) => Reason::ExcludedByMergeFilters,
+                None => Reason::NoTicket,
+            };
+            release.commits.push(CommitExplanation {
+                id: commit.id(),
+                summary: commit.summary().unwrap_or_default().to_owned(),
+                reason,
+            });
+        }
+
+        if target.is_none() || !release.commits.is_empty() {
+            explanations.push(release);
+        }
+    }
+    Ok(explanations)
+}
+
+pub fn print_explanations(explanations: &[ReleaseExplanation], args: &Args) {
+    if explanations.is_empty() && args.explain.as_ref().is_some_and(Option::is_some) {
+        println!(
+            "{}",
+            "The commit isn't reachable from any tag, so isn't in a release. rels orphans lists commits like this."
+                .dimmed()
+        );
+        return;
+    }
+
+    for release in explanations {
+        if release.too_old && release.commits.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "{} (older than --age {}, not searched)",
+                    release.tag_name, args.age
+                )
+                .dimmed()
+            );
+            continue;
+        }
+        println!("{}", release.tag_name.green().bold());
+        for commit in &release.commits {
+            let reason = format!("({})", commit.reason.describe(args));
+            let reason = match commit.reason {
+                Reason::Included { .. } => reason.green(),
+                _ => reason.dimmed(),
+            };
+            println!(
+                "  {} {} {}",
+                short_sha(&commit.id).yellow(),
+                commit.summary,
+                reason
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::DateTime;
+use git2::Repository;
+
+use crate::{
+    cli::Args,
+    forge::origin_remote,
+    last_run::release_id,
+    metrics::now,
+    releases::{commits_by_tag, release_commits, CommitTagInfo, TagCommits},
+    tickets::Trackers,
+};
+
+/// The longest a line of an iCalendar file can be, in bytes, before it must be folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Format a Unix timestamp as an iCalendar UTC date-time, e.g. `20240301T120000Z`.
+fn ics_time(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Escape the characters which are special in iCalendar text values.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Add a content line to the calendar, folding it onto continuation lines if it is too long.
+fn push_line(calendar: &mut String, line: &str) {
+    let mut length = 0;
+    for char in line.chars() {
+        if length + char.len_utf8() > MAX_LINE_LENGTH {
+            calendar.push_str("\r\n ");
+            length = 1;
+        }
+        calendar.push(char);
+        length += char.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+/// The description of a commit in a release event: its tickets and release note.
+fn commit_description(info: &CommitTagInfo) -> String {
+    let tickets = info
+        .tickets
+        .iter()
+        .map(|ticket| ticket.id.as_str())
+        .collect::<Vec<_>>();
+    if tickets.is_empty() {
+        format!("- {}", info.release_note)
+    } else {
+        format!("- {}: {}", tickets.join(", "), info.release_note)
+    }
+}
+
+/// Print an iCalendar feed with an event for each release, described by the release notes of
+/// its commits. Returns whether any releases matched `--filter`.
+pub fn print_feed(
+    repo: &Repository,
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+) -> bool {
+    let tag_to_commits = commits_by_tag(tag_commits);
+    let origin = origin_remote(repo, args);
+    let stamp = ics_time(now());
+
+    let mut calendar = String::new();
+    push_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_line(&mut calendar, "VERSION:2.0");
+    push_line(&mut calendar, "PRODID:-//rels//Releases//EN");
+    push_line(&mut calendar, "CALSCALE:GREGORIAN");
+    if let Some(origin) = &origin {
+        push_line(
+            &mut calendar,
+            &format!("X-WR-CALNAME:{} releases", escape(&origin.path)),
+        );
+    }
+
+    let mut printed = false;
+    for tag_name in &tag_commits.tag_names {
+        let Some(time) = tag_commits.tag_times.get(tag_name) else {
+            continue;
+        };
+        let ids = tag_to_commits
+            .get(tag_name.as_str())
+            .cloned()
+            .unwrap_or_default();
+        let Some(commits) = release_commits(args, trackers, tag_commits, tag_name, ids) else {
+            continue;
+        };
+        printed = true;
+
+        let description = tag_commits
+            .tag_notes
+            .get(tag_name)
+            .cloned()
+            .into_iter()
+            .chain(commits.into_iter().map(commit_description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        push_line(&mut calendar, "BEGIN:VEVENT");
+        push_line(
+            &mut calendar,
+            &format!("UID:{}", escape(&release_id(tag_name, tag_commits))),
+        );
+        push_line(&mut calendar, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut calendar, &format!("DTSTART:{}", ics_time(*time)));
+        push_line(&mut calendar, &format!("SUMMARY:{}", escape(tag_name)));
+        if !description.is_empty() {
+            push_line(
+                &mut calendar,
+                &format!("DESCRIPTION:{}", escape(&description)),
+            );
+        }
+        if let Some(url) = origin
+            .as_ref()
+            .and_then(|origin| origin.release_url(tag_name))
+        {
+            push_line(&mut calendar, &format!("URL:{}", url));
+        }
+        push_line(&mut calendar, "END:VEVENT");
+    }
+    push_line(&mut calendar, "END:VCALENDAR");
+
+    print!("{}", calendar);
+    printed
+}
@@ -0,0 +1,211 @@
+use git2::Repository;
+
+use crate::{cli::Args, forge_api::Forge};
+
+/// A repository hosted on a forge such as GitHub or GitLab, parsed from a remote URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remote {
+    /// The host name, e.g. `github.com`
+    pub host: String,
+    /// The path of the repository, e.g. `owner/repo`
+    pub path: String,
+    /// The forge hosting the repository, if known. Guessed from the host name unless set with
+    /// `--forge`, as self-hosted forges can have any name.
+    pub forge: Option<Forge>,
+}
+
+/// The forge a host is likely to be: GitHub for github.com, or GitLab for gitlab.com and
+/// self-hosted instances with gitlab in their host name.
+fn forge_from_host(host: &str) -> Option<Forge> {
+    if host == "github.com" {
+        Some(Forge::Github)
+    } else if host.contains("gitlab") {
+        Some(Forge::Gitlab)
+    } else {
+        None
+    }
+}
+
+impl Remote {
+    /// Parse a remote URL in any of the forms git accepts, e.g. `git@github.com:owner/repo.git`,
+    /// `ssh://git@github.com/owner/repo.git`, or `https://github.com/owner/repo`.
+    pub fn parse(url: &str) -> Option<Remote> {
+        let (host, path) = match url.split_once("://") {
+            Some((_, rest)) => {
+                let (authority, path) = rest.split_once('/')?;
+                let host = authority.rsplit('@').next()?;
+                // Drop the port, since the web UI doesn't use the same one as SSH
+                let host = host.split(':').next()?;
+                (host, path)
+            }
+            // scp-like syntax, e.g. git@github.com:owner/repo.git
+            None => {
+                let (authority, path) = url.split_once(':')?;
+                (authority.rsplit('@').next()?, path)
+            }
+        };
+
+        let path = path.trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        if host.is_empty() || path.is_empty() {
+            return None;
+        }
+
+        Some(Remote {
+            host: host.to_owned(),
+            path: path.to_owned(),
+            forge: forge_from_host(host),
+        })
+    }
+
+    /// The URL of the Azure DevOps project containing the repository, if it is hosted on Azure
+    /// DevOps.
+    pub fn azure_devops_project_url(&self) -> Option<String> {
+        let segments = self.path.split('/').collect::<Vec<_>>();
+        match (self.host.as_str(), segments.as_slice()) {
+            // https://dev.azure.com/org/project/_git/repo
+            ("dev.azure.com", [organization, project, "_git", _]) => Some(format!(
+                "https://dev.azure.com/{}/{}",
+                organization, project
+            )),
+            // git@ssh.dev.azure.com:v3/org/project/repo
+            ("ssh.dev.azure.com", ["v3", organization, project, _]) => Some(format!(
+                "https://dev.azure.com/{}/{}",
+                organization, project
+            )),
+            // https://org.visualstudio.com/project/_git/repo
+            (host, [project, "_git", _]) if host.ends_with(".visualstudio.com") => {
+                Some(format!("https://{}/{}", host, project))
+            }
+            _ => None,
+        }
+    }
+
+    /// The URL of the repository's web page.
+    pub fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.path)
+    }
+
+    /// The URL of a pull request on GitHub, or merge request on GitLab.
+    pub fn pull_request_url(&self, number: u64) -> Option<String> {
+        match self.forge? {
+            Forge::Github => Some(format!("{}/pull/{}", self.web_url(), number)),
+            Forge::Gitlab => Some(format!("{}/-/merge_requests/{}", self.web_url(), number)),
+        }
+    }
+
+    /// The URL of the page for the release of a tag, for repositories on GitHub or GitLab.
+    pub fn release_url(&self, tag: &str) -> Option<String> {
+        match self.forge? {
+            Forge::Github => Some(format!("{}/releases/tag/{}", self.web_url(), tag)),
+            Forge::Gitlab => Some(format!("{}/-/releases/{}", self.web_url(), tag)),
+        }
+    }
+
+    /// The URL of the diff between two tags, for repositories on GitHub, GitLab, or Bitbucket.
+    pub fn compare_url(&self, from: &str, to: &str) -> Option<String> {
+        match self.forge {
+            Some(Forge::Github) => Some(format!("{}/compare/{}...{}", self.web_url(), from, to)),
+            Some(Forge::Gitlab) => Some(format!("{}/-/compare/{}...{}", self.web_url(), from, to)),
+            None if self.host == "bitbucket.org" => Some(format!(
+                "{}/branches/compare/{}%0D{}",
+                self.web_url(),
+                to,
+                from
+            )),
+            None => None,
+        }
+    }
+}
+
+/// The forge repository of the `origin` remote, if there is one, on the forge set with `--forge`
+/// or the `forge` setting if there is one.
+pub fn origin_remote(repo: &Repository, args: &Args) -> Option<Remote> {
+    let remote = repo.find_remote("origin").ok()?;
+    let remote = Remote::parse(remote.url()?)?;
+    Some(Remote {
+        forge: args.forge.or(remote.forge),
+        ..remote
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(host: &str, path: &str, forge: Option<Forge>) -> Option<Remote> {
+        Some(Remote {
+            host: host.to_owned(),
+            path: path.to_owned(),
+            forge,
+        })
+    }
+
+    #[test]
+    fn remotes_are_parsed_from_every_url_form() {
+        let github = remote("github.com", "owner/repo", Some(Forge::Github));
+
+        assert_eq!(Remote::parse("git@github.com:owner/repo.git"), github);
+        assert_eq!(
+            Remote::parse("ssh://git@github.com:22/owner/repo.git"),
+            github
+        );
+        assert_eq!(Remote::parse("https://github.com/owner/repo"), github);
+        assert_eq!(
+            Remote::parse("https://token@github.com/owner/repo/"),
+            github
+        );
+    }
+
+    #[test]
+    fn forges_are_guessed_from_the_host() {
+        assert_eq!(
+            Remote::parse("https://gitlab.example.com/group/sub/repo.git"),
+            remote("gitlab.example.com", "group/sub/repo", Some(Forge::Gitlab))
+        );
+        assert_eq!(
+            Remote::parse("git@git.example.com:owner/repo.git"),
+            remote("git.example.com", "owner/repo", None)
+        );
+    }
+
+    #[test]
+    fn urls_without_a_host_or_path_are_not_remotes() {
+        assert_eq!(Remote::parse("/srv/git/repo.git"), None);
+        assert_eq!(Remote::parse("https://github.com/"), None);
+        assert_eq!(Remote::parse(":owner/repo"), None);
+    }
+
+    #[test]
+    fn azure_devops_project_urls_are_found_from_every_url_form() {
+        let project = Some("https://dev.azure.com/org/project".to_owned());
+        let project_url =
+            |url: &str| Remote::parse(url).and_then(|remote| remote.azure_devops_project_url());
+
+        assert_eq!(
+            project_url("https://dev.azure.com/org/project/_git/repo"),
+            project
+        );
+        assert_eq!(
+            project_url("https://org@dev.azure.com/org/project/_git/repo"),
+            project
+        );
+        assert_eq!(
+            project_url("git@ssh.dev.azure.com:v3/org/project/repo"),
+            project
+        );
+        assert_eq!(
+            project_url("https://org.visualstudio.com/project/_git/repo"),
+            Some("https://org.visualstudio.com/project".to_owned())
+        );
+    }
+
+    #[test]
+    fn other_remotes_have_no_azure_devops_project() {
+        let project_url =
+            |url: &str| Remote::parse(url).and_then(|remote| remote.azure_devops_project_url());
+
+        assert_eq!(project_url("https://github.com/owner/repo"), None);
+        assert_eq!(project_url("https://dev.azure.com/org/project"), None);
+    }
+}
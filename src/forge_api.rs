@@ -0,0 +1,264 @@
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use git2::Repository;
+
+use crate::{
+    auth::{token, AuthService},
+    cli::Args,
+    config::ConfigError,
+    forge::origin_remote,
+    http::HttpClient,
+    lookups::LookupError,
+    releases::TagCommitsError,
+};
+
+/// The forges rels can link to and use the API of, set with `--forge` or `forge` in the config
+/// for self-hosted instances.
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Forge {
+    Github,
+    Gitlab,
+}
+
+impl Forge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Forge::Github => "GitHub",
+            Forge::Gitlab => "GitLab",
+        }
+    }
+}
+
+/// The REST API of the repository on the forge hosting the `origin` remote.
+pub struct ForgeApi {
+    http: HttpClient,
+    pub forge: Forge,
+    /// The API URL of the repository, e.g. `https://api.github.com/repos/owner/repo`
+    repo_url: String,
+    token: String,
+}
+
+impl ForgeApi {
+    /// The API of the origin remote, if it is on GitHub or GitLab (including self-hosted GitLab
+    /// instances with gitlab in their host name) and there is a token for it in `GITHUB_TOKEN`
+    /// or `GITLAB_TOKEN`. Otherwise a warning is printed saying why `option` can't be used.
+    pub fn new(
+        repo: &Repository,
+        args: &Args,
+        http: Option<&HttpClient>,
+        option: &str,
+    ) -> Result<Option<ForgeApi>, TagCommitsError> {
+        Ok(match ForgeApi::resolve(repo, args, http)? {
+            Ok(api) => Some(api),
+            Err(reason) => {
+                warn(&format!("{} {}", option, reason));
+                None
+            }
+        })
+    }
+
+    /// The API of the origin remote as [`ForgeApi::new`] describes, failing if there isn't one
+    /// since `command` can't do anything without it.
+    pub fn required(
+        repo: &Repository,
+        args: &Args,
+        http: Option<&HttpClient>,
+        command: &str,
+    ) -> Result<ForgeApi, TagCommitsError> {
+        ForgeApi::resolve(repo, args, http)?
+            .map_err(|reason| ConfigError::Invalid(format!("{} {}", command, reason)).into())
+    }
+
+    /// The API of the origin remote, or the reason it can't be used.
+    fn resolve(
+        repo: &Repository,
+        args: &Args,
+        http: Option<&HttpClient>,
+    ) -> Result<Result<ForgeApi, String>, TagCommitsError> {
+        let Some(remote) = origin_remote(repo, args) else {
+            return Ok(Err("needs an origin remote on GitHub or GitLab".to_owned()));
+        };
+        let Some(forge) = remote.forge else {
+            return Ok(Err(format!(
+                "needs an origin remote on GitHub or GitLab, not {} (set forge in the config for a self-hosted forge)",
+                remote.host
+            )));
+        };
+        let (repo_url, token_var, service) = match forge {
+            Forge::Github if remote.host == "github.com" => (
+                format!("https://api.github.com/repos/{}", remote.path),
+                "GITHUB_TOKEN",
+                AuthService::Github,
+            ),
+            // GitHub Enterprise Server serves its API from the same host
+            Forge::Github => (
+                format!("https://{}/api/v3/repos/{}", remote.host, remote.path),
+                "GITHUB_TOKEN",
+                AuthService::Github,
+            ),
+            Forge::Gitlab => (
+                format!(
+                    "https://{}/api/v4/projects/{}",
+                    remote.host,
+                    remote.path.replace('/', "%2F")
+                ),
+                "GITLAB_TOKEN",
+                AuthService::Gitlab,
+            ),
+        };
+        let Some(token) = token(token_var, service) else {
+            return Ok(Err(format!(
+                "needs a token in {}, or stored with rels auth login {}",
+                token_var, service
+            )));
+        };
+
+        Ok(Ok(ForgeApi {
+            http: match http {
+                Some(http) => http.clone(),
+                None => HttpClient::new(args)?,
+            },
+            forge,
+            repo_url,
+            token,
+        }))
+    }
+
+    /// The header authenticating a request with the token.
+    fn auth_header(&self) -> (&'static str, String) {
+        match self.forge {
+            Forge::Github => ("Authorization", format!("Bearer {}", self.token)),
+            Forge::Gitlab => ("PRIVATE-TOKEN", self.token.clone()),
+        }
+    }
+
+    /// The API URL of a resource of the repository.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.repo_url, path)
+    }
+
+    /// Get a resource of the repository, e.g. `pulls/123`, returning `None` if it doesn't exist.
+    pub fn get<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, LookupError> {
+        let url = self.url(path);
+        let (auth_header, auth_value) = self.auth_header();
+        let mut response = match self.http.send(|agent| {
+            agent
+                .get(&url)
+                .header(auth_header, &auth_value)
+                .header("Accept", "application/json")
+                .call()
+        }) {
+            Ok(response) => response,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(response.body_mut().read_json()?))
+    }
+
+    /// Get every page of a list of resources, e.g. `releases`.
+    pub fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, LookupError> {
+        const PER_PAGE: usize = 100;
+        let mut items = Vec::new();
+        for page in 1.. {
+            let page_items: Vec<T> = self
+                .get(&format!("{}?per_page={}&page={}", path, PER_PAGE, page))?
+                .unwrap_or_default();
+            let count = page_items.len();
+            items.extend(page_items);
+            if count < PER_PAGE {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Create a resource of the repository, e.g. `releases`, returning what was created.
+    pub fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, LookupError> {
+        let url = self.url(path);
+        let (auth_header, auth_value) = self.auth_header();
+        Ok(self
+            .http
+            .send(|agent| {
+                agent
+                    .post(&url)
+                    .header(auth_header, &auth_value)
+                    .header("Accept", "application/json")
+                    .send_json(body)
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    /// Update a resource of the repository, e.g. `releases/123`, returning the updated resource.
+    pub fn patch<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, LookupError> {
+        let url = self.url(path);
+        let (auth_header, auth_value) = self.auth_header();
+        Ok(self
+            .http
+            .send(|agent| {
+                agent
+                    .patch(&url)
+                    .header(auth_header, &auth_value)
+                    .header("Accept", "application/json")
+                    .send_json(body)
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    /// Upload the contents of a file to a URL of the forge, e.g. a release asset, which is a
+    /// POST on GitHub and a PUT on GitLab.
+    pub fn upload<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        contents: &[u8],
+    ) -> Result<T, LookupError> {
+        let (auth_header, auth_value) = self.auth_header();
+        Ok(self
+            .http
+            .send(|agent| match self.forge {
+                Forge::Github => agent
+                    .post(url)
+                    .header(auth_header, &auth_value)
+                    .header("Accept", "application/json")
+                    .content_type("application/octet-stream")
+                    .send(contents),
+                Forge::Gitlab => agent
+                    .put(url)
+                    .header(auth_header, &auth_value)
+                    .header("Accept", "application/json")
+                    .content_type("application/octet-stream")
+                    .send(contents),
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+}
+
+/// Percent-encode a value for a URL path segment or query, e.g. a file name with spaces.
+pub fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub fn warn(message: &str) {
+    eprintln!("{}", message.yellow());
+}
@@ -0,0 +1,332 @@
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use serde_json::json;
+use std::cell::Cell;
+
+use git2::Repository;
+
+use crate::{
+    cli::Args,
+    forge_api::{encode, warn, Forge, ForgeApi},
+    http::HttpClient,
+    lookups::LookupError,
+    releases::TagCommitsError,
+};
+
+/// A release published on GitHub or GitLab for a tag.
+pub struct ForgeRelease {
+    pub tag: String,
+    /// The release notes
+    pub body: Option<String>,
+    pub assets: Vec<ReleaseAsset>,
+    /// The page of the release on the forge
+    pub url: Option<String>,
+    /// Whether the release is a draft, which only GitHub has
+    pub draft: bool,
+}
+
+/// A file published with a release, e.g. a binary uploaded by CI.
+#[derive(Serialize, Clone)]
+pub struct ReleaseAsset {
+    pub name: String,
+    /// The size in bytes, which GitLab doesn't report for its asset links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    pub url: String,
+}
+
+/// A file to upload with a release, given with `--asset`.
+pub struct AssetUpload {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+impl ReleaseAsset {
+    /// The size in the largest unit it has at least one of, e.g. `4.2 MiB`.
+    pub fn format_size(&self) -> Option<String> {
+        let size = self.size?;
+        let units = ["B", "KiB", "MiB", "GiB"];
+        let mut value = size as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < units.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        Some(match unit {
+            0 => format!("{} B", size),
+            _ => format!("{:.1} {}", value, units[unit]),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    id: u64,
+    tag_name: String,
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+    html_url: Option<String>,
+    /// The URL to upload assets to, as a URI template ending in `{?name,label}`
+    upload_url: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    size: u64,
+    browser_download_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabRelease {
+    tag_name: String,
+    description: Option<String>,
+    #[serde(default)]
+    assets: GitlabAssets,
+    #[serde(rename = "_links", default)]
+    links: GitlabReleaseLinks,
+}
+
+#[derive(Deserialize, Default)]
+struct GitlabReleaseLinks {
+    #[serde(rename = "self")]
+    page: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct GitlabAssets {
+    #[serde(default)]
+    links: Vec<GitlabAssetLink>,
+}
+
+#[derive(Deserialize)]
+struct GitlabAssetLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+impl From<GithubRelease> for ForgeRelease {
+    fn from(release: GithubRelease) -> Self {
+        ForgeRelease {
+            tag: release.tag_name,
+            body: release.body,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| ReleaseAsset {
+                    name: asset.name,
+                    size: Some(asset.size),
+                    url: asset.browser_download_url,
+                })
+                .collect(),
+            url: release.html_url,
+            draft: release.draft,
+        }
+    }
+}
+
+impl From<GitlabRelease> for ForgeRelease {
+    fn from(release: GitlabRelease) -> Self {
+        ForgeRelease {
+            tag: release.tag_name,
+            body: release.description,
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| ReleaseAsset {
+                    name: link.name,
+                    size: None,
+                    url: link.direct_asset_url.unwrap_or(link.url),
+                })
+                .collect(),
+            url: release.links.page,
+            draft: false,
+        }
+    }
+}
+
+/// The releases API of the forge hosting the `origin` remote.
+pub struct ReleaseApi {
+    api: ForgeApi,
+    /// Whether a request has failed, after which no more are made to avoid repeating the error
+    failed: Cell<bool>,
+}
+
+impl ReleaseApi {
+    /// The API of the origin remote, if it is on GitHub or GitLab and there is a token for it,
+    /// as described in [`ForgeApi::new`]. `option` is the option needing it, for the warning
+    /// printed if it can't be used.
+    pub fn new(
+        repo: &Repository,
+        args: &Args,
+        http: Option<&HttpClient>,
+        option: &str,
+    ) -> Result<Option<ReleaseApi>, TagCommitsError> {
+        Ok(
+            ForgeApi::new(repo, args, http, option)?.map(|api| ReleaseApi {
+                api,
+                failed: Cell::new(false),
+            }),
+        )
+    }
+
+    /// The releases API of the origin remote, failing if there isn't one as described in
+    /// [`ForgeApi::required`].
+    pub fn required(
+        repo: &Repository,
+        args: &Args,
+        command: &str,
+    ) -> Result<ReleaseApi, TagCommitsError> {
+        Ok(ReleaseApi {
+            api: ForgeApi::required(repo, args, None, command)?,
+            failed: Cell::new(false),
+        })
+    }
+
+    /// The forge hosting the origin remote.
+    pub fn forge(&self) -> Forge {
+        self.api.forge
+    }
+
+    /// Every release published on the forge.
+    pub fn releases(&self) -> Result<Vec<ForgeRelease>, LookupError> {
+        Ok(match self.api.forge {
+            Forge::Github => self
+                .api
+                .get_all::<GithubRelease>("releases")?
+                .into_iter()
+                .map(ForgeRelease::from)
+                .collect(),
+            Forge::Gitlab => self
+                .api
+                .get_all::<GitlabRelease>("releases")?
+                .into_iter()
+                .map(ForgeRelease::from)
+                .collect(),
+        })
+    }
+
+    /// The release published for a tag, if there is one.
+    pub fn release(&self, tag: &str) -> Result<Option<ForgeRelease>, LookupError> {
+        Ok(match self.api.forge {
+            Forge::Github => self
+                .api
+                .get::<GithubRelease>(&format!("releases/tags/{}", tag))?
+                .map(ForgeRelease::from),
+            Forge::Gitlab => self
+                .api
+                .get::<GitlabRelease>(&format!("releases/{}", tag.replace('/', "%2F")))?
+                .map(ForgeRelease::from),
+        })
+    }
+
+    /// Create a release for a tag with notes and upload assets to it, as a draft if `draft`
+    /// which is only hidden until it is published with [`ReleaseApi::publish_draft`] on
+    /// GitHub. GitLab has no release uploads, so assets are uploaded to its generic package
+    /// registry and linked from the release.
+    pub fn create(
+        &self,
+        tag: &str,
+        notes: &str,
+        draft: bool,
+        assets: &[AssetUpload],
+    ) -> Result<ForgeRelease, LookupError> {
+        match self.api.forge {
+            Forge::Github => {
+                let release: GithubRelease = self.api.post(
+                    "releases",
+                    &json!({ "tag_name": tag, "name": tag, "body": notes, "draft": draft }),
+                )?;
+                let upload_url = release
+                    .upload_url
+                    .as_deref()
+                    .and_then(|url| url.split('{').next())
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| self.api.url(&format!("releases/{}/assets", release.id)));
+                let mut release = ForgeRelease::from(release);
+                for asset in assets {
+                    let uploaded: GithubAsset = self.api.upload(
+                        &format!("{}?name={}", upload_url, encode(&asset.name)),
+                        &asset.contents,
+                    )?;
+                    release.assets.push(ReleaseAsset {
+                        name: uploaded.name,
+                        size: Some(uploaded.size),
+                        url: uploaded.browser_download_url,
+                    });
+                }
+                Ok(release)
+            }
+            Forge::Gitlab => {
+                let mut links = Vec::new();
+                for asset in assets {
+                    let url = self.api.url(&format!(
+                        "packages/generic/releases/{}/{}",
+                        encode(&tag.replace('/', "-")),
+                        encode(&asset.name)
+                    ));
+                    self.api.upload::<IgnoredAny>(&url, &asset.contents)?;
+                    links.push(json!({ "name": asset.name, "url": url, "link_type": "package" }));
+                }
+                Ok(self
+                    .api
+                    .post::<GitlabRelease>(
+                        "releases",
+                        &json!({
+                            "tag_name": tag,
+                            "name": tag,
+                            "description": notes,
+                            "assets": { "links": links },
+                        }),
+                    )?
+                    .into())
+            }
+        }
+    }
+
+    /// Publish the draft release for a tag, returning `None` if there isn't one. Drafts aren't
+    /// returned when looking up a release by its tag, so every release is listed to find it.
+    pub fn publish_draft(&self, tag: &str) -> Result<Option<ForgeRelease>, LookupError> {
+        if self.api.forge != Forge::Github {
+            return Ok(None);
+        }
+        let Some(draft) = self
+            .api
+            .get_all::<GithubRelease>("releases")?
+            .into_iter()
+            .find(|release| release.draft && release.tag_name == tag)
+        else {
+            return Ok(None);
+        };
+        let release: GithubRelease = self.api.patch(
+            &format!("releases/{}", draft.id),
+            &json!({ "draft": false }),
+        )?;
+        Ok(Some(release.into()))
+    }
+
+    /// Fetch the release for a tag, returning `None` if there isn't one or a request has
+    /// failed. The first failure is reported as a warning.
+    pub fn fetch(&self, tag: &str) -> Option<ForgeRelease> {
+        if self.failed.get() {
+            return None;
+        }
+        match self.release(tag) {
+            Ok(release) => release,
+            Err(err) => {
+                self.failed.set(true);
+                warn(&format!("Failed to fetch releases: {}", err));
+                None
+            }
+        }
+    }
+
+    /// Whether fetching a release has failed.
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+}
@@ -0,0 +1,532 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::SystemTime,
+};
+
+use git2::{BranchType, Commit, Oid, Repository, RepositoryOpenFlags, Sort, Tag};
+use regex::Regex;
+
+use tracing::{debug, trace};
+
+use crate::{cli::Args, releases::TagCommitsError};
+
+pub fn get_repo() -> Result<Repository, TagCommitsError> {
+    Repository::open_ext(
+        ".",
+        RepositoryOpenFlags::empty(),
+        &[] as &[&std::ffi::OsStr],
+    )
+    .map_err(|_| {
+        TagCommitsError::NotARepository(env::current_dir().unwrap_or(std::path::PathBuf::from(".")))
+    })
+}
+
+pub fn get_tags(repo: &Repository) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let _ = repo.tag_foreach(|tag_id, _| {
+        if let Ok(tag) = repo.find_tag(tag_id) {
+            tags.push(tag);
+        }
+        true
+    });
+    tags
+}
+
+/// A regex matching the same names as a glob pattern, where `*` matches any text, `?` any
+/// single character, and `[abc]` or `[!abc]` a character which is or isn't in the brackets.
+fn glob_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' if rest.contains(']') => {
+                let (class, after) = rest.split_once(']').expect("the class should be closed");
+                let (negated, class) = match class.strip_prefix('!') {
+                    Some(class) => ("^", class),
+                    None => ("", class),
+                };
+                let class = class
+                    .chars()
+                    .map(|c| match c {
+                        '-' => "-".to_owned(),
+                        c => regex::escape(c.encode_utf8(&mut [0; 4])),
+                    })
+                    .collect::<String>();
+                regex.push_str(&format!("[{}{}]", negated, class));
+                rest = after;
+            }
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    // Classes such as [] or [z-a] aren't valid, so match the pattern literally instead
+    Regex::new(&regex).unwrap_or_else(|_| {
+        Regex::new(&format!("^{}$", regex::escape(pattern)))
+            .expect("escaped patterns should be valid regexes")
+    })
+}
+
+/// The tags which are releases: every annotated tag, or only those matching the tag pattern
+/// from `--tag-pattern` or the config, e.g. `frontend-v*`.
+pub fn get_release_tags<'a>(repo: &'a Repository, args: &Args) -> Vec<Tag<'a>> {
+    let mut tags = get_tags(repo);
+    if let Some(pattern) = &args.tag_pattern {
+        let regex = glob_regex(pattern);
+        tags.retain(|tag| tag.name().is_some_and(|name| regex.is_match(name)));
+    }
+    tags
+}
+
+/// Whether a tag name matches `--tag-pattern`, if there is one.
+pub fn matches_tag_pattern(args: &Args, name: &str) -> bool {
+    args.tag_pattern
+        .as_ref()
+        .is_none_or(|pattern| glob_regex(pattern).is_match(name))
+}
+
+/// Find the tip of a branch, preferring a local branch over a remote one.
+pub fn get_branch_tip(repo: &Repository, name: &str) -> Option<Oid> {
+    repo.find_branch(name, BranchType::Local)
+        .or_else(|_| repo.find_branch(&format!("origin/{name}"), BranchType::Remote))
+        .or_else(|_| repo.find_branch(name, BranchType::Remote))
+        .ok()
+        .and_then(|branch| branch.get().target())
+}
+
+/// Resolve a branch, tag, or other revision to the commit it points at.
+pub fn resolve_ref(repo: &Repository, name: &str) -> Option<Oid> {
+    get_branch_tip(repo, name).or_else(|| {
+        repo.revparse_single(name)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .ok()
+    })
+}
+
+pub struct CommitDepthInfo {
+    pub id: Oid,
+    pub depth: usize,
+}
+
+/// A commit which can bound a release, such as the commit of another tag.
+pub struct Boundary {
+    pub id: Oid,
+    pub time: i64,
+}
+
+/// The commits of tags, which bound the releases of other tags.
+pub fn tag_boundaries(repo: &Repository, tags: &[Tag]) -> Vec<Boundary> {
+    tags.iter()
+        .filter_map(|tag| repo.find_commit(tag.target_id()).ok())
+        .map(|commit| Boundary {
+            id: commit.id(),
+            time: commit.time().seconds(),
+        })
+        .collect()
+}
+
+/// Whether the boundaries are all on one line of history, each an ancestor or descendant of
+/// every other. Boundaries are compared in order of commit time, so clock skew can make linear
+/// boundaries look diverging, but never the other way round.
+pub fn boundaries_are_linear(repo: &Repository, boundaries: &[Boundary]) -> bool {
+    let mut boundaries = boundaries
+        .iter()
+        .map(|boundary| (boundary.time, boundary.id))
+        .collect::<Vec<_>>();
+    boundaries.sort();
+    boundaries.windows(2).all(|pair| {
+        let ((_, older), (_, newer)) = (pair[0], pair[1]);
+        older == newer || repo.graph_descendant_of(newer, older).unwrap_or(false)
+    })
+}
+
+/// The commits of tags, with the name of the tag each is for.
+pub fn tagged_boundaries(repo: &Repository, tags: &[Tag]) -> Vec<(String, Boundary)> {
+    tags.iter()
+        .filter_map(|tag| {
+            let commit = repo.find_commit(tag.target_id()).ok()?;
+            let boundary = Boundary {
+                id: commit.id(),
+                time: commit.time().seconds(),
+            };
+            Some((tag.name()?.to_owned(), boundary))
+        })
+        .collect()
+}
+
+/// The release a release follows: the latest tagged commit before it which it descends from.
+/// Tags on other branches, e.g. a newer minor release than a patch release, aren't candidates.
+pub fn previous_release<'a>(
+    repo: &Repository,
+    commit: &Commit,
+    tagged: &'a [(String, Boundary)],
+) -> Option<&'a (String, Boundary)> {
+    let release_time = commit.time().seconds();
+    tagged
+        .iter()
+        .filter(|(_, boundary)| boundary.id != commit.id() && boundary.time <= release_time)
+        .filter(|(_, boundary)| {
+            repo.graph_descendant_of(commit.id(), boundary.id)
+                .unwrap_or(false)
+        })
+        .max_by_key(|(name, boundary)| (boundary.time, name.as_str()))
+}
+
+/// Get the boundaries which are likely ancestors of a commit. Boundaries committed after the
+/// commit are almost always descendants of it, which can't be hidden without hiding the commit
+/// itself. Boundaries committed in the same second are only hidden if they are ancestors, as
+/// otherwise tags on diverging branches would each hide the commits they share from the other.
+pub fn get_likely_ancestors(
+    repo: &Repository,
+    commit: &Commit,
+    boundaries: &[Boundary],
+) -> Vec<Oid> {
+    let release_time = commit.time().seconds();
+    boundaries
+        .iter()
+        .filter(|boundary| boundary.id != commit.id())
+        .filter(|boundary| {
+            boundary.time < release_time
+                || (boundary.time == release_time
+                    && repo
+                        .graph_descendant_of(commit.id(), boundary.id)
+                        .unwrap_or(false))
+        })
+        .map(|boundary| boundary.id)
+        .collect()
+}
+
+/// Get the commits in a release: the tagged commit and its ancestors, up to a maximum depth,
+/// excluding anything reachable from `boundaries` (e.g. the commits of previous tags). A maximum
+/// depth of 0 means there is no limit.
+pub fn get_release_commits(
+    repo: &Repository,
+    commit: &Commit,
+    boundaries: &[Boundary],
+    max_depth: usize,
+    first_parent: bool,
+) -> Result<Vec<CommitDepthInfo>, git2::Error> {
+    Ok(walk_release(repo, commit, boundaries, max_depth, first_parent)?.commits)
+}
+
+/// The commits in a release, and the boundaries which were hidden to find them.
+pub struct ReleaseWalk {
+    pub commits: Vec<CommitDepthInfo>,
+    pub hidden: Vec<Oid>,
+}
+
+/// Walk the commits in a release as [`get_release_commits`] does, also returning the boundaries
+/// which were hidden, which are the likely ancestors unless one of them was a descendant.
+pub fn walk_release(
+    repo: &Repository,
+    commit: &Commit,
+    boundaries: &[Boundary],
+    max_depth: usize,
+    first_parent: bool,
+) -> Result<ReleaseWalk, git2::Error> {
+    // Clock skew can cause the likely ancestors to include a descendant, so fall back to
+    // checking the ancestry of every boundary.
+    let likely_ancestors = get_likely_ancestors(repo, commit, boundaries);
+    if let Some(commits) =
+        walk_release_commits(repo, commit, &likely_ancestors, max_depth, first_parent)?
+    {
+        return Ok(ReleaseWalk {
+            commits,
+            hidden: likely_ancestors,
+        });
+    }
+    debug!(commit = %commit.id(), "a likely ancestor was a descendant, checking every boundary");
+
+    let release_time = commit.time().seconds();
+    let mut ancestors = Vec::new();
+    for boundary in boundaries
+        .iter()
+        .filter(|boundary| boundary.id != commit.id())
+    {
+        // Diverging boundaries are only hidden if they are older, for the same reason as above
+        let hide = match boundary.time < release_time {
+            true => !repo.graph_descendant_of(boundary.id, commit.id())?,
+            false => repo.graph_descendant_of(commit.id(), boundary.id)?,
+        };
+        if hide {
+            ancestors.push(boundary.id);
+        }
+    }
+    let commits = walk_release_commits(repo, commit, &ancestors, max_depth, first_parent)?
+        .unwrap_or_default();
+    Ok(ReleaseWalk {
+        commits,
+        hidden: ancestors,
+    })
+}
+
+/// Walk the commits in a release, returning `None` if the tagged commit itself was hidden.
+fn walk_release_commits(
+    repo: &Repository,
+    commit: &Commit,
+    hide: &[Oid],
+    max_depth: usize,
+    first_parent: bool,
+) -> Result<Option<Vec<CommitDepthInfo>>, git2::Error> {
+    let max_depth = if max_depth == 0 {
+        usize::MAX
+    } else {
+        max_depth
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    if first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.push(commit.id())?;
+    for id in hide {
+        revwalk.hide(*id)?;
+    }
+    let hidden = hide.iter().copied().collect::<HashSet<_>>();
+
+    // Topological order visits children before their parents, so the shortest distance to a
+    // commit is known by the time it is visited. Stop once there are no more commits within
+    // the maximum depth left to visit.
+    let mut depths = HashMap::from([(commit.id(), 0)]);
+    let mut pending = HashSet::from([commit.id()]);
+    let mut commits = Vec::new();
+
+    for id in revwalk {
+        let id = id?;
+        if commits.is_empty() && id != commit.id() {
+            return Ok(None);
+        }
+        if !pending.remove(&id) {
+            continue;
+        }
+
+        let depth = depths[&id];
+        if depth == max_depth {
+            trace!(commit = %id, "not searching parents beyond --depth");
+        }
+        if depth < max_depth {
+            let parent_count = if first_parent { 1 } else { usize::MAX };
+            for parent_id in repo.find_commit(id)?.parent_ids().take(parent_count) {
+                if hidden.contains(&parent_id) {
+                    continue;
+                }
+                let parent_depth = depths.entry(parent_id).or_insert(depth + 1);
+                *parent_depth = (*parent_depth).min(depth + 1);
+                pending.insert(parent_id);
+            }
+        }
+        commits.push(CommitDepthInfo { id, depth });
+
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    if commits.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(commits))
+}
+
+pub fn commit_is_within_duration(commit: &Commit, max_age: std::time::Duration) -> bool {
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        let commit_time = commit.time().seconds();
+        let now_seconds = now.as_secs();
+
+        let diff_seconds = now_seconds - commit_time as u64;
+        return diff_seconds < max_age.as_secs();
+    }
+    true
+}
+
+/// The name of a tag for showing in errors, with any bytes which aren't valid UTF-8 replaced.
+pub fn tag_name_lossy(tag: &Tag) -> String {
+    String::from_utf8_lossy(tag.name_bytes()).into_owned()
+}
+
+/// The time a tag was created, falling back to the time of the tagged commit
+/// for tags without a tagger signature.
+pub fn tag_time(tag: &Tag, commit: &Commit) -> i64 {
+    tag.tagger()
+        .map(|tagger| tagger.when().seconds())
+        .unwrap_or_else(|| commit.time().seconds())
+}
+
+/// The git note attached to an object, such as a commit or tag, in a notes ref such as
+/// `refs/notes/commits`. Refs without a `refs/` prefix are under `refs/notes/`, as for
+/// `git notes --ref`.
+pub fn git_note(repo: &Repository, notes_ref: &str, id: Oid) -> Option<String> {
+    let notes_ref = match notes_ref.starts_with("refs/") {
+        true => notes_ref.to_owned(),
+        false => format!("refs/notes/{}", notes_ref),
+    };
+    let note = repo.find_note(Some(&notes_ref), id).ok()?;
+    Some(note.message()?.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    /// A repository in a new temporary directory, removed when the test ends.
+    struct TempRepo {
+        repo: Repository,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let path = env::temp_dir().join(format!("rels-test-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            TempRepo {
+                repo: Repository::init(&path).expect("the repository should be created"),
+            }
+        }
+
+        /// Commit with no changes at a time in seconds, without moving any branch.
+        fn commit(&self, message: &str, time: i64, parents: &[Oid]) -> Oid {
+            let signature = Signature::new("Alice", "alice@example.com", &Time::new(time, 0))
+                .expect("the signature should be valid");
+            let tree_id = self
+                .repo
+                .treebuilder(None)
+                .and_then(|builder| builder.write())
+                .expect("the tree should be written");
+            let tree = self.repo.find_tree(tree_id).expect("the tree should exist");
+            let parents = parents
+                .iter()
+                .map(|id| self.repo.find_commit(*id).expect("the parent should exist"))
+                .collect::<Vec<_>>();
+            let parents = parents.iter().collect::<Vec<_>>();
+            self.repo
+                .commit(None, &signature, &signature, message, &tree, &parents)
+                .expect("the commit should be created")
+        }
+
+        fn boundary(&self, id: Oid) -> Boundary {
+            let commit = self.repo.find_commit(id).expect("the commit should exist");
+            Boundary {
+                id,
+                time: commit.time().seconds(),
+            }
+        }
+
+        fn release_commits(&self, id: Oid, boundaries: &[Boundary]) -> HashSet<Oid> {
+            let commit = self.repo.find_commit(id).expect("the commit should exist");
+            get_release_commits(&self.repo, &commit, boundaries, 0, false)
+                .expect("the release should be walked")
+                .into_iter()
+                .map(|info| info.id)
+                .collect()
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            if let Some(path) = self.repo.workdir() {
+                let _ = std::fs::remove_dir_all(path);
+            }
+        }
+    }
+
+    #[test]
+    fn diverging_tags_committed_in_the_same_second_keep_their_shared_commits() {
+        let temp = TempRepo::new("same-second");
+        let shared = temp.commit("ABC-1 shared", 1_000, &[]);
+        let release_branch = temp.commit("ABC-2 release branch", 2_000, &[shared]);
+        let main_branch = temp.commit("ABC-3 main branch", 2_000, &[shared]);
+        let boundaries = [temp.boundary(release_branch), temp.boundary(main_branch)];
+
+        let release = temp.release_commits(release_branch, &boundaries);
+        let main = temp.release_commits(main_branch, &boundaries);
+
+        assert_eq!(release, HashSet::from([release_branch, shared]));
+        assert_eq!(main, HashSet::from([main_branch, shared]));
+    }
+
+    #[test]
+    fn older_tags_on_diverging_branches_hide_their_shared_commits() {
+        let temp = TempRepo::new("older");
+        let shared = temp.commit("ABC-1 shared", 1_000, &[]);
+        let release_branch = temp.commit("ABC-2 release branch", 2_000, &[shared]);
+        let main_branch = temp.commit("ABC-3 main branch", 3_000, &[shared]);
+        let boundaries = [temp.boundary(release_branch), temp.boundary(main_branch)];
+
+        assert_eq!(
+            temp.release_commits(main_branch, &boundaries),
+            HashSet::from([main_branch])
+        );
+    }
+
+    #[test]
+    fn skewed_descendants_are_not_hidden_from_the_release() {
+        let temp = TempRepo::new("skewed");
+        let tagged = temp.commit("ABC-1 tagged", 2_000, &[]);
+        let skewed = temp.commit("ABC-2 committed with a slow clock", 1_000, &[tagged]);
+        let commit = temp
+            .repo
+            .find_commit(tagged)
+            .expect("the commit should exist");
+
+        let walk = walk_release(&temp.repo, &commit, &[temp.boundary(skewed)], 0, false)
+            .expect("the release should be walked");
+
+        assert!(walk.hidden.is_empty());
+        assert_eq!(
+            walk.commits.iter().map(|info| info.id).collect::<Vec<_>>(),
+            [tagged]
+        );
+    }
+
+    #[test]
+    fn boundaries_on_diverging_branches_are_not_linear() {
+        let temp = TempRepo::new("linear");
+        let first = temp.commit("ABC-1 first", 1_000, &[]);
+        let second = temp.commit("ABC-2 second", 2_000, &[first]);
+        let diverging = temp.commit("ABC-3 diverging", 3_000, &[first]);
+
+        let linear = [temp.boundary(second), temp.boundary(first)];
+        let diverging = [
+            temp.boundary(second),
+            temp.boundary(first),
+            temp.boundary(diverging),
+        ];
+
+        assert!(boundaries_are_linear(&temp.repo, &linear));
+        assert!(!boundaries_are_linear(&temp.repo, &diverging));
+    }
+
+    #[test]
+    fn glob_wildcards_match_any_text_or_a_single_character() {
+        let regex = glob_regex("v*.?");
+
+        assert!(regex.is_match("v1.2"));
+        assert!(regex.is_match("v.1"));
+        assert!(!regex.is_match("v1.23"));
+        assert!(!regex.is_match("release-v1.2"));
+    }
+
+    #[test]
+    fn glob_classes_match_characters_in_or_not_in_the_brackets() {
+        let included = glob_regex("v[0-2].0");
+        let excluded = glob_regex("v[!0-2].0");
+
+        assert!(included.is_match("v1.0"));
+        assert!(!included.is_match("v3.0"));
+        assert!(excluded.is_match("v3.0"));
+        assert!(!excluded.is_match("v1.0"));
+    }
+
+    #[test]
+    fn glob_patterns_match_regex_characters_literally() {
+        assert!(glob_regex("v1.0+build").is_match("v1.0+build"));
+        assert!(!glob_regex("v1.0").is_match("v1x0"));
+        assert!(glob_regex("v[1").is_match("v[1"));
+        assert!(glob_regex("v[z-a]").is_match("v[z-a]"));
+    }
+}
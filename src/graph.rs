@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use git2::{Oid, Repository};
+
+use crate::releases::CommitTagInfo;
+
+/// The parents a commit is drawn connected to by `--graph`: its nearest ancestors in the same
+/// release which are shown, looking through commits which aren't, e.g. because they don't
+/// reference a ticket. The first parent comes first.
+pub fn graph_parents(
+    repo: &Repository,
+    commit_to_tag: &HashMap<Oid, CommitTagInfo>,
+    release_ids: &HashSet<Oid>,
+    id: Oid,
+    first_parent: bool,
+) -> Result<Vec<Oid>, git2::Error> {
+    let parent_ids = |id: Oid| -> Result<Vec<Oid>, git2::Error> {
+        let commit = repo.find_commit(id)?;
+        Ok(match first_parent {
+            true => commit.parent_ids().take(1).collect(),
+            false => commit.parent_ids().collect(),
+        })
+    };
+
+    let mut parents = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from(parent_ids(id)?);
+    while let Some(parent) = queue.pop_front() {
+        if !release_ids.contains(&parent) || !visited.insert(parent) {
+            continue;
+        }
+        if commit_to_tag.contains_key(&parent) {
+            parents.push(parent);
+        } else {
+            queue.extend(parent_ids(parent)?);
+        }
+    }
+    Ok(parents)
+}
+
+/// A line of the graph of a release, which shows a commit or connects the lines of commits.
+pub struct GraphRow<'a> {
+    pub graph: String,
+    pub commit: Option<&'a CommitTagInfo>,
+}
+
+/// Sort commits so each comes before its parents, newest first where there is a choice.
+fn topological_order<'a>(
+    commits: &[&'a CommitTagInfo],
+    parents: &HashMap<Oid, Vec<Oid>>,
+) -> Vec<&'a CommitTagInfo> {
+    let mut children = HashMap::<Oid, usize>::new();
+    for commit in commits {
+        for parent in parents.get(&commit.id).into_iter().flatten() {
+            *children.entry(*parent).or_default() += 1;
+        }
+    }
+    let mut ready = commits
+        .iter()
+        .filter(|commit| !children.contains_key(&commit.id))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut ordered = Vec::with_capacity(commits.len());
+    while let Some(index) = (0..ready.len()).max_by_key(|index| ready[*index].time) {
+        let commit = ready.swap_remove(index);
+        ordered.push(commit);
+        for parent in parents.get(&commit.id).into_iter().flatten() {
+            let remaining = children.get_mut(parent).expect("parents should be counted");
+            *remaining -= 1;
+            if *remaining == 0 {
+                ready.extend(commits.iter().find(|commit| commit.id == *parent));
+            }
+        }
+    }
+    ordered
+}
+
+/// Draw a line of the graph from the characters at each position, where column `n` of the
+/// graph is at position `2n` and lines between columns are drawn in between.
+fn draw(width: usize, marks: impl IntoIterator<Item = (usize, char)>) -> String {
+    let mut line = vec![' '; width.max(1) * 2];
+    for (position, mark) in marks {
+        if let Some(char) = line.get_mut(position) {
+            *char = mark;
+        }
+    }
+    line.into_iter().collect::<String>().trim_end().to_owned()
+}
+
+/// Lay out the commits of a release as a graph of their ancestry, like `git log --graph`, with
+/// a line per commit and lines where branches fork and merge. Parents which aren't among the
+/// commits, e.g. because they don't match `--filter`, aren't drawn.
+pub fn graph_rows<'a>(
+    commits: &[&'a CommitTagInfo],
+    parents: &HashMap<Oid, Vec<Oid>>,
+) -> Vec<GraphRow<'a>> {
+    let shown = commits
+        .iter()
+        .map(|commit| commit.id)
+        .collect::<HashSet<_>>();
+    let parents = commits
+        .iter()
+        .map(|commit| {
+            let commit_parents = parents.get(&commit.id).into_iter().flatten();
+            let commit_parents = commit_parents.filter(|parent| shown.contains(parent));
+            (commit.id, commit_parents.copied().collect::<Vec<_>>())
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut rows = Vec::new();
+    let connector = |graph: String| GraphRow {
+        graph,
+        commit: None,
+    };
+    // The commit each line of the graph leads to next
+    let mut columns = Vec::<Oid>::new();
+    for commit in topological_order(commits, &parents) {
+        let column = match columns.iter().position(|id| *id == commit.id) {
+            Some(column) => column,
+            None => {
+                columns.push(commit.id);
+                columns.len() - 1
+            }
+        };
+        // Join other lines leading to this commit into its line
+        while let Some(other) = columns.iter().rposition(|id| *id == commit.id) {
+            if other == column {
+                break;
+            }
+            rows.push(connector(draw(
+                columns.len(),
+                (0..columns.len()).map(|index| match index < other {
+                    true => (index * 2, '|'),
+                    false => (index * 2 - 1, '/'),
+                }),
+            )));
+            columns.remove(other);
+        }
+
+        rows.push(GraphRow {
+            graph: draw(
+                columns.len(),
+                (0..columns.len())
+                    .map(|index| (index * 2, if index == column { '*' } else { '|' })),
+            ),
+            commit: Some(commit),
+        });
+
+        let commit_parents = &parents[&commit.id];
+        match commit_parents.first() {
+            Some(first) => columns[column] = *first,
+            None => {
+                columns.remove(column);
+                if column < columns.len() {
+                    // Move the lines to the right of the branch which ended into its place
+                    rows.push(connector(draw(
+                        columns.len() + 1,
+                        (0..=columns.len()).filter(|index| *index != column).map(
+                            |index| match index < column {
+                                true => (index * 2, '|'),
+                                false => (index * 2 - 1, '/'),
+                            },
+                        ),
+                    )));
+                }
+            }
+        }
+        // Fork a new line for each other parent of a merge
+        for (offset, parent) in commit_parents.iter().skip(1).enumerate() {
+            // A parent already on another line is joined into it when it is reached
+            let new_column = column + 1 + offset;
+            columns.insert(new_column, *parent);
+            rows.push(connector(draw(
+                columns.len(),
+                (0..columns.len()).map(|index| match index < new_column {
+                    true => (index * 2, '|'),
+                    false => (index * 2 - 1, '\\'),
+                }),
+            )));
+        }
+    }
+    rows
+}
@@ -0,0 +1,105 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use git2::Repository;
+
+use crate::{config::HooksConfig, json::ReleaseJson, releases::TagCommitsError};
+
+/// Quote a value for the shell running hooks, so tags containing characters such as `;` or `$`
+/// are passed as a single argument.
+fn quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+/// Run a hook in the root of the repository, so scripts can be given relative to it, with
+/// `RELS_HOOK` set to its name and `RELS_TAG` to the tag of the release if it is for one.
+fn run_hook(
+    repo: &Repository,
+    hook: &'static str,
+    command: &str,
+    tag: Option<&str>,
+    input: Option<&[u8]>,
+) -> Result<(), TagCommitsError> {
+    let command = match tag {
+        Some(tag) => command.replace("{tag}", &quote(tag)),
+        None => command.to_owned(),
+    };
+    let mut shell = shell(&command);
+    if let Some(dir) = repo.workdir() {
+        shell.current_dir(dir);
+    }
+    shell.env("RELS_HOOK", hook);
+    if let Some(tag) = tag {
+        shell.env("RELS_TAG", tag);
+    }
+    let mut child = shell
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .spawn()
+        .map_err(|err| TagCommitsError::HookFailed(hook, err.to_string()))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // Hooks don't have to read the release, in which case they may exit before it is written
+        match stdin.write_all(input) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(TagCommitsError::HookFailed(hook, err.to_string()));
+            }
+            _ => {}
+        }
+    }
+    let status = child
+        .wait()
+        .map_err(|err| TagCommitsError::HookFailed(hook, err.to_string()))?;
+    if !status.success() {
+        return Err(TagCommitsError::HookFailed(
+            hook,
+            format!("{} ({})", command, status),
+        ));
+    }
+    Ok(())
+}
+
+/// Run the `pre` hook, if there is one, before the releases are read.
+pub fn run_pre_hook(repo: &Repository, hooks: &HooksConfig) -> Result<(), TagCommitsError> {
+    match &hooks.pre {
+        Some(command) => run_hook(repo, "pre", command, None, None),
+        None => Ok(()),
+    }
+}
+
+/// Run the `post` hook, if there is one, for each release listed, with the release as JSON on
+/// stdin, the same as `--format json` prints it. Stops at the first release it fails for.
+pub fn run_post_hooks(
+    repo: &Repository,
+    hooks: &HooksConfig,
+    releases: &[ReleaseJson],
+) -> Result<(), TagCommitsError> {
+    let Some(command) = &hooks.post else {
+        return Ok(());
+    };
+    for release in releases {
+        let json = serde_json::to_vec(release).expect("releases should serialize to JSON");
+        run_hook(repo, "post", command, Some(release.tag), Some(&json))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::debug;
+use ureq::{
+    http::Response,
+    tls::{parse_pem, Certificate, PemItem, RootCerts, TlsConfig},
+    Agent, Body, Proxy,
+};
+
+use crate::{cli::Args, releases::TagCommitsError};
+
+/// Parse a `--proxy` URL, e.g. `http://proxy.example.com:8080`, with `http://` assumed if it
+/// has no scheme.
+pub fn parse_proxy(url: &str) -> Result<Proxy, String> {
+    Proxy::new(url).map_err(|err| err.to_string())
+}
+
+/// Add the hosts listed in `NO_PROXY` to a proxy from `--proxy`, since ureq only reads it for
+/// proxies from the environment.
+fn with_no_proxy(proxy: &Proxy) -> Proxy {
+    let Some(no_proxy) = ["NO_PROXY", "no_proxy"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+    else {
+        return proxy.clone();
+    };
+
+    let mut builder = Proxy::builder(proxy.protocol())
+        .host(proxy.host())
+        .port(proxy.port())
+        .resolve_target(proxy.resolve_target());
+    if let Some(username) = proxy.username() {
+        builder = builder.username(username);
+    }
+    if let Some(password) = proxy.password() {
+        builder = builder.password(password);
+    }
+    for host in no_proxy
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+    {
+        builder = builder.no_proxy(host);
+    }
+    builder.build().unwrap_or_else(|_| proxy.clone())
+}
+
+/// Trust the certificates in a PEM file, e.g. an internal CA, as well as the usual root
+/// certificates.
+fn root_certs(path: &Path) -> Result<RootCerts, TagCommitsError> {
+    let read_error = |err| TagCommitsError::Io("read CA certificate", err);
+    let pem = fs::read(path).map_err(read_error)?;
+    let extra = parse_pem(&pem)
+        .filter_map(|item| match item {
+            Ok(PemItem::Certificate(cert)) => Some(Ok(cert)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| read_error(io::Error::new(io::ErrorKind::InvalidData, err.to_string())))?;
+    if extra.is_empty() {
+        return Err(read_error(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} doesn't contain any PEM certificates", path.display()),
+        )));
+    }
+
+    Ok(webpki_root_certs::TLS_SERVER_ROOT_CERTS
+        .iter()
+        .map(|cert| Certificate::from_der(cert).to_owned())
+        .chain(extra)
+        .into())
+}
+
+/// The timeout for each attempt at a request, so requests which hang are retried.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The delay before the first retry, which doubles for each retry after it.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Whether a request which failed may succeed if it is retried, e.g. when the server is
+/// overloaded or the connection dropped.
+fn is_transient(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(status) => *status == 429 || *status >= 500,
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::BodyStalled => true,
+        _ => false,
+    }
+}
+
+/// The longest to wait for a rate limit to reset before giving up on a request.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// How long a response asks to wait before making more requests, from `Retry-After` in seconds,
+/// or from `X-RateLimit-Reset` once `X-RateLimit-Remaining` reaches 0, as GitHub and GitLab do.
+fn rate_limit_wait(response: &Response<Body>) -> Option<Duration> {
+    let header = |name| response.headers().get(name)?.to_str().ok();
+    if let Some(seconds) = header("retry-after").and_then(|value| value.trim().parse().ok()) {
+        return Some(Duration::from_secs(seconds));
+    }
+    if header("x-ratelimit-remaining") != Some("0") {
+        return None;
+    }
+    let reset = header("x-ratelimit-reset")?.parse::<u64>().ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+/// How long to wait before a retry, doubling with each attempt, and randomized between half and
+/// all of that so that clients which failed together don't retry together.
+fn backoff(attempt: u32) -> Duration {
+    let delay = INITIAL_BACKOFF * 2u32.saturating_pow(attempt - 1);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    delay / 2 + delay.mul_f64(f64::from(nanos) / 2e9)
+}
+
+/// Makes requests to tracker and forge APIs, retrying transient failures.
+#[derive(Clone)]
+pub struct HttpClient {
+    agent: Agent,
+    max_attempts: u32,
+    /// When requests can be made again after a rate limit, shared by clones of the client so
+    /// that requests made at the same time all wait
+    paused_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl HttpClient {
+    /// A client for the network settings in the arguments. Requests go through `--proxy` if it
+    /// is given, or else a proxy from `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY`, except to the
+    /// hosts listed in `NO_PROXY`. Fails if the `--ca-cert` file can't be read.
+    pub fn new(args: &Args) -> Result<HttpClient, TagCommitsError> {
+        let proxy = match &args.proxy {
+            Some(proxy) => Some(with_no_proxy(proxy)),
+            None => Proxy::try_from_env(),
+        };
+
+        let mut tls = TlsConfig::builder().disable_verification(args.insecure);
+        if let Some(path) = &args.ca_cert {
+            tls = tls.root_certs(root_certs(path)?);
+        }
+
+        Ok(HttpClient {
+            agent: Agent::config_builder()
+                .proxy(proxy)
+                .tls_config(tls.build())
+                .timeout_global(Some(ATTEMPT_TIMEOUT))
+                // Error statuses are turned into errors by send, after checking for rate limits
+                .http_status_as_error(false)
+                .build()
+                .into(),
+            max_attempts: args.max_attempts,
+            paused_until: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Wait until any rate limit hit by another request has reset.
+    fn wait_for_rate_limit(&self) {
+        let paused_until = *self
+            .paused_until
+            .lock()
+            .expect("the lock shouldn't be poisoned");
+        if let Some(delay) =
+            paused_until.and_then(|until| until.checked_duration_since(Instant::now()))
+        {
+            thread::sleep(delay);
+        }
+    }
+
+    fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self
+            .paused_until
+            .lock()
+            .expect("the lock shouldn't be poisoned");
+        *paused_until = Some(paused_until.map_or(until, |paused| paused.max(until)));
+    }
+
+    /// Send a request, retrying it with exponential backoff if it fails with a server error or
+    /// network problem, up to `--max-attempts` times. Rate limits are waited out as the response
+    /// asks, pausing every request made with the client, unless that would take more than a
+    /// minute. The request is built by `request` for each attempt, since a request can only be
+    /// sent once.
+    pub fn send(
+        &self,
+        request: impl Fn(&Agent) -> Result<Response<Body>, ureq::Error>,
+    ) -> Result<Response<Body>, ureq::Error> {
+        let mut attempt = 1;
+        loop {
+            self.wait_for_rate_limit();
+            let (err, rate_limit) = match request(&self.agent) {
+                Ok(response)
+                    if !response.status().is_client_error()
+                        && !response.status().is_server_error() =>
+                {
+                    return Ok(response)
+                }
+                Ok(response) => (
+                    ureq::Error::StatusCode(response.status().as_u16()),
+                    rate_limit_wait(&response),
+                ),
+                Err(err) => (err, None),
+            };
+            if attempt >= self.max_attempts || !(rate_limit.is_some() || is_transient(&err)) {
+                return Err(err);
+            }
+
+            match rate_limit {
+                Some(delay) if delay > MAX_RATE_LIMIT_WAIT => return Err(err),
+                Some(delay) => {
+                    debug!("Rate limited, waiting {}s", delay.as_secs());
+                    self.pause(delay);
+                }
+                None => {
+                    let delay = backoff(attempt);
+                    debug!(
+                        "Request failed ({}), retrying in {}ms",
+                        err,
+                        delay.as_millis()
+                    );
+                    thread::sleep(delay);
+                }
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_with_each_attempt_between_half_and_all_of_the_delay() {
+        for attempt in 1..=4 {
+            let delay = INITIAL_BACKOFF * 2u32.pow(attempt - 1);
+            let backoff = backoff(attempt);
+
+            assert!(
+                backoff >= delay / 2,
+                "{:?} is under half of {:?}",
+                backoff,
+                delay
+            );
+            assert!(backoff <= delay, "{:?} is over {:?}", backoff, delay);
+        }
+    }
+
+    #[test]
+    fn backoff_does_not_overflow_after_many_attempts() {
+        assert!(backoff(64) >= INITIAL_BACKOFF / 2);
+    }
+
+    fn response(headers: &[(&str, &str)]) -> Response<Body> {
+        headers
+            .iter()
+            .fold(Response::builder(), |builder, (name, value)| {
+                builder.header(*name, *value)
+            })
+            .body(Body::builder().data(""))
+            .expect("the response should be valid")
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("the clock should be after the epoch")
+            .as_secs()
+    }
+
+    #[test]
+    fn retry_after_is_waited_for() {
+        let wait = rate_limit_wait(&response(&[("Retry-After", "30")]));
+
+        assert_eq!(wait, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn rate_limit_reset_is_waited_for_once_no_requests_remain() {
+        let reset = (now() + 120).to_string();
+        let exhausted = response(&[
+            ("X-RateLimit-Remaining", "0"),
+            ("X-RateLimit-Reset", &reset),
+        ]);
+        let remaining = response(&[
+            ("X-RateLimit-Remaining", "10"),
+            ("X-RateLimit-Reset", &reset),
+        ]);
+
+        let wait = rate_limit_wait(&exhausted).expect("the rate limit should be waited for");
+
+        assert!(wait > Duration::from_secs(110) && wait <= Duration::from_secs(120));
+        assert_eq!(rate_limit_wait(&remaining), None);
+    }
+
+    #[test]
+    fn rate_limits_which_have_reset_are_not_waited_for() {
+        let reset = (now() - 10).to_string();
+        let wait = rate_limit_wait(&response(&[
+            ("X-RateLimit-Remaining", "0"),
+            ("X-RateLimit-Reset", &reset),
+        ]));
+
+        assert_eq!(wait, Some(Duration::ZERO));
+        assert_eq!(rate_limit_wait(&response(&[])), None);
+    }
+}
@@ -0,0 +1,802 @@
+use chrono::DateTime;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{fs, io, path::PathBuf};
+
+use git2::Repository;
+
+use crate::{
+    cli::{Args, TransitionArgs},
+    config::{Config, ConfigError, JiraConfig},
+    forge::Remote,
+    http::HttpClient,
+    last_run::release_id,
+    lookups::{LookupError, StatusCategory, TicketApi, TicketDetails},
+    presets::TrackerPreset,
+    releases::{TagCommits, TagCommitsError},
+    tickets::{resolve_tracker_configs, Ticket, Trackers},
+};
+
+/// The REST API of a Jira instance, for looking up tickets and managing the versions they are
+/// released in.
+#[derive(Clone)]
+pub struct JiraApi {
+    http: HttpClient,
+    base_url: String,
+    /// The Authorization header, which is basic auth for Jira Cloud, or a bearer token for Jira
+    /// Data Center
+    authorization: String,
+}
+
+#[derive(Deserialize)]
+struct JiraIssue {
+    fields: JiraFields,
+}
+
+#[derive(Deserialize)]
+struct JiraFields {
+    summary: String,
+    status: Option<JiraStatus>,
+    issuetype: Option<JiraIssueType>,
+    assignee: Option<JiraUser>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JiraStatus {
+    name: String,
+    status_category: Option<JiraStatusCategory>,
+}
+
+#[derive(Deserialize)]
+struct JiraStatusCategory {
+    /// new, indeterminate, or done
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct JiraIssueType {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JiraUser {
+    display_name: String,
+}
+
+#[derive(Deserialize)]
+struct JiraComments {
+    comments: Vec<JiraComment>,
+}
+
+#[derive(Deserialize)]
+struct JiraComment {
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct JiraTransitions {
+    transitions: Vec<JiraTransition>,
+}
+
+/// A transition a ticket can make in its workflow, e.g. Release, to the status `to`.
+#[derive(Deserialize)]
+struct JiraTransition {
+    id: String,
+    name: String,
+    to: Option<JiraStatus>,
+}
+
+/// A version of a Jira project, which tickets are released in with their fix versions.
+#[derive(Deserialize)]
+struct JiraVersion {
+    id: String,
+    name: String,
+    #[serde(default)]
+    released: bool,
+}
+
+impl JiraApi {
+    pub fn new(http: HttpClient, base_url: &str, authorization: String) -> JiraApi {
+        JiraApi {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            authorization,
+        }
+    }
+
+    /// The URL of an endpoint of version 2 of the API, which is supported by both Jira Cloud
+    /// and Jira Data Center.
+    fn url(&self, path: &str) -> String {
+        format!("{}/rest/api/2/{}", self.base_url, path)
+    }
+
+    /// Look up a ticket, returning `None` if Jira doesn't know about it.
+    pub fn lookup(&self, id: &str) -> Result<Option<TicketDetails>, LookupError> {
+        let url = self.url(&format!(
+            "issue/{}?fields=summary,status,issuetype,assignee",
+            id
+        ));
+        let issue: JiraIssue = match self.http.send(|agent| {
+            agent
+                .get(&url)
+                .header("Authorization", &self.authorization)
+                .header("Accept", "application/json")
+                .call()
+        }) {
+            Ok(mut response) => response.body_mut().read_json()?,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let status = issue.fields.status;
+        Ok(Some(TicketDetails {
+            title: issue.fields.summary,
+            status_category: status
+                .as_ref()
+                .and_then(|status| status.status_category.as_ref())
+                .and_then(|category| match category.key.as_str() {
+                    "new" => Some(StatusCategory::ToDo),
+                    "indeterminate" => Some(StatusCategory::InProgress),
+                    "done" => Some(StatusCategory::Done),
+                    _ => None,
+                }),
+            status: status.map(|status| status.name),
+            ticket_type: issue.fields.issuetype.map(|issue_type| issue_type.name),
+            assignee: issue.fields.assignee.map(|user| user.display_name),
+        }))
+    }
+
+    fn project_versions(&self, project: &str) -> Result<Vec<JiraVersion>, LookupError> {
+        let url = self.url(&format!("project/{}/versions", project));
+        Ok(self
+            .http
+            .send(|agent| {
+                agent
+                    .get(&url)
+                    .header("Authorization", &self.authorization)
+                    .header("Accept", "application/json")
+                    .call()
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn create_version(
+        &self,
+        project: &str,
+        name: &str,
+        release_date: Option<&str>,
+        released: bool,
+    ) -> Result<JiraVersion, LookupError> {
+        let url = self.url("version");
+        let mut version = json!({ "project": project, "name": name, "released": released });
+        if let Some(date) = release_date {
+            version["releaseDate"] = json!(date);
+        }
+        Ok(self
+            .http
+            .send(|agent| {
+                agent
+                    .post(&url)
+                    .header("Authorization", &self.authorization)
+                    .send_json(&version)
+            })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn release_version(&self, id: &str, release_date: Option<&str>) -> Result<(), LookupError> {
+        let url = self.url(&format!("version/{}", id));
+        let mut version = json!({ "released": true });
+        if let Some(date) = release_date {
+            version["releaseDate"] = json!(date);
+        }
+        self.http.send(|agent| {
+            agent
+                .put(&url)
+                .header("Authorization", &self.authorization)
+                .send_json(&version)
+        })?;
+        Ok(())
+    }
+
+    /// The text of the comments on a ticket.
+    fn comments(&self, key: &str) -> Result<Vec<String>, LookupError> {
+        let url = self.url(&format!("issue/{}/comment?maxResults=1000", key));
+        let comments: JiraComments = self
+            .http
+            .send(|agent| {
+                agent
+                    .get(&url)
+                    .header("Authorization", &self.authorization)
+                    .header("Accept", "application/json")
+                    .call()
+            })?
+            .body_mut()
+            .read_json()?;
+        Ok(comments
+            .comments
+            .into_iter()
+            .map(|comment| comment.body)
+            .collect())
+    }
+
+    fn add_comment(&self, key: &str, body: &str) -> Result<(), LookupError> {
+        let url = self.url(&format!("issue/{}/comment", key));
+        self.http.send(|agent| {
+            agent
+                .post(&url)
+                .header("Authorization", &self.authorization)
+                .send_json(json!({ "body": body }))
+        })?;
+        Ok(())
+    }
+
+    /// The transitions a ticket can currently make.
+    fn transitions(&self, key: &str) -> Result<Vec<JiraTransition>, LookupError> {
+        let url = self.url(&format!("issue/{}/transitions", key));
+        let transitions: JiraTransitions = self
+            .http
+            .send(|agent| {
+                agent
+                    .get(&url)
+                    .header("Authorization", &self.authorization)
+                    .header("Accept", "application/json")
+                    .call()
+            })?
+            .body_mut()
+            .read_json()?;
+        Ok(transitions.transitions)
+    }
+
+    fn transition(&self, key: &str, transition_id: &str) -> Result<(), LookupError> {
+        let url = self.url(&format!("issue/{}/transitions", key));
+        self.http.send(|agent| {
+            agent
+                .post(&url)
+                .header("Authorization", &self.authorization)
+                .send_json(json!({ "transition": { "id": transition_id } }))
+        })?;
+        Ok(())
+    }
+
+    /// Add a version to a ticket's fix versions, keeping any it already has.
+    fn add_fix_version(&self, key: &str, version: &str) -> Result<(), LookupError> {
+        let url = self.url(&format!("issue/{}", key));
+        let update = json!({ "update": { "fixVersions": [{ "add": { "name": version } }] } });
+        self.http.send(|agent| {
+            agent
+                .put(&url)
+                .header("Authorization", &self.authorization)
+                .send_json(&update)
+        })?;
+        Ok(())
+    }
+}
+
+/// The API of the configured Jira tracker, with the credentials `--lookup` uses.
+pub fn jira_api(
+    config: &Config,
+    args: &Args,
+    repo: &Repository,
+) -> Result<JiraApi, TagCommitsError> {
+    let tracker = resolve_tracker_configs(config, args, repo)?
+        .into_iter()
+        .find(|tracker| tracker.preset == Some(TrackerPreset::Jira))
+        .ok_or_else(|| ConfigError::Invalid("no jira tracker is configured".to_owned()))?;
+    match TrackerPreset::Jira.api(&tracker, &HttpClient::new(args)?)? {
+        Some(TicketApi::Jira(api)) => Ok(api),
+        _ => unreachable!("the jira preset should have a Jira API"),
+    }
+}
+
+/// The tickets of Jira trackers, leaving out tickets of any other trackers.
+fn jira_tickets<'a>(
+    trackers: &'a Trackers,
+    tickets: &'a [&'a Ticket],
+) -> impl Iterator<Item = &'a Ticket> + 'a {
+    tickets
+        .iter()
+        .copied()
+        .filter(|ticket| trackers.preset(ticket) == Some(TrackerPreset::Jira))
+}
+
+/// The Jira version for a release in one project.
+pub struct VersionReport {
+    pub project: String,
+    pub version: String,
+    /// Whether the version was created, rather than already existing
+    pub created: bool,
+    /// Whether the version was marked as released
+    pub released: bool,
+    /// The tickets the version was added to as a fix version
+    pub tickets: Vec<String>,
+}
+
+/// Create a version named after a release in each Jira project with tickets in it, and add it
+/// to the fix versions of those tickets. Versions which already exist are reused, so this can
+/// be run again after more tickets are found. If `release` is set, the versions are also marked
+/// as released on the date of the tag.
+pub fn create_versions(
+    api: &JiraApi,
+    trackers: &Trackers,
+    tag_name: &str,
+    tag_time: Option<i64>,
+    tickets: &[&Ticket],
+    release: bool,
+) -> Result<Vec<VersionReport>, TagCommitsError> {
+    let release_date = tag_time
+        .and_then(|time| DateTime::from_timestamp(time, 0))
+        .map(|time| time.format("%Y-%m-%d").to_string());
+
+    let mut projects: Vec<(&str, Vec<&str>)> = Vec::new();
+    for ticket in jira_tickets(trackers, tickets) {
+        let project = trackers.project_key(ticket);
+        match projects.iter_mut().find(|(key, _)| *key == project) {
+            Some((_, keys)) => keys.push(&ticket.id),
+            None => projects.push((project, vec![&ticket.id])),
+        }
+    }
+
+    let api_error = |action| move |err| TagCommitsError::Api(action, err);
+    let mut reports = Vec::new();
+    for (project, keys) in projects {
+        let existing = api
+            .project_versions(project)
+            .map_err(api_error("list Jira versions"))?
+            .into_iter()
+            .find(|version| version.name == tag_name);
+        let created = existing.is_none();
+        let version = match existing {
+            Some(version) => version,
+            None => api
+                .create_version(project, tag_name, release_date.as_deref(), release)
+                .map_err(api_error("create Jira version"))?,
+        };
+        if release && !version.released {
+            api.release_version(&version.id, release_date.as_deref())
+                .map_err(api_error("release Jira version"))?;
+        }
+        for key in &keys {
+            api.add_fix_version(key, &version.name)
+                .map_err(api_error("set fix version"))?;
+        }
+        reports.push(VersionReport {
+            project: project.to_owned(),
+            version: version.name,
+            created,
+            released: release || version.released,
+            tickets: keys.into_iter().map(str::to_owned).collect(),
+        });
+    }
+    Ok(reports)
+}
+
+pub fn print_version_reports(tag_name: &str, reports: &[VersionReport]) {
+    if reports.is_empty() {
+        println!("{}", format!("{} has no Jira tickets", tag_name).dimmed());
+        return;
+    }
+
+    for report in reports {
+        let action = if report.created { "Created" } else { "Updated" };
+        let released = if report.released { " (released)" } else { "" };
+        println!(
+            "{} {} {}{}",
+            action,
+            report.project.bold(),
+            report.version.green().bold(),
+            released.dimmed()
+        );
+        println!("  Fix version of {}", report.tickets.join(", "));
+    }
+}
+
+/// The comment posted on tickets by `rels jira comment` if there is a release page to link to.
+const DEFAULT_COMMENT_TEMPLATE: &str = "Shipped in {tag}, see {url}";
+
+/// The comment posted on tickets when there isn't a release page to link to.
+const DEFAULT_COMMENT_TEMPLATE_WITHOUT_URL: &str = "Shipped in {tag}";
+
+/// What happened to a ticket's comment.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CommentOutcome {
+    Commented,
+    /// The ticket already had the same comment, e.g. from an earlier run
+    AlreadyCommented,
+    /// The comment would have been posted, but `--dry-run` was given
+    WouldComment,
+}
+
+pub struct CommentReport {
+    pub ticket: String,
+    pub comment: String,
+    pub outcome: CommentOutcome,
+}
+
+/// Fill in a comment template for a ticket. `{tag}` is replaced with the tag, `{ticket}` with the
+/// ticket, and `{url}` with the release's page on the origin remote, which must exist if the
+/// template uses it.
+fn render_comment(
+    template: &str,
+    tag_name: &str,
+    ticket: &str,
+    release_url: Option<&str>,
+) -> Result<String, TagCommitsError> {
+    let comment = template
+        .replace("{tag}", tag_name)
+        .replace("{ticket}", ticket);
+    match release_url {
+        Some(url) => Ok(comment.replace("{url}", url)),
+        None if comment.contains("{url}") => Err(ConfigError::Invalid(
+            "{url} in the comment template needs an origin remote on GitHub or GitLab".to_owned(),
+        )
+        .into()),
+        None => Ok(comment),
+    }
+}
+
+/// Comment on each Jira ticket in a release, skipping tickets which already have the same
+/// comment so that runs can be repeated. Comments use `template`, or a default which links to
+/// the release page on the origin remote if there is one. With `dry_run`, nothing is posted.
+pub fn comment_tickets(
+    api: &JiraApi,
+    trackers: &Trackers,
+    origin: Option<&Remote>,
+    tag_name: &str,
+    tickets: &[&Ticket],
+    template: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<CommentReport>, TagCommitsError> {
+    let release_url = origin.and_then(|remote| remote.release_url(tag_name));
+    let template = template.unwrap_or(match release_url {
+        Some(_) => DEFAULT_COMMENT_TEMPLATE,
+        None => DEFAULT_COMMENT_TEMPLATE_WITHOUT_URL,
+    });
+
+    let mut reports = Vec::new();
+    for ticket in jira_tickets(trackers, tickets) {
+        let comment = render_comment(template, tag_name, &ticket.id, release_url.as_deref())?;
+        let already_commented = api
+            .comments(&ticket.id)
+            .map_err(|err| TagCommitsError::Api("list Jira comments", err))?
+            .iter()
+            .any(|existing| existing.trim() == comment.trim());
+        let outcome = if already_commented {
+            CommentOutcome::AlreadyCommented
+        } else if dry_run {
+            CommentOutcome::WouldComment
+        } else {
+            api.add_comment(&ticket.id, &comment)
+                .map_err(|err| TagCommitsError::Api("add Jira comment", err))?;
+            CommentOutcome::Commented
+        };
+        reports.push(CommentReport {
+            ticket: ticket.id.clone(),
+            comment,
+            outcome,
+        });
+    }
+    Ok(reports)
+}
+
+pub fn print_comment_reports(tag_name: &str, reports: &[CommentReport]) {
+    if reports.is_empty() {
+        println!("{}", format!("{} has no Jira tickets", tag_name).dimmed());
+        return;
+    }
+
+    for report in reports {
+        match report.outcome {
+            CommentOutcome::Commented => {
+                println!("Commented on {}: {}", report.ticket.bold(), report.comment)
+            }
+            CommentOutcome::WouldComment => {
+                println!(
+                    "Would comment on {}: {}",
+                    report.ticket.bold(),
+                    report.comment
+                )
+            }
+            CommentOutcome::AlreadyCommented => println!(
+                "{}",
+                format!("Already commented on {}", report.ticket).dimmed()
+            ),
+        }
+    }
+}
+
+/// What happened to a ticket when transitioning a release's tickets.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransitionOutcome {
+    Transitioned,
+    /// The ticket would have been transitioned, but `--dry-run` was given
+    WouldTransition,
+    /// The ticket is already in the release status
+    AlreadyInStatus,
+    /// The ticket's workflow has no transition to the release status from its current status
+    NoTransition,
+    /// No release status is configured for the ticket's project
+    NoStatus,
+    /// Jira doesn't have the ticket, e.g. because of a typo in the commit message
+    NotFound,
+}
+
+pub struct TransitionReport {
+    pub ticket: String,
+    /// The status the ticket is moved to, if its project has one
+    pub status: Option<String>,
+    pub outcome: TransitionOutcome,
+}
+
+/// The transitions shown by the last `rels jira transition --dry-run`, which must match the
+/// transitions about to be made, so that tickets are never transitioned without a preview.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct TransitionPreview {
+    /// The release as `<tag name>@<tagged commit>`, so that moving the tag needs a new preview
+    release: String,
+    /// Each ticket with the status it is moved to
+    transitions: Vec<(String, String)>,
+}
+
+fn preview_file(repo: &Repository) -> PathBuf {
+    repo.path()
+        .join("rels-cache")
+        .join("jira-transition-preview.json")
+}
+
+impl TransitionPreview {
+    fn load(repo: &Repository) -> Option<TransitionPreview> {
+        serde_json::from_slice(&fs::read(preview_file(repo)).ok()?).ok()
+    }
+
+    fn save(&self, repo: &Repository) -> io::Result<()> {
+        let path = preview_file(repo);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    fn remove(repo: &Repository) -> io::Result<()> {
+        match fs::remove_file(preview_file(repo)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Move each Jira ticket in a release to its project's release status from `config`, or
+/// `--to` if given, with whichever transition of its workflow leads there. Each project with
+/// tickets in the release must have a released version named after its tag. With `--dry-run`,
+/// the transitions are only saved as a preview, which must be made before the same transitions
+/// can be made for real.
+pub fn transition_tickets(
+    api: &JiraApi,
+    config: &JiraConfig,
+    trackers: &Trackers,
+    repo: &Repository,
+    tag_commits: &TagCommits,
+    tickets: &[&Ticket],
+    transition_args: &TransitionArgs,
+) -> Result<Vec<TransitionReport>, TagCommitsError> {
+    let status = transition_args.to.as_deref();
+    if status.is_none()
+        && config.release_status.is_none()
+        && config.project_release_statuses.is_empty()
+    {
+        return Err(ConfigError::Invalid(
+            "set release_status in the [jira] section, or pass --to, to transition tickets"
+                .to_owned(),
+        )
+        .into());
+    }
+
+    let api_error = |action| move |err| TagCommitsError::Api(action, err);
+    let mut projects = Vec::new();
+    for ticket in jira_tickets(trackers, tickets) {
+        let project = trackers.project_key(ticket);
+        if !projects.contains(&project) {
+            projects.push(project);
+        }
+    }
+    for project in projects {
+        let released = api
+            .project_versions(project)
+            .map_err(api_error("list Jira versions"))?
+            .into_iter()
+            .any(|version| version.name == transition_args.tag && version.released);
+        if !released {
+            return Err(TagCommitsError::UnreleasedVersion(
+                transition_args.tag.clone(),
+                project.to_owned(),
+            ));
+        }
+    }
+
+    let mut reports = Vec::new();
+    let mut transitions = Vec::new();
+    for ticket in jira_tickets(trackers, tickets) {
+        let Some(status) = status.or_else(|| config.release_status(trackers.project_key(ticket)))
+        else {
+            reports.push(TransitionReport {
+                ticket: ticket.id.clone(),
+                status: None,
+                outcome: TransitionOutcome::NoStatus,
+            });
+            continue;
+        };
+
+        let details = api
+            .lookup(&ticket.id)
+            .map_err(api_error("look up Jira ticket"))?;
+        let outcome = match details.map(|details| details.status) {
+            None => TransitionOutcome::NotFound,
+            Some(Some(current)) if current.eq_ignore_ascii_case(status) => {
+                TransitionOutcome::AlreadyInStatus
+            }
+            Some(_) => {
+                let transition = api
+                    .transitions(&ticket.id)
+                    .map_err(api_error("list Jira transitions"))?
+                    .into_iter()
+                    .find(|transition| {
+                        transition
+                            .to
+                            .as_ref()
+                            .is_some_and(|to| to.name.eq_ignore_ascii_case(status))
+                            || transition.name.eq_ignore_ascii_case(status)
+                    });
+                match transition {
+                    Some(transition) => {
+                        transitions.push((ticket.id.clone(), status.to_owned(), transition.id));
+                        TransitionOutcome::WouldTransition
+                    }
+                    None => TransitionOutcome::NoTransition,
+                }
+            }
+        };
+        reports.push(TransitionReport {
+            ticket: ticket.id.clone(),
+            status: Some(status.to_owned()),
+            outcome,
+        });
+    }
+
+    let preview = TransitionPreview {
+        release: release_id(&transition_args.tag, tag_commits),
+        transitions: transitions
+            .iter()
+            .map(|(ticket, status, _)| (ticket.clone(), status.clone()))
+            .collect(),
+    };
+    if transition_args.dry_run {
+        preview
+            .save(repo)
+            .map_err(|err| TagCommitsError::Io("save transition preview", err))?;
+        return Ok(reports);
+    }
+    if transitions.is_empty() {
+        return Ok(reports);
+    }
+    if TransitionPreview::load(repo).as_ref() != Some(&preview) {
+        return Err(TagCommitsError::PreviewRequired(
+            transition_args.tag.clone(),
+        ));
+    }
+
+    for (ticket, _, id) in &transitions {
+        api.transition(ticket, id)
+            .map_err(api_error("transition Jira ticket"))?;
+        if let Some(report) = reports.iter_mut().find(|report| report.ticket == *ticket) {
+            report.outcome = TransitionOutcome::Transitioned;
+        }
+    }
+    TransitionPreview::remove(repo)
+        .map_err(|err| TagCommitsError::Io("remove transition preview", err))?;
+    Ok(reports)
+}
+
+pub fn print_transition_reports(tag_name: &str, reports: &[TransitionReport]) {
+    if reports.is_empty() {
+        println!("{}", format!("{} has no Jira tickets", tag_name).dimmed());
+        return;
+    }
+
+    for report in reports {
+        let ticket = report.ticket.bold();
+        let status = report.status.as_deref().unwrap_or_default();
+        match report.outcome {
+            TransitionOutcome::Transitioned => {
+                println!("Transitioned {} to {}", ticket, status.green())
+            }
+            TransitionOutcome::WouldTransition => {
+                println!("Would transition {} to {}", ticket, status.green())
+            }
+            TransitionOutcome::AlreadyInStatus => println!(
+                "{}",
+                format!("{} is already {}", report.ticket, status).dimmed()
+            ),
+            TransitionOutcome::NoTransition => println!(
+                "{}",
+                format!("{} has no transition to {}", report.ticket, status).yellow()
+            ),
+            TransitionOutcome::NotFound => println!(
+                "{}",
+                format!("{} wasn't found in Jira", report.ticket).dimmed()
+            ),
+            TransitionOutcome::NoStatus => println!(
+                "{}",
+                format!(
+                    "{} has no release status configured for its project",
+                    report.ticket
+                )
+                .dimmed()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+    use std::{collections::HashMap, env};
+
+    /// A release of `v1.0` tagging a commit, transitioning its tickets to statuses.
+    fn preview(target: &str, transitions: &[(&str, &str)]) -> TransitionPreview {
+        let tag_commits = TagCommits {
+            commit_to_tag: HashMap::new(),
+            tag_names: vec!["v1.0".to_owned()],
+            tag_times: HashMap::new(),
+            tag_targets: HashMap::from([(
+                "v1.0".to_owned(),
+                Oid::from_str(target).expect("the OID should be valid"),
+            )]),
+            tag_notes: HashMap::new(),
+            tag_environments: HashMap::new(),
+            previous_releases: HashMap::new(),
+            tag_diffstats: HashMap::new(),
+            commit_parents: HashMap::new(),
+        };
+        TransitionPreview {
+            release: release_id("v1.0", &tag_commits),
+            transitions: transitions
+                .iter()
+                .map(|(ticket, status)| (ticket.to_string(), status.to_string()))
+                .collect(),
+        }
+    }
+
+    const TARGET: &str = "1111111111111111111111111111111111111111";
+    const MOVED_TARGET: &str = "2222222222222222222222222222222222222222";
+
+    #[test]
+    fn saved_previews_only_match_the_same_release_and_transitions() {
+        let path = env::temp_dir().join(format!("rels-jira-preview-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).expect("the repository should be created");
+
+        preview(TARGET, &[("ABC-1", "Released")])
+            .save(&repo)
+            .expect("the preview should be saved");
+        let saved = TransitionPreview::load(&repo);
+
+        let matches = |preview: TransitionPreview| saved.as_ref() == Some(&preview);
+        assert!(matches(preview(TARGET, &[("ABC-1", "Released")])));
+        assert!(!matches(preview(MOVED_TARGET, &[("ABC-1", "Released")])));
+        assert!(!matches(preview(TARGET, &[("ABC-1", "Done")])));
+        assert!(!matches(preview(
+            TARGET,
+            &[("ABC-1", "Released"), ("ABC-2", "Released")]
+        )));
+
+        TransitionPreview::remove(&repo).expect("the preview should be removed");
+        assert!(TransitionPreview::load(&repo).is_none());
+        TransitionPreview::remove(&repo).expect("a missing preview should be ignored");
+        let _ = fs::remove_dir_all(&path);
+    }
+}
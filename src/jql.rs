@@ -0,0 +1,32 @@
+use colored::Colorize;
+
+use crate::{
+    presets::TrackerPreset,
+    tickets::{Ticket, Trackers},
+};
+
+/// A JQL query for the Jira tickets among `tickets`, e.g. `key in (ABC-1, ABC-2)`, with each of
+/// `clauses` added with AND. Returns `None` if none of the tickets are Jira tickets.
+pub fn tickets_jql(trackers: &Trackers, tickets: &[&Ticket], clauses: &[String]) -> Option<String> {
+    let keys = tickets
+        .iter()
+        .filter(|ticket| trackers.preset(ticket) == Some(TrackerPreset::Jira))
+        .map(|ticket| ticket.id.as_str())
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return None;
+    }
+
+    let mut jql = format!("key in ({})", keys.join(", "));
+    for clause in clauses {
+        jql = format!("{} AND ({})", jql, clause);
+    }
+    Some(jql)
+}
+
+pub fn print_jql(tag_name: &str, jql: Option<&str>) {
+    match jql {
+        Some(jql) => println!("{}", jql),
+        None => eprintln!("{}", format!("{} has no Jira tickets", tag_name).dimmed()),
+    }
+}
@@ -0,0 +1,338 @@
+use git2::Oid;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    cli::Args,
+    diffstat::DiffStat,
+    forge_releases::ReleaseAsset,
+    lookups::StatusCategory,
+    releases::{
+        commits_by_tag, contributors, get_first_releases, release_commits, CommitTagInfo,
+        TagCommits,
+    },
+    tickets::{Ticket, Trackers},
+};
+
+/// The version of the JSON releases are printed as, which scripts can check to see whether
+/// they understand it. Adding fields doesn't change it, only removing fields or changing what
+/// they mean.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON Schema of the releases printed by `--format json`, for `rels schema`.
+const SCHEMA: &str = include_str!("schema.json");
+
+#[derive(Serialize)]
+pub struct ReleaseJson<'a> {
+    schema_version: u32,
+    pub tag: &'a str,
+    pub time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_note: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environments: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_release: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compare_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diffstat: Option<&'a DiffStat>,
+    /// With `--assets`, if the release has been published
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assets: Option<Vec<ReleaseAsset>>,
+    pub commits: Vec<CommitJson<'a>>,
+    /// With `--contributors`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contributors: Option<Vec<ContributorJson<'a>>>,
+}
+
+#[derive(Serialize)]
+struct ContributorJson<'a> {
+    name: &'a str,
+    commits: usize,
+}
+
+#[derive(Serialize)]
+pub struct CommitJson<'a> {
+    pub sha: String,
+    time: i64,
+    author_time: i64,
+    /// The seconds from the commit being authored to its release being tagged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lead_time: Option<i64>,
+    depth: usize,
+    pub tickets: Vec<TicketJson<'a>>,
+    pub release_note: &'a str,
+    pub author: &'a str,
+    co_authors: &'a [String],
+    reviewers: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_note: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    environments: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pr_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pr_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pr_title: Option<&'a str>,
+    cherry_picked_in: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reverts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reverted_by: Option<String>,
+}
+
+/// A commit as a line of `--format jsonl`, with the release it is in.
+#[derive(Serialize)]
+struct CommitLineJson<'a, 'b> {
+    schema_version: u32,
+    tag: &'a str,
+    #[serde(flatten)]
+    commit: &'b CommitJson<'a>,
+}
+
+#[derive(Serialize)]
+pub struct TicketJson<'a> {
+    pub id: &'a str,
+    tracker: &'a str,
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_category: Option<StatusCategory>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    ticket_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_release: Option<&'a str>,
+}
+
+pub fn ticket_json<'a>(
+    ticket: &'a Ticket,
+    trackers: &'a Trackers,
+    first_releases: Option<&HashMap<&'a str, &'a str>>,
+) -> TicketJson<'a> {
+    let details = trackers.details(ticket);
+    TicketJson {
+        id: &ticket.id,
+        tracker: trackers.tracker_name(ticket),
+        url: trackers.link(ticket),
+        title: details.as_ref().map(|details| details.title.clone()),
+        status_category: details.as_ref().and_then(|details| details.status_category),
+        ticket_type: details
+            .as_ref()
+            .and_then(|details| details.ticket_type.clone()),
+        assignee: details
+            .as_ref()
+            .and_then(|details| details.assignee.clone()),
+        status: details.and_then(|details| details.status),
+        first_release: first_releases
+            .and_then(|releases| releases.get(ticket.id.as_str()))
+            .copied(),
+    }
+}
+
+fn commit_json<'a>(
+    info: &'a CommitTagInfo,
+    tag_commits: &TagCommits,
+    trackers: &'a Trackers,
+    first_releases: Option<&HashMap<&'a str, &'a str>>,
+) -> CommitJson<'a> {
+    CommitJson {
+        sha: info.id.to_string(),
+        time: info.time,
+        author_time: info.author_time,
+        lead_time: tag_commits.lead_time(info),
+        depth: info.depth,
+        tickets: info
+            .tickets
+            .iter()
+            .map(|ticket| ticket_json(ticket, trackers, first_releases))
+            .collect(),
+        release_note: &info.release_note,
+        author: &info.author,
+        co_authors: &info.co_authors,
+        reviewers: &info.reviewers,
+        git_note: info.git_note.as_deref(),
+        environments: &info.environments,
+        pr_number: info.pr_number,
+        pr_url: info.pr_number.and_then(|number| trackers.pr_url(number)),
+        pr_title: info.pr_title.as_deref(),
+        cherry_picked_in: &info.cherry_picked_in,
+        reverts: info.reverts.map(|id| id.to_string()),
+        reverted_by: info.reverted_by.map(|id| id.to_string()),
+    }
+}
+
+/// The releases containing a ticket, with only the commits referencing it.
+#[derive(Serialize)]
+pub struct TicketReleasesJson<'a> {
+    pub ticket: &'a str,
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub releases: Vec<ReleaseJson<'a>>,
+}
+
+/// A release as `--format json` prints it, given the ids of its commits. Returns `None` if it
+/// doesn't match `--filter`.
+fn release_json<'a>(
+    args: &Args,
+    trackers: &'a Trackers,
+    tag_commits: &'a TagCommits,
+    tag_name: &'a str,
+    ids: impl IntoIterator<Item = &'a Oid>,
+    first_releases: Option<&HashMap<&'a str, &'a str>>,
+) -> Option<ReleaseJson<'a>> {
+    let commits = release_commits(args, trackers, tag_commits, tag_name, ids)?;
+    trackers.prefetch_details(commits.iter().flat_map(|info| &info.tickets));
+    Some(ReleaseJson {
+        schema_version: SCHEMA_VERSION,
+        tag: tag_name,
+        time: tag_commits.tag_times.get(tag_name).copied(),
+        git_note: tag_commits.tag_notes.get(tag_name).map(String::as_str),
+        environments: tag_commits
+            .tag_environments
+            .get(tag_name)
+            .map(Vec::as_slice),
+        previous_release: tag_commits
+            .previous_releases
+            .get(tag_name)
+            .map(String::as_str),
+        compare_url: trackers.compare_url(tag_commits, tag_name),
+        diffstat: tag_commits.tag_diffstats.get(tag_name),
+        assets: trackers.release_assets(tag_name),
+        contributors: args.contributors.then(|| {
+            contributors(&commits)
+                .into_iter()
+                .map(|(name, commits)| ContributorJson { name, commits })
+                .collect()
+        }),
+        commits: commits
+            .into_iter()
+            .map(|info| commit_json(info, tag_commits, trackers, first_releases))
+            .collect(),
+    })
+}
+
+/// Every release which matches `--filter`, as `--format json` prints them.
+pub fn releases_json<'a>(
+    args: &Args,
+    trackers: &'a Trackers,
+    tag_commits: &'a TagCommits,
+) -> Vec<ReleaseJson<'a>> {
+    let tag_to_commits = commits_by_tag(tag_commits);
+    let first_releases = args.first_release.then(|| get_first_releases(tag_commits));
+
+    tag_commits
+        .tag_names
+        .iter()
+        .filter_map(|tag_name| {
+            let ids = tag_to_commits
+                .get(tag_name.as_str())
+                .cloned()
+                .unwrap_or_default();
+            release_json(
+                args,
+                trackers,
+                tag_commits,
+                tag_name,
+                ids,
+                first_releases.as_ref(),
+            )
+        })
+        .collect()
+}
+
+/// The releases containing a ticket, ignoring case, or `None` if no commits reference it.
+pub fn ticket_releases_json<'a>(
+    args: &Args,
+    trackers: &'a Trackers,
+    tag_commits: &'a TagCommits,
+    id: &str,
+) -> Option<TicketReleasesJson<'a>> {
+    let mut releases = releases_json(args, trackers, tag_commits);
+    for release in &mut releases {
+        release.commits.retain(|commit| {
+            commit
+                .tickets
+                .iter()
+                .any(|ticket| ticket.id.eq_ignore_ascii_case(id))
+        });
+    }
+    releases.retain(|release| !release.commits.is_empty());
+    let ticket = releases
+        .iter()
+        .flat_map(|release| &release.commits)
+        .flat_map(|commit| &commit.tickets)
+        .find(|ticket| ticket.id.eq_ignore_ascii_case(id))?;
+    Some(TicketReleasesJson {
+        ticket: ticket.id,
+        url: ticket.url.clone(),
+        title: ticket.title.clone(),
+        releases,
+    })
+}
+
+/// Print every release as a JSON array, returning whether any releases matched `--filter`.
+pub fn print_releases_json(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> bool {
+    let releases = releases_json(args, trackers, tag_commits);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&releases).expect("releases should serialize to JSON")
+    );
+    !releases.is_empty()
+}
+
+/// A release as lines of JSON, one per commit with the tag of the release, for tools which
+/// process a line at a time such as `jq -c` or log shippers.
+pub fn release_jsonl(release: &ReleaseJson) -> String {
+    release
+        .commits
+        .iter()
+        .map(|commit| {
+            let line = CommitLineJson {
+                schema_version: SCHEMA_VERSION,
+                tag: release.tag,
+                commit,
+            };
+            serde_json::to_string(&line).expect("commits should serialize to JSON") + "\n"
+        })
+        .collect()
+}
+
+/// Print a release as lines of JSON as soon as it has been searched, returning whether it
+/// matched `--filter`.
+pub fn print_release_jsonl<'a>(
+    args: &Args,
+    trackers: &'a Trackers,
+    tag_commits: &'a TagCommits,
+    tag_name: &'a str,
+    ids: impl IntoIterator<Item = &'a Oid>,
+) -> bool {
+    match release_json(args, trackers, tag_commits, tag_name, ids, None) {
+        Some(release) => {
+            print!("{}", release_jsonl(&release));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Print every release as lines of JSON, one per commit, returning whether any releases
+/// matched `--filter`.
+pub fn print_releases_jsonl(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> bool {
+    let releases = releases_json(args, trackers, tag_commits);
+    for release in &releases {
+        print!("{}", release_jsonl(release));
+    }
+    !releases.is_empty()
+}
+
+pub fn print_schema() {
+    print!("{}", SCHEMA);
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use git2::Repository;
+
+use crate::{cli::Args, releases::TagCommits};
+
+/// The releases processed by the previous run, for `--since-last-run`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct LastRun {
+    /// Each release, as from [`release_id`]
+    releases: HashSet<String>,
+}
+
+fn state_file(repo: &Repository, args: &Args) -> PathBuf {
+    args.state_file
+        .clone()
+        .unwrap_or_else(|| repo.path().join("rels-cache").join("last-run.json"))
+}
+
+/// A release as `<tag name>@<tagged commit>`, so that a tag which is moved is a new release.
+pub fn release_id(tag_name: &str, tag_commits: &TagCommits) -> String {
+    let target = tag_commits
+        .tag_targets
+        .get(tag_name)
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    format!("{}@{}", tag_name, target)
+}
+
+impl LastRun {
+    /// Load the state of the previous run. If there was no previous run, nothing is filtered out.
+    pub fn load(repo: &Repository, args: &Args) -> LastRun {
+        fs::read(state_file(repo, args))
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether a release was processed by the previous run.
+    pub fn has_seen(&self, tag_name: &str, tag_commits: &TagCommits) -> bool {
+        self.releases.contains(&release_id(tag_name, tag_commits))
+    }
+
+    /// Remove the releases which were processed by the previous run.
+    pub fn remove_seen_releases(&self, tag_commits: &mut TagCommits) {
+        let seen = tag_commits
+            .tag_names
+            .iter()
+            .filter(|tag_name| self.has_seen(tag_name, tag_commits))
+            .cloned()
+            .collect::<HashSet<_>>();
+        tag_commits
+            .tag_names
+            .retain(|tag_name| !seen.contains(tag_name));
+    }
+
+    /// Save the releases processed by this run.
+    pub fn save(repo: &Repository, args: &Args, tag_commits: &TagCommits) -> std::io::Result<()> {
+        let last_run = LastRun {
+            releases: tag_commits
+                .tag_targets
+                .keys()
+                .map(|tag_name| release_id(tag_name, tag_commits))
+                .collect(),
+        };
+
+        let path = state_file(repo, args);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(&last_run)?)
+    }
+}
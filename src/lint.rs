@@ -0,0 +1,568 @@
+use colored::Colorize;
+use std::collections::HashSet;
+
+use git2::{Commit, Oid, Repository, Revwalk, Sort};
+
+use crate::{
+    cli::{Args, LintArgs, OutputFormat},
+    config::{LintConfig, TicketRequirement},
+    git::{get_release_tags, resolve_ref},
+    releases::{short_sha, TagCommitsError},
+    teamcity::{print_service_message, print_statistic},
+    tickets::Trackers,
+};
+
+/// A rule a commit or pull request broke.
+pub enum LintProblem {
+    NoTicket,
+    PullRequestWithoutTicket,
+    NotConventional,
+}
+
+impl LintProblem {
+    fn describe(&self) -> &'static str {
+        match self {
+            LintProblem::NoTicket => "no ticket reference",
+            LintProblem::PullRequestWithoutTicket => "no ticket reference in the pull request",
+            LintProblem::NotConventional => "not a conventional commit",
+        }
+    }
+}
+
+/// A commit which broke the lint rules. For pull requests, this is the merge commit, or the
+/// newest commit when checking a range as a single pull request.
+pub struct LintFailure {
+    pub id: Oid,
+    pub summary: String,
+    pub problems: Vec<LintProblem>,
+}
+
+/// A commit which was checked against the lint rules.
+pub struct LintedCommit {
+    pub id: Oid,
+    pub summary: String,
+}
+
+pub struct LintReport {
+    /// The commits checked, newest first
+    pub checked: Vec<LintedCommit>,
+    pub failures: Vec<LintFailure>,
+}
+
+impl LintReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn add_problem(&mut self, commit: &Commit, problem: LintProblem) {
+        match self
+            .failures
+            .iter_mut()
+            .find(|failure| failure.id == commit.id())
+        {
+            Some(failure) => failure.problems.push(problem),
+            None => self.failures.push(LintFailure {
+                id: commit.id(),
+                summary: commit.summary().unwrap_or_default().to_owned(),
+                problems: vec![problem],
+            }),
+        }
+    }
+}
+
+/// Set up a revwalk over the commits to lint: a range such as `main..HEAD`, the commits since a
+/// single revision (e.g. the base branch of a pull request), or by default the commits which
+/// aren't in any tag yet.
+fn lint_revwalk<'a>(
+    repo: &'a Repository,
+    args: &Args,
+    lint_args: &LintArgs,
+) -> Result<Revwalk<'a>, TagCommitsError> {
+    let mut revwalk = repo.revwalk()?;
+    if args.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+    let Some(range) = &lint_args.range else {
+        revwalk.push_head()?;
+        for tag in get_release_tags(repo, args) {
+            revwalk.hide(tag.target()?.peel_to_commit()?.id())?;
+        }
+        return Ok(revwalk);
+    };
+
+    let resolve = |name: &str| {
+        resolve_ref(repo, name).ok_or_else(|| TagCommitsError::RefNotFound(name.to_owned()))
+    };
+    match range.split_once("..") {
+        Some((from, to)) => {
+            // A...B lists the commits on B since it diverged from A, the same as A..B here
+            let to = to.trim_start_matches('.');
+            revwalk.hide(resolve(from)?)?;
+            if to.is_empty() {
+                revwalk.push_head()?;
+            } else {
+                revwalk.push(resolve(to)?)?;
+            }
+        }
+        None => {
+            revwalk.hide(resolve(range)?)?;
+            revwalk.push_head()?;
+        }
+    }
+    Ok(revwalk)
+}
+
+/// The commits merged by a merge commit: those reachable from its other parents but not its
+/// first parent.
+fn merged_commits<'a>(
+    repo: &'a Repository,
+    merge: &Commit,
+) -> Result<Vec<Commit<'a>>, TagCommitsError> {
+    let mut revwalk = repo.revwalk()?;
+    for parent_id in merge.parent_ids().skip(1) {
+        revwalk.push(parent_id)?;
+    }
+    revwalk.hide(merge.parent_id(0)?)?;
+
+    let mut commits = Vec::new();
+    for id in revwalk {
+        commits.push(repo.find_commit(id?)?);
+    }
+    Ok(commits)
+}
+
+/// Whether a commit summary starts with one of the conventional commit types, e.g.
+/// `feat(parser)!: `.
+fn is_conventional(summary: &str, types: &[String]) -> bool {
+    let Some((prefix, _)) = summary.split_once(": ") else {
+        return false;
+    };
+    let prefix = prefix.trim_end_matches('!');
+    let commit_type = match prefix.split_once('(') {
+        Some((commit_type, scope)) if scope.ends_with(')') => commit_type,
+        Some(_) => return false,
+        None => prefix,
+    };
+    types.iter().any(|allowed| allowed == commit_type)
+}
+
+struct Linter<'a> {
+    trackers: &'a Trackers,
+    config: &'a LintConfig,
+    ignore_case: bool,
+}
+
+impl Linter<'_> {
+    fn is_exempt(&self, commit: &Commit) -> bool {
+        let author = commit.author();
+        self.config.exempt_authors.iter().any(|exempt| {
+            author.name() == Some(exempt.as_str()) || author.email() == Some(exempt.as_str())
+        })
+    }
+
+    /// Whether a commit references a ticket, or one of the allowed placeholders.
+    fn has_ticket(&self, commit: &Commit) -> bool {
+        let message = commit.message().unwrap_or_default();
+        if !self.trackers.find_tickets(message).is_empty() {
+            return true;
+        }
+        self.config.allowed_placeholders.iter().any(|placeholder| {
+            if self.ignore_case {
+                message.to_uppercase().contains(&placeholder.to_uppercase())
+            } else {
+                message.contains(placeholder)
+            }
+        })
+    }
+
+    /// Whether any commit in a pull request references a ticket. Pull requests made up only of
+    /// commits by exempt authors pass.
+    fn pull_request_passes(&self, commits: &[Commit]) -> bool {
+        commits.iter().any(|commit| self.has_ticket(commit))
+            || commits
+                .iter()
+                .filter(|commit| commit.parent_count() <= 1)
+                .all(|commit| self.is_exempt(commit))
+    }
+}
+
+/// Check commits against the lint rules in the config. By default every commit must reference a
+/// ticket. Merge commits are skipped, since their messages are usually generated, but count
+/// towards the tickets of the pull request they merge.
+pub fn lint_commits(
+    repo: &Repository,
+    trackers: &Trackers,
+    config: &LintConfig,
+    args: &Args,
+    lint_args: &LintArgs,
+) -> Result<LintReport, TagCommitsError> {
+    let linter = Linter {
+        trackers,
+        config,
+        ignore_case: args.ignore_case,
+    };
+    let mut report = LintReport {
+        checked: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    let mut commits = Vec::new();
+    for commit_id in lint_revwalk(repo, args, lint_args)? {
+        let commit = repo.find_commit(commit_id?)?;
+        if args.includes_commit(&commit) {
+            commits.push(commit);
+        }
+    }
+
+    for commit in &commits {
+        if commit.parent_count() > 1 || linter.is_exempt(commit) {
+            continue;
+        }
+        report.checked.push(LintedCommit {
+            id: commit.id(),
+            summary: commit.summary().unwrap_or_default().to_owned(),
+        });
+
+        if config.require_ticket == TicketRequirement::Commit && !linter.has_ticket(commit) {
+            report.add_problem(commit, LintProblem::NoTicket);
+        }
+        if !config.conventional_types.is_empty()
+            && !is_conventional(
+                commit.summary().unwrap_or_default(),
+                &config.conventional_types,
+            )
+        {
+            report.add_problem(commit, LintProblem::NotConventional);
+        }
+    }
+
+    if config.require_ticket != TicketRequirement::PullRequest {
+        return Ok(report);
+    }
+
+    if lint_args.range.is_some() {
+        // A range is usually the commits of a single pull request, e.g. in CI
+        if let Some(newest) = commits.first() {
+            if !linter.pull_request_passes(&commits) {
+                report.add_problem(newest, LintProblem::PullRequestWithoutTicket);
+            }
+        }
+        return Ok(report);
+    }
+
+    // Otherwise each merge commit is a pull request, and other commits on the mainline were
+    // pushed or squash merged directly
+    let mut merged_ids = HashSet::new();
+    for commit in &commits {
+        if merged_ids.contains(&commit.id()) {
+            continue;
+        }
+        let mut pull_request = vec![commit.clone()];
+        if commit.parent_count() > 1 {
+            let merged = merged_commits(repo, commit)?;
+            merged_ids.extend(merged.iter().map(|merged| merged.id()));
+            pull_request.extend(merged);
+        }
+        if !linter.pull_request_passes(&pull_request) {
+            report.add_problem(commit, LintProblem::PullRequestWithoutTicket);
+        }
+    }
+    Ok(report)
+}
+
+/// Escape the characters which are special in XML text and attribute values.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Print the report as JUnit XML with a test case per commit, which CI systems show as test
+/// results, e.g. in merge requests with GitLab's `artifacts:reports:junit`.
+fn print_junit_report(report: &LintReport) {
+    // Failures of pull requests are on merge commits, which aren't checked themselves
+    let unchecked_failures = report
+        .failures
+        .iter()
+        .filter(|failure| !report.checked.iter().any(|commit| commit.id == failure.id))
+        .collect::<Vec<_>>();
+    let tests = report.checked.len() + unchecked_failures.len();
+    let failures = report.failures.len();
+
+    println!(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    println!(
+        r#"<testsuites name="rels lint" tests="{}" failures="{}">"#,
+        tests, failures
+    );
+    println!(
+        r#"  <testsuite name="rels lint" tests="{}" failures="{}">"#,
+        tests, failures
+    );
+    let cases = report
+        .checked
+        .iter()
+        .map(|commit| (commit.id, commit.summary.as_str()))
+        .chain(
+            unchecked_failures
+                .iter()
+                .map(|failure| (failure.id, failure.summary.as_str())),
+        );
+    for (id, summary) in cases {
+        let name = escape_xml(&format!("{} {}", short_sha(&id), summary));
+        match report.failures.iter().find(|failure| failure.id == id) {
+            None => println!(r#"    <testcase classname="rels lint" name="{}"/>"#, name),
+            Some(failure) => {
+                let problems = failure
+                    .problems
+                    .iter()
+                    .map(LintProblem::describe)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                println!(r#"    <testcase classname="rels lint" name="{}">"#, name);
+                println!(
+                    r#"      <failure message="{}">Commit {} broke the commit policy: {}</failure>"#,
+                    escape_xml(&problems),
+                    id,
+                    escape_xml(&problems)
+                );
+                println!("    </testcase>");
+            }
+        }
+    }
+    println!("  </testsuite>");
+    println!("</testsuites>");
+}
+
+/// Print the report as TeamCity service messages, with a build problem for each commit which
+/// broke the commit policy so TeamCity fails the build and lists them.
+fn print_teamcity_report(report: &LintReport) {
+    print_statistic("rels.lint.checked", report.checked.len());
+    print_statistic("rels.lint.failures", report.failures.len());
+    for failure in &report.failures {
+        let problems = failure
+            .problems
+            .iter()
+            .map(LintProblem::describe)
+            .collect::<Vec<_>>()
+            .join(", ");
+        print_service_message(
+            "buildProblem",
+            &[
+                (
+                    "description",
+                    &format!(
+                        "{} {} broke the commit policy: {}",
+                        short_sha(&failure.id),
+                        failure.summary,
+                        problems
+                    ),
+                ),
+                ("identity", &failure.id.to_string()),
+            ],
+        );
+    }
+}
+
+pub fn print_lint_report(report: &LintReport, format: OutputFormat) {
+    match format {
+        OutputFormat::Junit => return print_junit_report(report),
+        OutputFormat::Teamcity => return print_teamcity_report(report),
+        _ => {}
+    }
+    if report.checked.is_empty() {
+        println!("{}", "No commits to check".dimmed());
+        return;
+    }
+    if report.passed() {
+        println!(
+            "{}",
+            format!("All {} commit(s) passed", report.checked.len()).green()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} commit(s) broke the commit policy, of {} checked",
+            report.failures.len(),
+            report.checked.len()
+        )
+        .red()
+        .bold()
+    );
+    for failure in &report.failures {
+        let problems = failure
+            .problems
+            .iter()
+            .map(LintProblem::describe)
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "  {} {} {}",
+            short_sha(&failure.id).yellow(),
+            failure.summary,
+            format!("({})", problems).dimmed()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use clap::Parser;
+    use git2::{Signature, Time};
+    use std::env;
+
+    /// A repository in a new temporary directory, removed when the test ends.
+    struct TempRepo {
+        repo: Repository,
+        time: i64,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let path = env::temp_dir().join(format!("rels-lint-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            TempRepo {
+                repo: Repository::init(&path).expect("the repository should be created"),
+                time: 1_000,
+            }
+        }
+
+        /// Commit with no changes on top of `HEAD`.
+        fn commit(&mut self, author: &str, message: &str) -> Oid {
+            self.time += 1;
+            let signature = Signature::new(author, "dev@example.com", &Time::new(self.time, 0))
+                .expect("the signature should be valid");
+            let tree_id = self
+                .repo
+                .treebuilder(None)
+                .and_then(|builder| builder.write())
+                .expect("the tree should be written");
+            let tree = self.repo.find_tree(tree_id).expect("the tree should exist");
+            let parent = self
+                .repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok());
+            let parents = parent.iter().collect::<Vec<_>>();
+            self.repo
+                .commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    message,
+                    &tree,
+                    &parents,
+                )
+                .expect("the commit should be created")
+        }
+
+        fn lint(&self, config: &LintConfig, range: Option<&str>) -> LintReport {
+            let args = Args::try_parse_from(["rels"]).expect("the arguments should be valid");
+            let Ok(trackers) = Trackers::new(&Config::default(), &args, &self.repo) else {
+                panic!("the trackers should be valid");
+            };
+            let lint_args = LintArgs {
+                range: range.map(str::to_owned),
+            };
+            let Ok(report) = lint_commits(&self.repo, &trackers, config, &args, &lint_args) else {
+                panic!("the commits should be linted");
+            };
+            report
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            if let Some(path) = self.repo.workdir() {
+                let _ = std::fs::remove_dir_all(path);
+            }
+        }
+    }
+
+    /// The summaries of the commits which failed, and how.
+    fn failures(report: &LintReport) -> Vec<(&str, Vec<&str>)> {
+        report
+            .failures
+            .iter()
+            .map(|failure| {
+                let problems = failure.problems.iter().map(LintProblem::describe);
+                (failure.summary.as_str(), problems.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conventional_commits_need_an_allowed_type() {
+        let types = ["feat".to_owned(), "fix".to_owned()];
+
+        assert!(is_conventional("feat: add lint", &types));
+        assert!(is_conventional("fix(parser)!: handle tabs", &types));
+        assert!(!is_conventional("chore: bump deps", &types));
+        assert!(!is_conventional("fix(parser: handle tabs", &types));
+        assert!(!is_conventional("fix handle tabs", &types));
+    }
+
+    #[test]
+    fn commits_need_a_ticket_or_an_allowed_placeholder() {
+        let mut temp = TempRepo::new("commit");
+        temp.commit("Alice", "ABC-1 add lint");
+        temp.commit("Alice", "NO-TICKET fix typo");
+        temp.commit("Alice", "tidy up");
+        temp.commit("Dependabot", "bump deps");
+        let config = LintConfig {
+            allowed_placeholders: vec!["NO-TICKET".to_owned()],
+            exempt_authors: vec!["Dependabot".to_owned()],
+            ..LintConfig::default()
+        };
+
+        let report = temp.lint(&config, None);
+
+        assert_eq!(report.checked.len(), 3);
+        assert_eq!(
+            failures(&report),
+            [("tidy up", vec!["no ticket reference"])]
+        );
+    }
+
+    #[test]
+    fn pull_requests_need_a_ticket_in_any_commit() {
+        let mut temp = TempRepo::new("pull-request");
+        let base = temp.commit("Alice", "ABC-1 initial").to_string();
+        temp.commit("Alice", "ABC-2 add lint");
+        temp.commit("Alice", "address review");
+        let config = LintConfig {
+            require_ticket: TicketRequirement::PullRequest,
+            conventional_types: vec!["feat".to_owned()],
+            ..LintConfig::default()
+        };
+
+        let with_ticket = temp.lint(&config, Some(&format!("{}..HEAD", base)));
+        let without_ticket = temp.lint(&config, Some("HEAD~1..HEAD"));
+
+        assert_eq!(
+            failures(&with_ticket),
+            [
+                ("address review", vec!["not a conventional commit"]),
+                ("ABC-2 add lint", vec!["not a conventional commit"]),
+            ]
+        );
+        assert_eq!(
+            failures(&without_ticket),
+            [(
+                "address review",
+                vec![
+                    "not a conventional commit",
+                    "no ticket reference in the pull request"
+                ]
+            )]
+        );
+    }
+}
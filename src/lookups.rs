@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, fmt};
+
+use crate::{http::HttpClient, jira::JiraApi};
+
+const LINEAR_API_URL: &str = "https://api.linear.app/graphql";
+
+/// The category of a ticket's status, for comparing statuses from trackers with different
+/// workflows, e.g. In Review and QA are both in progress.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatusCategory {
+    ToDo,
+    InProgress,
+    Done,
+}
+
+impl StatusCategory {
+    /// The name of the category, as used in JSON and `--ticket-status`.
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusCategory::ToDo => "to-do",
+            StatusCategory::InProgress => "in-progress",
+            StatusCategory::Done => "done",
+        }
+    }
+}
+
+/// The title and status of a ticket, looked up from its tracker's API.
+#[derive(Clone, Debug)]
+pub struct TicketDetails {
+    pub title: String,
+    pub status: Option<String>,
+    /// The category of the status, if the tracker has one
+    pub status_category: Option<StatusCategory>,
+    /// The kind of ticket, e.g. Bug or Story
+    pub ticket_type: Option<String>,
+    /// The name of the person the ticket is assigned to
+    pub assignee: Option<String>,
+}
+
+impl TicketDetails {
+    /// Whether the ticket's status or status category is one of `statuses`, ignoring case.
+    pub fn has_status(&self, statuses: &[String]) -> bool {
+        statuses.iter().any(|status| {
+            self.status
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(status))
+                || self
+                    .status_category
+                    .is_some_and(|category| category.name().eq_ignore_ascii_case(status))
+        })
+    }
+}
+
+/// An issue tracker API which can look up the details of tickets.
+#[derive(Clone)]
+pub enum TicketApi {
+    Jira(JiraApi),
+    Linear {
+        http: HttpClient,
+        api_key: String,
+    },
+    Youtrack {
+        http: HttpClient,
+        base_url: String,
+        token: String,
+    },
+}
+
+pub enum LookupError {
+    Http(ureq::Error),
+    Api(String),
+}
+
+impl From<ureq::Error> for LookupError {
+    fn from(err: ureq::Error) -> Self {
+        LookupError::Http(err)
+    }
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::Http(err) => write!(f, "{}", err),
+            LookupError::Api(message) => f.write_str(message),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct LinearIssueData {
+    issue: Option<LinearIssue>,
+}
+
+#[derive(Deserialize)]
+struct LinearIssue {
+    title: String,
+    state: Option<LinearState>,
+    assignee: Option<LinearUser>,
+}
+
+#[derive(Deserialize)]
+struct LinearUser {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct LinearState {
+    name: String,
+    /// The kind of state, e.g. backlog, started, or completed
+    #[serde(rename = "type")]
+    state_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YoutrackIssue {
+    summary: String,
+    #[serde(default)]
+    custom_fields: Vec<YoutrackField>,
+}
+
+#[derive(Deserialize)]
+struct YoutrackField {
+    name: String,
+    value: Option<serde_json::Value>,
+}
+
+impl TicketApi {
+    /// Look up a ticket by its ID, returning `None` if the tracker doesn't know about it.
+    pub fn lookup(&self, id: &str) -> Result<Option<TicketDetails>, LookupError> {
+        match self {
+            TicketApi::Jira(api) => api.lookup(id),
+            TicketApi::Linear { http, api_key } => lookup_linear_issue(http, api_key, id),
+            TicketApi::Youtrack {
+                http,
+                base_url,
+                token,
+            } => lookup_youtrack_issue(http, base_url, token, id),
+        }
+    }
+}
+
+/// Look up many tickets at once, returning the result of each lookup in the same order. The
+/// HTTP client is synchronous, so each lookup runs on tokio's blocking thread pool, which is
+/// limited to `max_concurrent` threads so that at most that many requests are made at once.
+#[cfg(feature = "async")]
+pub fn lookup_all(
+    lookups: Vec<(TicketApi, String)>,
+    max_concurrent: u32,
+) -> Vec<Result<Option<TicketDetails>, LookupError>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .max_blocking_threads(max_concurrent as usize)
+        .build()
+        .expect("the tokio runtime should start");
+    runtime.block_on(async {
+        let handles = lookups
+            .into_iter()
+            .map(|(api, id)| tokio::task::spawn_blocking(move || api.lookup(&id)))
+            .collect::<Vec<_>>();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.expect("ticket lookups shouldn't panic"));
+        }
+        results
+    })
+}
+
+fn lookup_linear_issue(
+    http: &HttpClient,
+    api_key: &str,
+    id: &str,
+) -> Result<Option<TicketDetails>, LookupError> {
+    let query =
+        "query($id: String!) { issue(id: $id) { title state { name type } assignee { name } } }";
+    let response: GraphqlResponse<LinearIssueData> = http
+        .send(|agent| {
+            agent
+                .post(LINEAR_API_URL)
+                .header("Authorization", api_key)
+                .send_json(json!({ "query": query, "variables": { "id": id } }))
+        })?
+        .body_mut()
+        .read_json()?;
+
+    match response.data.and_then(|data| data.issue) {
+        Some(issue) => Ok(Some(TicketDetails {
+            title: issue.title,
+            status_category: issue.state.as_ref().and_then(|state| {
+                match state.state_type.as_str() {
+                    "triage" | "backlog" | "unstarted" => Some(StatusCategory::ToDo),
+                    "started" => Some(StatusCategory::InProgress),
+                    "completed" | "canceled" => Some(StatusCategory::Done),
+                    _ => None,
+                }
+            }),
+            status: issue.state.map(|state| state.name),
+            // Linear has labels rather than issue types
+            ticket_type: None,
+            assignee: issue.assignee.map(|user| user.name),
+        })),
+        // Linear reports unknown issues as errors, which aren't worth failing for
+        None if response
+            .errors
+            .iter()
+            .all(|err| err.message.contains("not found")) =>
+        {
+            Ok(None)
+        }
+        None => Err(LookupError::Api(
+            response
+                .errors
+                .into_iter()
+                .map(|err| err.message)
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
+
+fn lookup_youtrack_issue(
+    http: &HttpClient,
+    base_url: &str,
+    token: &str,
+    id: &str,
+) -> Result<Option<TicketDetails>, LookupError> {
+    let url = format!(
+        "{}/api/issues/{}?fields=summary,customFields(name,value(name,fullName,isResolved))",
+        base_url, id
+    );
+    let issue: YoutrackIssue = match http.send(|agent| {
+        agent
+            .get(&url)
+            .header("Authorization", &format!("Bearer {}", token))
+            .header("Accept", "application/json")
+            .call()
+    }) {
+        Ok(mut response) => response.body_mut().read_json()?,
+        Err(ureq::Error::StatusCode(404)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    // The status, type, and assignee are fields of the default workflow
+    let mut fields = issue
+        .custom_fields
+        .into_iter()
+        .filter_map(|field| Some((field.name, field.value?)))
+        .collect::<HashMap<_, _>>();
+    // YouTrack only says whether a state is resolved, so unresolved states aren't categorized
+    let resolved = fields
+        .get("State")
+        .and_then(|state| state.get("isResolved")?.as_bool());
+    let mut field_name = |field: &str| {
+        let value = fields.remove(field)?;
+        let name = value.get("fullName").or_else(|| value.get("name"))?;
+        name.as_str().map(str::to_owned)
+    };
+    Ok(Some(TicketDetails {
+        title: issue.summary,
+        status: field_name("State"),
+        status_category: (resolved == Some(true)).then_some(StatusCategory::Done),
+        ticket_type: field_name("Type"),
+        assignee: field_name("Assignee"),
+    }))
+}
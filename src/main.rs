@@ -1,359 +1,587 @@
-use colored::Colorize;
-use regex::Regex;
-use std::{collections::HashMap, env, time::SystemTime};
-
-use clap::Parser;
-use git2::{Commit, Repository, RepositoryOpenFlags, Tag};
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use git2::Repository;
+use std::time::Duration;
+
+mod audit;
+mod auth;
+mod azure_wiki;
+mod backports;
+mod cache;
+mod cadence;
+mod cherry_picks;
+mod cli;
+mod completions;
+mod config;
+mod config_commands;
+mod diffstat;
+mod doctor;
+mod environments;
+mod errors;
+mod exit_codes;
+mod explain;
+mod feed;
+mod forge;
+mod forge_api;
+mod forge_releases;
+mod git;
+mod graph;
+mod hooks;
+mod http;
+mod jira;
+mod jql;
+mod json;
+mod last_run;
+mod lint;
+mod logging;
+mod lookups;
+mod man;
+mod markdown;
+mod mcp;
+mod metrics;
+mod notes;
+mod orphans;
+mod output;
+mod plugins;
+mod porcelain;
+mod presets;
+mod progress;
+mod publish;
+mod pull_requests;
+mod regex_engine;
+mod releases;
+mod reverts;
+mod self_update;
+mod serve;
+mod spanning;
+mod stats;
+mod step_summary;
+mod table;
+mod teamcity;
+mod tickets;
+mod timeline;
+mod timings;
+mod trailers;
+mod watch;
+
+use audit::{audit_releases, print_release_audit};
+use auth::{login, logout};
+use azure_wiki::{print_wiki_reports, publish_wiki_pages};
+use backports::{get_backport_reports, print_backport_reports};
+use cache::{clear_cache, print_cache_stats};
+use cadence::{get_cadence, print_cadence};
+use cli::{
+    Args, AuditCommand, AuthCommand, CacheCommand, Command, ConfigCommand, JiraCommand,
+    OutputFormat, PublishCommand,
+};
+use completions::{print_completions, COMPLETE_VAR};
+use config::{Config, HooksConfig};
+use config_commands::{init_config, show_config};
+use doctor::{print_findings, run_doctor, Severity};
+use errors::print_error;
+use exit_codes::ExitCode;
+use explain::{explain_commits, print_explanations};
+use feed::print_feed;
+use forge::origin_remote;
+use forge_api::Forge;
+use git::get_repo;
+use hooks::{run_post_hooks, run_pre_hook};
+use http::HttpClient;
+use jira::{
+    comment_tickets, create_versions, jira_api, print_comment_reports, print_transition_reports,
+    print_version_reports, transition_tickets,
+};
+use jql::{print_jql, tickets_jql};
+use json::{
+    print_release_jsonl, print_releases_json, print_releases_jsonl, print_schema, releases_json,
+};
+use last_run::LastRun;
+use lint::{lint_commits, print_lint_report};
+use logging::init_logging;
+use man::print_man_page;
+use markdown::print_releases_markdown;
+use mcp::serve_mcp;
+use metrics::{get_metrics, print_metrics};
+use notes::{print_notes_reports, write_split_notes};
+use orphans::{get_orphan_commits, print_orphans};
+use output::write_output;
+use plugins::run_plugin;
+use porcelain::{print_release_porcelain, print_releases_porcelain};
+use publish::{finalize_release, print_published, publish_release};
+use releases::{
+    get_tag_commits, print_release, print_releases, release_tickets, stream_tag_commits,
+    TagCommitsError,
+};
+use self_update::{self_update, UpdateStatus};
+use serve::serve;
+use spanning::{get_spanning_tickets, print_spanning_tickets};
+use stats::{get_stats, print_stats};
+use step_summary::write_step_summary;
+use tickets::Trackers;
+use timeline::{get_timeline, print_timeline};
+use timings::Timings;
+use watch::watch;
 
 // TODO:
 // - Allow option to link to commit in GitHub/GitLab/DevOps/etc
 // - Allow option to show commit SHA
 // - Option to find via release branch instead of tag
 
-#[derive(Parser, Debug)]
-struct Args {
-    #[arg(
-        short,
-        long,
-        default_value_t = 10,
-        help = "Maximum depth to search commits from tags"
-    )]
-    depth: usize,
-
-    #[arg(
-        short = 't',
-        long,
-        default_value = "1y",
-        help = "The maximum age ([t]ime) of tags to show, in the format 1y 2mon 3w 4d 5h 6m 7s"
-    )]
-    age: String,
-
-    #[arg(
-        short = 'u',
-        long,
-        help = "The base URL for JIRA tickets, e.g. `https://jira.example.com/browse/`. If not specified, JIRA ticket numbers will not be linked. If {ticket} is included in the URL, it will be replaced with the ticket number, otherwise it will be appended to end of the URL."
-    )]
-    jira_url: Option<String>,
-
-    #[arg(
-        short = 'r',
-        long,
-        default_value = "[A-Z]+-[0-9]+",
-        help = "The regex to use to match JIRA ticket numbers"
-    )]
-    jira_regex: String,
-
-    #[arg(
-        short,
-        long,
-        help = "Show all commits, not just those matching the JIRA regex."
-    )]
-    all: bool,
-
-    #[arg(short, long, help = "Filter by tag name or commit message")]
-    filter: Option<String>,
+fn exit_with_error(err: TagCommitsError, format: OutputFormat) -> ! {
+    print_error(&err, format);
+    err.exit_code().exit();
 }
 
-fn get_repo() -> Repository {
-    match Repository::open_ext(
-        ".",
-        RepositoryOpenFlags::empty(),
-        &[] as &[&std::ffi::OsStr],
-    ) {
-        Ok(repo) => repo,
-        Err(_) => {
-            let path = env::current_dir().unwrap_or(std::path::PathBuf::from("."));
-            eprintln!(
-                "{}",
-                format!(
-                    "{} is not a git repository!",
-                    path.display().to_string().bold()
+/// Print the releases, which is what rels does without a command, running the hooks around it
+/// with `--hooks`. Returns the exit code if no releases matched `--filter`.
+fn list_releases(
+    repo: &Repository,
+    max_age: Duration,
+    trackers: &Trackers,
+    args: &Args,
+    hooks: Option<&HooksConfig>,
+    timings: &mut Timings,
+) -> Option<ExitCode> {
+    if let Some(hooks) = hooks {
+        run_pre_hook(repo, hooks).unwrap_or_else(|err| exit_with_error(err, args.format));
+        timings.phase("run pre hook");
+    }
+    let last_run = args.since_last_run.then(|| LastRun::load(repo, args));
+    let mut printed = false;
+    let tag_commits = if args.can_stream() {
+        let tag_commits = stream_tag_commits(
+            repo,
+            max_age,
+            trackers,
+            args,
+            |tag_commits, tag_name, added| {
+                let seen = last_run
+                    .as_ref()
+                    .is_some_and(|last_run| last_run.has_seen(tag_name, tag_commits));
+                if !seen {
+                    printed |= match args.format {
+                        _ if args.porcelain => {
+                            print_release_porcelain(args, trackers, tag_commits, tag_name, added)
+                        }
+                        OutputFormat::Jsonl => {
+                            print_release_jsonl(args, trackers, tag_commits, tag_name, added)
+                        }
+                        _ => print_release(args, trackers, tag_commits, tag_name, added, None),
+                    };
+                }
+            },
+        )
+        .unwrap_or_else(|err| exit_with_error(err, args.format));
+        timings.phase("search tags");
+        tag_commits
+    } else {
+        let mut tag_commits = get_tag_commits(repo, max_age, trackers, args)
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+        timings.phase("search tags");
+        if let Some(last_run) = &last_run {
+            last_run.remove_seen_releases(&mut tag_commits);
+        }
+        printed = match (&args.output, args.format) {
+            (Some(path), _) => write_output(path, args, trackers, &tag_commits)
+                .unwrap_or_else(|err| exit_with_error(err, args.format)),
+            (None, _) if args.porcelain => print_releases_porcelain(args, trackers, &tag_commits),
+            (None, OutputFormat::Text) => print_releases(args, trackers, &tag_commits),
+            (None, OutputFormat::Json) => print_releases_json(args, trackers, &tag_commits),
+            (None, OutputFormat::Jsonl) => print_releases_jsonl(args, trackers, &tag_commits),
+            (None, OutputFormat::Markdown) => print_releases_markdown(args, trackers, &tag_commits),
+            (None, OutputFormat::GhSummary) => write_step_summary(args, trackers, &tag_commits)
+                .unwrap_or_else(|err| exit_with_error(err, args.format)),
+            (None, OutputFormat::Ics | OutputFormat::Junit | OutputFormat::Teamcity) => {
+                exit_with_error(
+                    TagCommitsError::UnsupportedFormat("rels", args.format),
+                    args.format,
                 )
-                .red()
-            );
-            std::process::exit(1);
+            }
+        };
+        tag_commits
+    };
+    if args.since_last_run {
+        if let Err(err) = LastRun::save(repo, args, &tag_commits) {
+            exit_with_error(TagCommitsError::Io("save last run", err), args.format);
         }
     }
-}
-
-fn get_tags<'a>(repo: &'a Repository) -> Vec<Tag<'a>> {
-    let mut tags = Vec::new();
-    let _ = repo.tag_foreach(|tag_id, _| {
-        if let Ok(tag) = repo.find_tag(tag_id) {
-            tags.push(tag);
-        }
-        true
-    });
-    tags
-}
-
-struct CommitDepthInfo<'a> {
-    commit: Commit<'a>,
-    depth: usize,
-}
-
-/// Get all the parent commits of a commit, up to a maximum depth.
-fn get_parent_commits<'a>(
-    repo: &'a Repository,
-    commit: &Commit<'a>,
-    max_depth: usize,
-) -> Vec<CommitDepthInfo<'a>> {
-    let mut commits = Vec::new();
-    let parents = commit.parents();
-    let mut commit_ids_to_check = parents.map(|p| p.id()).collect::<Vec<_>>();
-    let mut depths: HashMap<_, _> = commit_ids_to_check
-        .iter()
-        .map(|id| (*id, 1))
-        .collect::<HashMap<_, _>>();
-
-    while let Some(parent_id) = commit_ids_to_check.pop() {
-        let parent_commit = repo
-            .find_commit(parent_id)
-            .expect("repo should contain commit");
-
-        let depth = *depths.get(&parent_id).unwrap_or(&1);
-
-        if depth > max_depth {
-            continue;
+    if let Some(hooks) = hooks {
+        let mut releases = releases_json(args, trackers, &tag_commits);
+        if let Some(last_run) = &last_run {
+            releases.retain(|release| !last_run.has_seen(release.tag, &tag_commits));
         }
-
-        commit_ids_to_check.extend(parent_commit.parents().map(|p| p.id()));
-        parent_commit.parents().for_each(|p| {
-            depths.insert(p.id(), depth + 1);
-        });
-
-        commits.push(CommitDepthInfo {
-            commit: parent_commit,
-            depth,
-        });
+        run_post_hooks(repo, hooks, &releases)
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+        timings.phase("run post hooks");
     }
-    commits
+    (!args.filters.is_empty() && !printed).then_some(ExitCode::NoMatchingReleases)
 }
 
-fn commit_is_within_duration(commit: &Commit, max_age: std::time::Duration) -> bool {
-    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        let commit_time = commit.time().seconds();
-        let now_seconds = now.as_secs();
-
-        let diff_seconds = now_seconds - commit_time as u64;
-        return diff_seconds < max_age.as_secs();
+fn main() {
+    // Answer completion requests from the scripts printed by `rels completions`
+    CompleteEnv::with_factory(Args::command)
+        .var(COMPLETE_VAR)
+        .complete();
+
+    let mut args = Args::parse();
+    init_logging(args.verbose);
+    // Commands which don't need a repository
+    match &args.command {
+        Some(Command::Completions { shell }) => {
+            if let Err(err) = print_completions(*shell) {
+                exit_with_error(TagCommitsError::Io("print completions", err), args.format);
+            }
+            return;
+        }
+        Some(Command::Schema) => {
+            print_schema();
+            return;
+        }
+        Some(Command::Man { out_dir }) => {
+            if let Err(err) = print_man_page(out_dir.as_deref()) {
+                exit_with_error(TagCommitsError::Io("write man page", err), args.format);
+            }
+            return;
+        }
+        Some(Command::Auth(AuthCommand::Login { service, token })) => {
+            login(*service, token.clone()).unwrap_or_else(|err| exit_with_error(err, args.format));
+            println!("Stored the {} token in the keyring", service);
+            return;
+        }
+        Some(Command::Auth(AuthCommand::Logout { service })) => {
+            match logout(*service).unwrap_or_else(|err| exit_with_error(err, args.format)) {
+                true => println!("Removed the {} token from the keyring", service),
+                false => println!("No {} token was stored", service),
+            }
+            return;
+        }
+        Some(Command::SelfUpdate { check }) => {
+            match HttpClient::new(&args)
+                .and_then(|http| Ok(self_update(&http, *check)?))
+                .unwrap_or_else(|err| exit_with_error(err, args.format))
+            {
+                UpdateStatus::UpToDate(version) => {
+                    println!("rels {} is the latest version", version)
+                }
+                UpdateStatus::Available(version) => println!(
+                    "rels {} is available, run rels self-update to install it",
+                    version
+                ),
+                UpdateStatus::Updated(version) => println!("Updated rels to {}", version),
+            }
+            return;
+        }
+        _ => {}
     }
-    true
-}
-
-enum TagCommitsError {
-    NoTags,
-    Git(git2::Error),
-    Regex(regex::Error),
-}
 
-impl From<git2::Error> for TagCommitsError {
-    fn from(err: git2::Error) -> Self {
-        TagCommitsError::Git(err)
+    let mut timings = Timings::new(args.timings);
+    let repo = get_repo().unwrap_or_else(|err| exit_with_error(err, args.format));
+    timings.phase("open repository");
+
+    let max_age = args
+        .max_age()
+        .unwrap_or_else(|err| exit_with_error(err, args.format));
+    // Commands which check or write the config file, so don't load it first
+    if let Some(Command::Config(ConfigCommand::Init { force })) = &args.command {
+        let path = init_config(&repo, &args, *force)
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+        println!("Wrote {}", path.display());
+        return;
     }
-}
-
-impl From<regex::Error> for TagCommitsError {
-    fn from(err: regex::Error) -> Self {
-        TagCommitsError::Regex(err)
+    let config = Config::load(&repo, &args);
+    if let Ok(config) = &config {
+        args.tag_pattern = args.tag_pattern.take().or(config.tag_pattern.clone());
+        args.release_note_trailer = args
+            .release_note_trailer
+            .take()
+            .or(config.release_note_trailer.clone());
+        args.notes_ref = args.notes_ref.take().or(config.notes_ref.clone());
+        args.forge = args
+            .forge
+            .or_else(|| origin_remote(&repo, &args).and_then(|remote| config.forge(&remote)));
+        if args.environments.is_empty() {
+            args.environments = config.environments.clone();
+        }
     }
-}
-
-fn get_tag_commits<'a>(
-    repo: &'a Repository,
-    max_age: std::time::Duration,
-    args: &'a Args,
-) -> Result<
-    (
-        HashMap<std::string::String, CommitTagInfo<'a>>,
-        Vec<std::string::String>,
-    ),
-    TagCommitsError,
-> {
-    let mut commit_to_tag: HashMap<String, CommitTagInfo> = HashMap::new();
-    let mut tag_names = Vec::new();
-
-    for tag in get_tags(&repo) {
-        let tag_name = tag.name().ok_or(TagCommitsError::NoTags)?.to_owned();
-        tag_names.push(tag_name.clone());
-
-        let commit = repo.find_commit(tag.target()?.id())?;
-        if !commit_is_within_duration(&commit, max_age) {
-            continue;
+    if let Some(Command::Doctor) = &args.command {
+        let findings = run_doctor(&repo, max_age, &args, config);
+        print_findings(&findings);
+        if findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+        {
+            ExitCode::DoctorFailed.exit();
         }
+        return;
+    }
 
-        // Add the commit directly referenced by the tag
-        add_if_matches_regex(commit.clone(), &mut commit_to_tag, 0, &tag_name, &args)?;
-
-        let parents = get_parent_commits(&repo, &commit, args.depth);
-        for parent in parents {
-            let parent_id = parent.commit.id().to_string();
-            let parent_depth = parent.depth;
-
-            if let Some(existing) = commit_to_tag.get(&parent_id) {
-                if existing.depth < parent_depth {
-                    continue;
-                }
+    let config = config.unwrap_or_else(|err| exit_with_error(err.into(), args.format));
+    let trackers = Trackers::new(&config, &args, &repo)
+        .unwrap_or_else(|err| exit_with_error(err, args.format));
+    timings.phase("load config");
+
+    let hooks = args.hooks.then_some(&config.hooks);
+    // Failures which still print output, so exit once everything has been printed
+    let mut exit_code = None;
+    match &args.command {
+        Some(Command::Orphans(orphans_args)) => {
+            let orphans = get_orphan_commits(&repo, max_age, &trackers, &args, orphans_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("find orphans");
+            print_orphans(&orphans, &trackers, args.quiet);
+        }
+        Some(Command::Spanning) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            print_spanning_tickets(&get_spanning_tickets(&tag_commits), args.quiet);
+        }
+        Some(Command::Backports(backports_args)) => {
+            let reports = get_backport_reports(&repo, max_age, &trackers, &args, backports_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("compare branches");
+            print_backport_reports(&reports, args.quiet);
+        }
+        Some(Command::Metrics) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            print_metrics(&get_metrics(&tag_commits, max_age), args.format);
+        }
+        Some(Command::Stats) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            print_stats(&get_stats(&tag_commits, max_age), args.format);
+        }
+        Some(Command::Cadence(cadence_args)) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let counts = get_cadence(&tag_commits, max_age, cadence_args.period);
+            print_cadence(&counts, cadence_args.period, args.format);
+        }
+        Some(Command::Timeline(timeline_args)) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let timeline = get_timeline(&tag_commits, max_age, timeline_args.period);
+            print_timeline(&timeline, timeline_args.period, args.format);
+        }
+        Some(Command::Feed) => {
+            if args.format != OutputFormat::Ics {
+                exit_with_error(
+                    TagCommitsError::UnsupportedFormat("rels feed", args.format),
+                    args.format,
+                );
+            }
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            if !print_feed(&repo, &args, &trackers, &tag_commits) && !args.filters.is_empty() {
+                exit_code = Some(ExitCode::NoMatchingReleases);
             }
-
-            add_if_matches_regex(
-                parent.commit.clone(),
-                &mut commit_to_tag,
-                parent_depth,
-                &tag_name,
-                &args,
-            )?;
         }
-    }
-
-    tag_names.sort();
-
-    return Ok((commit_to_tag, tag_names));
-}
-
-fn add_if_matches_regex<'a>(
-    commit: Commit<'a>,
-    commit_to_tag: &mut HashMap<String, CommitTagInfo<'a>>,
-    depth: usize,
-    tag_name: &String,
-    args: &Args,
-) -> Result<(), TagCommitsError> {
-    let regex = Regex::new(args.jira_regex.as_str())?;
-    let Some(message) = commit.message() else {
-        return Ok(());
-    };
-
-    let formatted_tickets = regex
-        .find_iter(message)
-        .map(|regex_match| {
-            let ticket = regex_match.as_str().bold().italic();
-            format!("{ticket}")
-        })
-        .collect::<Vec<String>>()
-        .join(", ");
-
-    let formatted_tickets = if formatted_tickets.is_empty() {
-        "(no tickets)".dimmed().to_string()
-    } else {
-        formatted_tickets
-    };
-
-    let urls = regex
-        .find_iter(message)
-        .map(|regex_match| {
-            let ticket = regex_match.as_str();
-
-            match &args.jira_url {
-                Some(url) => {
-                    if url.contains("{ticket}") {
-                        url.replace("{ticket}", ticket)
-                    } else {
-                        format!("{}{}", url, ticket)
-                    }
+        Some(Command::Notes(notes_args)) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let printed = match &notes_args.split_dir {
+                Some(dir) => {
+                    let reports = write_split_notes(dir, &args, &trackers, &tag_commits)
+                        .unwrap_or_else(|err| exit_with_error(err, args.format));
+                    print_notes_reports(&reports, args.quiet);
+                    !reports.is_empty()
                 }
-                None => ticket.to_owned(),
+                None => print_releases_markdown(&args, &trackers, &tag_commits),
+            };
+            if !printed && !args.filters.is_empty() {
+                exit_code = Some(ExitCode::NoMatchingReleases);
             }
-        })
-        .collect::<Vec<String>>();
-
-    if regex.is_match(message) || args.all {
-        commit_to_tag.insert(
-            commit.id().to_string(),
-            CommitTagInfo {
-                commit,
-                depth,
-                tag_name: tag_name.clone(),
-                formatted_tickets,
-                formatted_urls: urls,
-            },
-        );
-    }
-    Ok(())
-}
-
-struct CommitTagInfo<'a> {
-    commit: Commit<'a>,
-    depth: usize,
-    tag_name: String,
-    formatted_tickets: String,
-    formatted_urls: Vec<String>,
-}
-
-fn main() {
-    let args = Args::parse();
-    let repo = get_repo();
-
-    let max_age = duration_str::parse(&args.age).unwrap_or_default();
-    let (commit_to_tag, tag_names) = match get_tag_commits(&repo, max_age, &args) {
-        Ok((commit_to_tag, tag_names)) => (commit_to_tag, tag_names),
-        Err(err) => {
-            match err {
-                TagCommitsError::Git(err) => {
-                    eprintln!("{}", format!("Git error: {}", err).red());
-                }
-                TagCommitsError::Regex(err) => {
-                    eprintln!("{}", format!("Regex error: {}", err).red());
-                }
-                TagCommitsError::NoTags => {
-                    eprintln!("{}", "No tags found!".red());
+        }
+        Some(Command::Jql(jql_args)) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let tickets = release_tickets(&args, &trackers, &tag_commits, &jql_args.tag)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let jql = tickets_jql(&trackers, &tickets, &jql_args.clauses);
+            print_jql(&jql_args.tag, jql.as_deref());
+        }
+        Some(Command::Serve(serve_args)) => {
+            serve(&repo, max_age, &trackers, &args, serve_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+        }
+        Some(Command::Plugin(plugin_args)) => {
+            let code = run_plugin(&repo, max_age, &trackers, &args, plugin_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            std::process::exit(code);
+        }
+        Some(Command::Mcp) => {
+            serve_mcp(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+        }
+        Some(Command::Publish(PublishCommand::AzureWiki(wiki_args))) => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let reports = publish_wiki_pages(&repo, &args, &trackers, &tag_commits, wiki_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("publish wiki pages");
+            print_wiki_reports(&reports, args.quiet);
+        }
+        Some(Command::Publish(command)) => {
+            let (forge, publish_args, draft, finalize) = match command {
+                PublishCommand::Github(github_args) => (
+                    Forge::Github,
+                    &github_args.release,
+                    github_args.draft,
+                    github_args.finalize.as_deref(),
+                ),
+                PublishCommand::Gitlab(publish_args) => (Forge::Gitlab, publish_args, false, None),
+                PublishCommand::AzureWiki(_) => unreachable!("azure-wiki is handled above"),
+            };
+            let release = match finalize {
+                Some(tag) => finalize_release(&repo, &args, tag),
+                None => {
+                    let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                        .unwrap_or_else(|err| exit_with_error(err, args.format));
+                    timings.phase("search tags");
+                    publish_release(
+                        &repo,
+                        &args,
+                        &trackers,
+                        &tag_commits,
+                        forge,
+                        publish_args,
+                        draft,
+                    )
                 }
             }
-            std::process::exit(1);
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("publish release");
+            print_published(&release);
         }
-    };
-
-    let tag_to_commits = commit_to_tag
-        .iter()
-        .fold(HashMap::new(), |mut map, (_, info)| {
-            map.entry(&info.tag_name)
-                .or_insert_with(Vec::new)
-                .push(info);
-            map
-        });
-
-    for tag_name in tag_names {
-        let empty = Vec::new();
-        let commits = tag_to_commits.get(&tag_name).unwrap_or(&empty);
-        let tag_matches_filter = if let Some(filter) = args.filter.clone() {
-            tag_name.contains(&filter)
-        } else {
-            true
-        };
-
-        let filtered_commits = commits
-            .iter()
-            .filter(|commit| {
-                if let Some(filter) = args.filter.clone() {
-                    tag_matches_filter || commit.formatted_tickets.contains(&filter)
-                } else {
-                    true
-                }
-            })
-            .collect::<Vec<_>>();
-
-        if args.filter.is_some() && !tag_matches_filter && filtered_commits.is_empty() {
-            continue;
+        Some(Command::Jira(JiraCommand::CreateVersion { tag, released })) => {
+            let api = jira_api(&config, &args, &repo)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let tickets = release_tickets(&args, &trackers, &tag_commits, tag)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let tag_time = tag_commits.tag_times.get(tag).copied();
+            let reports = create_versions(&api, &trackers, tag, tag_time, &tickets, *released)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("update jira");
+            print_version_reports(tag, &reports);
         }
-
-        match filtered_commits.is_empty() {
-            true => {
-                println!("{}", format!("{} (no entries)", tag_name).dimmed())
+        Some(Command::Jira(JiraCommand::Comment {
+            tag,
+            template,
+            dry_run,
+        })) => {
+            let api = jira_api(&config, &args, &repo)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let tickets = release_tickets(&args, &trackers, &tag_commits, tag)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let reports = comment_tickets(
+                &api,
+                &trackers,
+                origin_remote(&repo, &args).as_ref(),
+                tag,
+                &tickets,
+                template.as_deref(),
+                *dry_run,
+            )
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("update jira");
+            print_comment_reports(tag, &reports);
+        }
+        Some(Command::Jira(JiraCommand::Transition(transition_args))) => {
+            let api = jira_api(&config, &args, &repo)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let tickets = release_tickets(&args, &trackers, &tag_commits, &transition_args.tag)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            let reports = transition_tickets(
+                &api,
+                &config.jira,
+                &trackers,
+                &repo,
+                &tag_commits,
+                &tickets,
+                transition_args,
+            )
+            .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("update jira");
+            print_transition_reports(&transition_args.tag, &reports);
+        }
+        Some(Command::Lint(lint_args)) => {
+            let report = lint_commits(&repo, &trackers, &config.lint, &args, lint_args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("lint commits");
+            print_lint_report(&report, args.format);
+            if !report.passed() {
+                exit_code = Some(ExitCode::LintFailed);
             }
-            false => {
-                println!("{}", tag_name.green().bold())
+        }
+        Some(Command::Audit(AuditCommand::Releases)) => {
+            let audit = audit_releases(&repo, max_age, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("audit releases");
+            print_release_audit(&audit, args.format);
+            if !audit.passed() {
+                exit_code = Some(ExitCode::AuditFailed);
             }
         }
-
-        for commit in filtered_commits {
-            if args.jira_url.is_some() {
-                println!(
-                    "  {: <10} | {}",
-                    commit.formatted_tickets.clone(),
-                    commit.formatted_urls.join(", ")
-                );
-            } else {
-                println!("  {}", commit.formatted_tickets.clone());
+        Some(Command::Cache(CacheCommand::Clear)) => {
+            if let Err(err) = clear_cache(&repo) {
+                exit_with_error(TagCommitsError::Io("clear cache", err), args.format);
             }
         }
+        Some(Command::Cache(CacheCommand::Stats)) => print_cache_stats(&repo),
+        Some(Command::Config(ConfigCommand::Show)) => show_config(&repo, &args, config)
+            .unwrap_or_else(|err| exit_with_error(err, args.format)),
+        // Handled above, since they don't need the config
+        Some(
+            Command::Completions { .. }
+            | Command::Man { .. }
+            | Command::Schema
+            | Command::SelfUpdate { .. }
+            | Command::Auth(_)
+            | Command::Config(ConfigCommand::Init { .. })
+            | Command::Doctor,
+        ) => {}
+        None if args.explain.is_some() => {
+            let tag_commits = get_tag_commits(&repo, max_age, &trackers, &args)
+                .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("search tags");
+            let commit = args.explain.clone().flatten();
+            let explanations =
+                explain_commits(&repo, max_age, &args, &tag_commits, commit.as_deref())
+                    .unwrap_or_else(|err| exit_with_error(err, args.format));
+            timings.phase("explain commits");
+            print_explanations(&explanations, &args);
+        }
+        None if args.watch => watch(&repo, args.interval, args.fetch, args.quiet, || {
+            list_releases(&repo, max_age, &trackers, &args, hooks, &mut timings);
+        }),
+        None => exit_code = list_releases(&repo, max_age, &trackers, &args, hooks, &mut timings),
+    }
+    timings.phase("print output");
+    timings.breakdown("match tickets", trackers.match_time());
+    timings.print();
+
+    if trackers.had_api_errors() {
+        exit_code = exit_code.or(Some(ExitCode::ApiError));
+    }
+    if let Some(exit_code) = exit_code {
+        exit_code.exit();
     }
 }
@@ -0,0 +1,18 @@
+use clap::CommandFactory;
+use clap_mangen::Man;
+use std::{fs, io, path::Path};
+
+use crate::cli::Args;
+
+/// Print the man page for rels, or write a page for rels and each subcommand to `out_dir`, e.g.
+/// `rels.1` and `rels-lint.1`.
+pub fn print_man_page(out_dir: Option<&Path>) -> io::Result<()> {
+    let command = Args::command();
+    match out_dir {
+        Some(out_dir) => {
+            fs::create_dir_all(out_dir)?;
+            clap_mangen::generate_to(command, out_dir)
+        }
+        None => Man::new(command).render(&mut io::stdout()),
+    }
+}
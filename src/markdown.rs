@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::{
+    cli::Args,
+    releases::{
+        commit_notes, commits_by_tag, contributors, get_first_releases, one_line, release_commits,
+        CommitTagInfo, TagCommits,
+    },
+    tickets::Trackers,
+};
+
+/// A ticket as a markdown link, if the tracker has a URL for it.
+fn ticket_link(id: &str, url: Option<String>) -> String {
+    match url {
+        Some(url) => format!("[{}]({})", id, url),
+        None => id.to_owned(),
+    }
+}
+
+/// A list item for a commit: its tickets, release note, and any notes about it.
+fn commit_item(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    info: &CommitTagInfo,
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> String {
+    let mut item = info
+        .tickets
+        .iter()
+        .map(|ticket| {
+            let link = ticket_link(&ticket.id, trackers.link(ticket));
+            match first_releases.and_then(|releases| releases.get(ticket.id.as_str())) {
+                Some(release) => format!("{} (first released in {})", link, release),
+                None => link,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if item.is_empty() {
+        item = info.release_note.clone();
+    } else {
+        item = format!("{}: {}", item, info.release_note);
+    }
+    if let Some((number, url)) = info
+        .pr_number
+        .filter(|_| args.show_pr)
+        .and_then(|number| Some((number, trackers.pr_url(number)?)))
+    {
+        item = format!("{} ([#{}]({}))", item, number, url);
+    }
+    for note in commit_notes(args, tag_commits, info) {
+        item = format!("{} {}", item, note);
+    }
+    format!("- {}", item)
+}
+
+/// The markdown for a release without its heading: its deployments and notes, a list item per
+/// commit, and the links, assets and contributors enabled by the options.
+fn release_markdown(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    tag_name: &str,
+    commits: &[&CommitTagInfo],
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> String {
+    let mut lines = Vec::new();
+    match tag_commits.tag_environments.get(tag_name) {
+        Some(environments) if environments.is_empty() => lines.push("_Not deployed_\n".to_owned()),
+        Some(environments) => lines.push(format!("_Deployed to {}_\n", environments.join(", "))),
+        None => {}
+    }
+    if let Some(note) = tag_commits.tag_notes.get(tag_name) {
+        lines.push(format!("> {}\n", one_line(note)));
+    }
+    if let Some(stat) = tag_commits.tag_diffstats.get(tag_name) {
+        let directories = stat
+            .top_directories()
+            .filter(|_| args.dirstat)
+            .map(|(percentage, dir)| format!("{:.1}% `{}`", percentage, dir.path))
+            .collect::<Vec<_>>();
+        if directories.is_empty() {
+            lines.push(format!("_{}_\n", stat.summary()));
+        } else {
+            lines.push(format!(
+                "_{}: {}_\n",
+                stat.summary(),
+                directories.join(", ")
+            ));
+        }
+    }
+    if commits.is_empty() {
+        lines.push("_No entries_".to_owned());
+        return lines.join("\n");
+    }
+    for info in commits {
+        lines.push(commit_item(
+            args,
+            trackers,
+            tag_commits,
+            info,
+            first_releases,
+        ));
+    }
+    if let Some(url) = trackers.compare_url(tag_commits, tag_name) {
+        let previous = &tag_commits.previous_releases[tag_name];
+        lines.push(String::new());
+        lines.push(format!(
+            "**Full changelog**: [{}...{}]({})",
+            previous, tag_name, url
+        ));
+    }
+    if let Some(assets) = trackers
+        .release_assets(tag_name)
+        .filter(|assets| !assets.is_empty())
+    {
+        lines.push("\n### Assets\n".to_owned());
+        for asset in assets {
+            lines.push(match asset.format_size() {
+                Some(size) => format!("- [{}]({}) ({})", asset.name, asset.url, size),
+                None => format!("- [{}]({})", asset.name, asset.url),
+            });
+        }
+    }
+    if args.contributors {
+        lines.push("\n### Contributors\n".to_owned());
+        for (name, commits) in contributors(commits) {
+            let plural = if commits == 1 { "" } else { "s" };
+            lines.push(format!("- {} ({} commit{})", name, commits, plural));
+        }
+    }
+    lines.join("\n")
+}
+
+/// The release notes of a tag as markdown, as `--format markdown` prints them but without the
+/// heading, e.g. for the body of a release on the forge. Returns `None` if the tag isn't a
+/// release or doesn't match `--filter`.
+pub fn release_notes(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    tag_name: &str,
+) -> Option<String> {
+    let ids = commits_by_tag(tag_commits)
+        .get(tag_name)
+        .cloned()
+        .unwrap_or_default();
+    let commits = release_commits(args, trackers, tag_commits, tag_name, ids)?;
+    trackers.prefetch_details(commits.iter().flat_map(|info| &info.tickets));
+    let first_releases = args.first_release.then(|| get_first_releases(tag_commits));
+    Some(release_markdown(
+        args,
+        trackers,
+        tag_commits,
+        tag_name,
+        &commits,
+        first_releases.as_ref(),
+    ))
+}
+
+/// A release rendered as markdown.
+pub struct ReleaseMarkdown<'a> {
+    pub tag_name: &'a str,
+    pub commits: Vec<&'a CommitTagInfo>,
+    /// The markdown for the release, without a heading
+    pub body: String,
+}
+
+/// Render every release which matches `--filter` as markdown.
+pub fn releases_markdown<'a>(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &'a TagCommits,
+) -> Vec<ReleaseMarkdown<'a>> {
+    let tag_to_commits = commits_by_tag(tag_commits);
+    let first_releases = args.first_release.then(|| get_first_releases(tag_commits));
+
+    let mut releases = Vec::new();
+    for tag_name in &tag_commits.tag_names {
+        let ids = tag_to_commits
+            .get(tag_name.as_str())
+            .cloned()
+            .unwrap_or_default();
+        let Some(commits) = release_commits(args, trackers, tag_commits, tag_name, ids) else {
+            continue;
+        };
+        trackers.prefetch_details(commits.iter().flat_map(|info| &info.tickets));
+        let body = release_markdown(
+            args,
+            trackers,
+            tag_commits,
+            tag_name,
+            &commits,
+            first_releases.as_ref(),
+        );
+        releases.push(ReleaseMarkdown {
+            tag_name,
+            commits,
+            body,
+        });
+    }
+    releases
+}
+
+/// Every release as a markdown document, with a heading per release and a list item per
+/// commit.
+pub fn markdown_document(releases: &[ReleaseMarkdown]) -> String {
+    releases
+        .iter()
+        .map(|release| format!("## {}\n\n{}\n", release.tag_name, release.body))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print every release as markdown, returning whether any releases matched `--filter`.
+pub fn print_releases_markdown(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> bool {
+    let releases = releases_markdown(args, trackers, tag_commits);
+    print!("{}", markdown_document(&releases));
+    !releases.is_empty()
+}
@@ -0,0 +1,329 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    cmp::Reverse,
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+use tracing::debug;
+
+use git2::Repository;
+
+use crate::{
+    cli::{Args, OrphansArgs},
+    json::{releases_json, ticket_json, ticket_releases_json, ReleaseJson, TicketJson},
+    orphans::get_orphan_commits,
+    releases::{get_tag_commits, TagCommits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// The newest version of the Model Context Protocol rels implements, which is used unless the
+/// client asks for an older one rels also supports.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", PROTOCOL_VERSION];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The number of releases `list_releases` returns if the client doesn't give a limit, so a
+/// long history doesn't fill the assistant's context.
+const DEFAULT_RELEASE_LIMIT: u64 = 10;
+
+/// The tools rels offers, with JSON schemas of their arguments.
+fn tools() -> Value {
+    json!([
+        {
+            "name": "list_releases",
+            "description": "List the releases of the repository, newest first, with their commits and the tickets each commit references.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "tag": { "type": "string", "description": "Only list the release with this tag" },
+                    "limit": { "type": "integer", "minimum": 1, "description": "The number of releases to list, 10 by default" }
+                }
+            }
+        },
+        {
+            "name": "find_ticket",
+            "description": "Find the releases containing a ticket, e.g. to answer which release fixed ABC-123, with the commits referencing it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "ticket": { "type": "string", "description": "The ticket, e.g. ABC-123" }
+                },
+                "required": ["ticket"]
+            }
+        },
+        {
+            "name": "compare_tags",
+            "description": "List the releases after one tag up to and including another, with the tickets they contain, e.g. to see what changes when upgrading.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "from": { "type": "string", "description": "The release being upgraded from, which isn't included" },
+                    "to": { "type": "string", "description": "The release being upgraded to" }
+                },
+                "required": ["from", "to"]
+            }
+        },
+        {
+            "name": "unreleased_commits",
+            "description": "List the commits on the mainline branches which aren't in any release yet, with the tickets they reference.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "branches": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The mainline branches, main and master by default"
+                    }
+                }
+            }
+        }
+    ])
+}
+
+#[derive(Serialize)]
+struct ComparisonJson<'a> {
+    from: &'a str,
+    to: &'a str,
+    releases: Vec<ReleaseJson<'a>>,
+    tickets: Vec<TicketJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct UnreleasedCommitJson<'a> {
+    sha: String,
+    summary: String,
+    tickets: Vec<TicketJson<'a>>,
+}
+
+/// An error from a tool, which is given to the assistant as the tool's result rather than as a
+/// protocol error so it can correct its arguments.
+struct ToolError(String);
+
+impl From<TagCommitsError> for ToolError {
+    fn from(err: TagCommitsError) -> Self {
+        ToolError(err.message())
+    }
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, ToolError> {
+    arguments[name]
+        .as_str()
+        .ok_or_else(|| ToolError(format!("{} is required", name)))
+}
+
+fn to_text(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).expect("tool results should serialize to JSON")
+}
+
+struct McpServer<'a> {
+    repo: &'a Repository,
+    max_age: Duration,
+    trackers: &'a Trackers,
+    args: &'a Args,
+}
+
+impl McpServer<'_> {
+    fn list_releases(&self, tag_commits: &TagCommits, arguments: &Value) -> String {
+        let mut releases = releases_json(self.args, self.trackers, tag_commits);
+        if let Some(tag) = arguments["tag"].as_str() {
+            releases.retain(|release| release.tag == tag);
+        }
+        releases.sort_by_key(|release| Reverse(tag_commits.release_order(release.tag)));
+        let limit = arguments["limit"].as_u64().unwrap_or(DEFAULT_RELEASE_LIMIT);
+        releases.truncate(limit.try_into().unwrap_or(usize::MAX));
+        to_text(&releases)
+    }
+
+    fn find_ticket(
+        &self,
+        tag_commits: &TagCommits,
+        arguments: &Value,
+    ) -> Result<String, ToolError> {
+        let ticket = string_argument(arguments, "ticket")?;
+        match ticket_releases_json(self.args, self.trackers, tag_commits, ticket) {
+            Some(releases) => Ok(to_text(&releases)),
+            None => Ok(format!("{} isn't in any release", ticket)),
+        }
+    }
+
+    fn compare_tags(
+        &self,
+        tag_commits: &TagCommits,
+        arguments: &Value,
+    ) -> Result<String, ToolError> {
+        let from = string_argument(arguments, "from")?;
+        let to = string_argument(arguments, "to")?;
+        for tag in [from, to] {
+            if !tag_commits.tag_names.iter().any(|name| name == tag) {
+                return Err(TagCommitsError::NotARelease(tag.to_owned()).into());
+            }
+        }
+        let (start, end) = (
+            tag_commits.release_order(from),
+            tag_commits.release_order(to),
+        );
+
+        let mut releases = releases_json(self.args, self.trackers, tag_commits);
+        releases.retain(|release| {
+            let order = tag_commits.release_order(release.tag);
+            start < order && order <= end
+        });
+        releases.sort_by_key(|release| tag_commits.release_order(release.tag));
+        let mut tickets = tag_commits
+            .commit_to_tag
+            .values()
+            .filter(|info| releases.iter().any(|release| release.tag == info.tag_name))
+            .flat_map(|info| &info.tickets)
+            .collect::<Vec<_>>();
+        tickets.sort_unstable();
+        tickets.dedup_by(|a, b| a.id == b.id);
+        let tickets = tickets
+            .into_iter()
+            .map(|ticket| ticket_json(ticket, self.trackers, None))
+            .collect();
+
+        Ok(to_text(&ComparisonJson {
+            from,
+            to,
+            releases,
+            tickets,
+        }))
+    }
+
+    fn unreleased_commits(&self, arguments: &Value) -> Result<String, ToolError> {
+        let branches = match arguments["branches"].as_array() {
+            Some(branches) => branches
+                .iter()
+                .filter_map(|branch| branch.as_str().map(str::to_owned))
+                .collect(),
+            None => vec!["main".to_owned(), "master".to_owned()],
+        };
+        let orphans = get_orphan_commits(
+            self.repo,
+            self.max_age,
+            self.trackers,
+            self.args,
+            &OrphansArgs { branches },
+        )?;
+        let commits = orphans
+            .iter()
+            .map(|orphan| UnreleasedCommitJson {
+                sha: orphan.sha.clone(),
+                summary: orphan.summary.clone(),
+                tickets: orphan
+                    .tickets
+                    .iter()
+                    .map(|ticket| ticket_json(ticket, self.trackers, None))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        Ok(to_text(&commits))
+    }
+
+    /// Call a tool, reading the releases again so tags created since the server started are
+    /// included.
+    fn call_tool(&self, name: &str, arguments: &Value) -> Result<String, ToolError> {
+        if name == "unreleased_commits" {
+            return self.unreleased_commits(arguments);
+        }
+        let tag_commits = get_tag_commits(self.repo, self.max_age, self.trackers, self.args)?;
+        match name {
+            "list_releases" => Ok(self.list_releases(&tag_commits, arguments)),
+            "find_ticket" => self.find_ticket(&tag_commits, arguments),
+            "compare_tags" => self.compare_tags(&tag_commits, arguments),
+            _ => Err(ToolError(format!("{} isn't a tool", name))),
+        }
+    }
+
+    /// The result of a request, or a JSON-RPC error code and message.
+    fn handle(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                let version = params["protocolVersion"]
+                    .as_str()
+                    .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+                    .unwrap_or(PROTOCOL_VERSION);
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "rels", "version": env!("CARGO_PKG_VERSION") }
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => {
+                let name = params["name"]
+                    .as_str()
+                    .ok_or((INVALID_PARAMS, "name is required".to_owned()))?;
+                let (text, is_error) = match self.call_tool(name, &params["arguments"]) {
+                    Ok(text) => (text, false),
+                    Err(ToolError(message)) => (message, true),
+                };
+                Ok(json!({
+                    "content": [{ "type": "text", "text": text }],
+                    "isError": is_error
+                }))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("{} isn't a method", method))),
+        }
+    }
+
+    /// The response to a message, or `None` for notifications, which have no ID.
+    fn respond(&self, line: &str) -> Option<Value> {
+        let message = match serde_json::from_str::<Value>(line) {
+            Ok(message) => message,
+            Err(err) => {
+                return Some(error_response(Value::Null, PARSE_ERROR, &err.to_string()));
+            }
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message["method"].as_str() else {
+            return id.map(|id| error_response(id, INVALID_REQUEST, "method is required"));
+        };
+        debug!("MCP {}", method);
+        let id = id?;
+        Some(match self.handle(method, &message["params"]) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Serve the releases to coding assistants over the Model Context Protocol, reading JSON-RPC
+/// messages from stdin and writing responses to stdout, one per line, until stdin is closed.
+pub fn serve_mcp(
+    repo: &Repository,
+    max_age: Duration,
+    trackers: &Trackers,
+    args: &Args,
+) -> Result<(), TagCommitsError> {
+    let server = McpServer {
+        repo,
+        max_age,
+        trackers,
+        args,
+    };
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|err| TagCommitsError::Io("read MCP message", err))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.respond(&line) {
+            writeln!(stdout, "{}", response)
+                .and_then(|_| stdout.flush())
+                .map_err(|err| TagCommitsError::Io("write MCP response", err))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use chrono::DateTime;
+use colored::Colorize;
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    cli::OutputFormat,
+    releases::TagCommits,
+    teamcity::{print_service_message, print_statistic},
+};
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+const MONTH: i64 = 30 * DAY;
+
+/// How a team performs on a DORA metric, using the bands of the DORA report.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum PerformanceLevel {
+    Elite,
+    High,
+    Medium,
+    Low,
+}
+
+impl PerformanceLevel {
+    /// Elite teams deploy at least daily, high performers at least weekly, and medium
+    /// performers at least monthly.
+    fn deployment_frequency(per_week: f64) -> PerformanceLevel {
+        if per_week >= 7.0 {
+            PerformanceLevel::Elite
+        } else if per_week >= 1.0 {
+            PerformanceLevel::High
+        } else if per_week >= WEEK as f64 / MONTH as f64 {
+            PerformanceLevel::Medium
+        } else {
+            PerformanceLevel::Low
+        }
+    }
+
+    /// Elite teams ship changes within a day, high performers within a week, and medium
+    /// performers within a month.
+    fn lead_time(seconds: i64) -> PerformanceLevel {
+        if seconds < DAY {
+            PerformanceLevel::Elite
+        } else if seconds < WEEK {
+            PerformanceLevel::High
+        } else if seconds < MONTH {
+            PerformanceLevel::Medium
+        } else {
+            PerformanceLevel::Low
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PerformanceLevel::Elite => "elite",
+            PerformanceLevel::High => "high",
+            PerformanceLevel::Medium => "medium",
+            PerformanceLevel::Low => "low",
+        }
+    }
+}
+
+/// The DORA metrics which can be measured from the releases in a window of time: how often
+/// releases are made, and how long commits take to be released.
+#[derive(Serialize)]
+pub struct Metrics {
+    /// The start of the window, as a Unix timestamp
+    pub window_start: i64,
+    pub window_end: i64,
+    pub releases: usize,
+    /// The commits in the releases, which are only those referencing tickets unless `--all`
+    /// is given
+    pub commits: usize,
+    pub deployments_per_week: f64,
+    pub deployment_frequency: PerformanceLevel,
+    /// The median time from a commit to the release containing it, in seconds
+    pub lead_time_median: Option<i64>,
+    pub lead_time_p90: Option<i64>,
+    pub lead_time: Option<PerformanceLevel>,
+}
+
+/// The value below which `fraction` of the sorted values fall, using the nearest rank.
+pub fn percentile(sorted: &[i64], fraction: f64) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Format a number of seconds as its two largest units, e.g. `3d 4h` or `12m`.
+pub fn format_duration(seconds: i64) -> String {
+    if seconds < 60 {
+        return "<1m".to_owned();
+    }
+    let units = [(DAY, "d"), (HOUR, "h"), (60, "m")];
+    units
+        .iter()
+        .scan(seconds, |remaining, (size, unit)| {
+            let count = *remaining / size;
+            *remaining %= size;
+            Some((count, unit))
+        })
+        .skip_while(|(count, _)| *count == 0)
+        .take(2)
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, unit)| format!("{}{}", count, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The current time, as a Unix timestamp.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+/// The start of the window of `--age` before now, as a Unix timestamp.
+pub fn window_start(max_age: Duration) -> i64 {
+    now().saturating_sub(max_age.as_secs() as i64)
+}
+
+/// The releases tagged within `--age` of now, oldest first.
+pub fn releases_within(tag_commits: &TagCommits, max_age: Duration) -> Vec<&str> {
+    let start = window_start(max_age);
+    let mut releases = tag_commits
+        .tag_names
+        .iter()
+        .map(String::as_str)
+        .filter(|tag_name| {
+            tag_commits
+                .tag_times
+                .get(*tag_name)
+                .is_some_and(|time| *time >= start)
+        })
+        .collect::<Vec<_>>();
+    releases.sort_by_key(|tag_name| tag_commits.release_order(tag_name));
+    releases
+}
+
+/// Measure the releases tagged within `--age` of now.
+pub fn get_metrics(tag_commits: &TagCommits, max_age: Duration) -> Metrics {
+    let window_end = now();
+    let window_start = window_start(max_age);
+    let in_window = releases_within(tag_commits, max_age);
+
+    let releases = in_window.len();
+    let mut lead_times = tag_commits
+        .commit_to_tag
+        .values()
+        .filter(|info| in_window.contains(&info.tag_name.as_str()))
+        .map(|info| (tag_commits.tag_times[&info.tag_name] - info.time).max(0))
+        .collect::<Vec<_>>();
+    lead_times.sort_unstable();
+
+    let weeks = (window_end - window_start).max(DAY) as f64 / WEEK as f64;
+    let deployments_per_week = releases as f64 / weeks;
+    let lead_time_median = percentile(&lead_times, 0.5);
+    Metrics {
+        window_start,
+        window_end,
+        releases,
+        commits: lead_times.len(),
+        deployments_per_week,
+        deployment_frequency: PerformanceLevel::deployment_frequency(deployments_per_week),
+        lead_time_median,
+        lead_time_p90: percentile(&lead_times, 0.9),
+        lead_time: lead_time_median.map(PerformanceLevel::lead_time),
+    }
+}
+
+/// Format a Unix timestamp as a date, e.g. `2024-03-01`.
+pub fn format_date(time: i64) -> String {
+    DateTime::from_timestamp(time, 0)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Print the metrics as TeamCity build statistics, which TeamCity charts across builds, with
+/// durations in seconds.
+fn print_teamcity_metrics(metrics: &Metrics) {
+    print_statistic("rels.releases", metrics.releases);
+    print_statistic("rels.commits", metrics.commits);
+    print_statistic(
+        "rels.deploymentsPerWeek",
+        format!("{:.2}", metrics.deployments_per_week),
+    );
+    if let Some(median) = metrics.lead_time_median {
+        print_statistic("rels.leadTimeMedian", median);
+    }
+    if let Some(p90) = metrics.lead_time_p90 {
+        print_statistic("rels.leadTimeP90", p90);
+    }
+    print_service_message(
+        "message",
+        &[(
+            "text",
+            &format!(
+                "Deployment frequency: {}, lead time for changes: {}",
+                metrics.deployment_frequency.name(),
+                metrics
+                    .lead_time
+                    .map_or("no commits", PerformanceLevel::name)
+            ),
+        )],
+    );
+}
+
+pub fn print_metrics(metrics: &Metrics, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(metrics).expect("metrics should serialize to JSON")
+            );
+            return;
+        }
+        OutputFormat::Teamcity => return print_teamcity_metrics(metrics),
+        _ => {}
+    }
+
+    println!(
+        "{: <22} {} to {}",
+        "Window",
+        format_date(metrics.window_start),
+        format_date(metrics.window_end)
+    );
+    println!("{: <22} {}", "Releases", metrics.releases);
+    println!("{: <22} {}", "Commits", metrics.commits);
+    println!(
+        "{: <22} {:.2} per week {}",
+        "Deployment frequency",
+        metrics.deployments_per_week,
+        format!("({})", metrics.deployment_frequency.name()).dimmed()
+    );
+    match (
+        metrics.lead_time_median,
+        metrics.lead_time_p90,
+        metrics.lead_time,
+    ) {
+        (Some(median), Some(p90), Some(level)) => println!(
+            "{: <22} {} median, {} p90 {}",
+            "Lead time for changes",
+            format_duration(median),
+            format_duration(p90),
+            format!("({})", level.name()).dimmed()
+        ),
+        _ => println!("{: <22} {}", "Lead time for changes", "no commits".dimmed()),
+    }
+}
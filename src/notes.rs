@@ -0,0 +1,90 @@
+use colored::Colorize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cli::Args,
+    markdown::releases_markdown,
+    releases::{TagCommits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// What happened to the notes file of a release.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NotesFileOutcome {
+    Created,
+    Updated,
+    /// The file already had the same notes, e.g. from an earlier run
+    Unchanged,
+}
+
+/// The notes file of a release.
+pub struct NotesFileReport {
+    pub tag: String,
+    pub path: PathBuf,
+    pub outcome: NotesFileOutcome,
+}
+
+/// Write the notes of every release matching `--filter` to its own markdown file in `dir`,
+/// named after its tag, e.g. `v1.5.0.md`, for a docs site or changelog directory. Files which
+/// already have the same notes are left alone, so their modification times and the history of
+/// the directory only show real changes.
+pub fn write_split_notes(
+    dir: &Path,
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+) -> Result<Vec<NotesFileReport>, TagCommitsError> {
+    let mut reports = Vec::new();
+    for release in releases_markdown(args, trackers, tag_commits) {
+        let path = dir.join(format!("{}.md", release.tag_name));
+        let content = format!("# {}\n\n{}\n", release.tag_name, release.body);
+        let outcome = match fs::read_to_string(&path) {
+            Ok(existing) if existing == content => NotesFileOutcome::Unchanged,
+            Ok(_) => NotesFileOutcome::Updated,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => NotesFileOutcome::Created,
+            Err(err) => return Err(TagCommitsError::Io("read release notes", err)),
+        };
+        if outcome != NotesFileOutcome::Unchanged {
+            // Tags such as app/v1.5.0 are written to a directory for the app
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|err| TagCommitsError::Io("create directory", err))?;
+            }
+            fs::write(&path, content)
+                .map_err(|err| TagCommitsError::Io("write release notes", err))?;
+        }
+        reports.push(NotesFileReport {
+            tag: release.tag_name.to_owned(),
+            path,
+            outcome,
+        });
+    }
+    Ok(reports)
+}
+
+/// Print what happened to each notes file, only mentioning files which were written if
+/// `quiet`.
+pub fn print_notes_reports(reports: &[NotesFileReport], quiet: bool) {
+    if reports.is_empty() {
+        if !quiet {
+            println!("{}", "No releases to write".dimmed());
+        }
+        return;
+    }
+
+    for report in reports {
+        let path = report.path.display();
+        match report.outcome {
+            NotesFileOutcome::Created => println!("Created {}: {}", report.tag.bold(), path),
+            NotesFileOutcome::Updated => println!("Updated {}: {}", report.tag.bold(), path),
+            NotesFileOutcome::Unchanged if quiet => {}
+            NotesFileOutcome::Unchanged => println!(
+                "{}",
+                format!("{} is already up to date", report.tag).dimmed()
+            ),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use colored::Colorize;
+
+use git2::{Repository, Sort};
+
+use crate::{
+    cli::{Args, OrphansArgs},
+    git::{commit_is_within_duration, get_branch_tip, get_release_tags},
+    releases::TagCommitsError,
+    table::Table,
+    tickets::{Ticket, Trackers},
+};
+
+/// A commit on a mainline branch which isn't contained in any release.
+pub struct OrphanCommit {
+    pub sha: String,
+    pub summary: String,
+    pub tickets: Vec<Ticket>,
+}
+
+/// Find commits reachable from the mainline branches but not from any tag within the age
+/// window. Commits older than the age window are ignored.
+pub fn get_orphan_commits(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    trackers: &Trackers,
+    args: &Args,
+    orphans_args: &OrphansArgs,
+) -> Result<Vec<OrphanCommit>, TagCommitsError> {
+    let mut revwalk = repo.revwalk()?;
+    if args.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+    let mut found_branch = false;
+    for branch in &orphans_args.branches {
+        if let Some(tip) = get_branch_tip(repo, branch) {
+            revwalk.push(tip)?;
+            found_branch = true;
+        }
+    }
+    if !found_branch {
+        return Err(TagCommitsError::NoBranches(orphans_args.branches.clone()));
+    }
+
+    for tag in get_release_tags(repo, args) {
+        let commit = repo.find_commit(tag.target()?.id())?;
+        if commit_is_within_duration(&commit, max_age) {
+            revwalk.hide(commit.id())?;
+        }
+    }
+
+    let mut orphans = Vec::new();
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if !commit_is_within_duration(&commit, max_age) || !args.includes_commit(&commit) {
+            continue;
+        }
+
+        let message = commit.message().unwrap_or_default();
+        orphans.push(OrphanCommit {
+            sha: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_owned(),
+            tickets: trackers.find_tickets(message),
+        });
+    }
+    Ok(orphans)
+}
+
+/// Print the orphaned commits, without the header or the message when there are none if
+/// `quiet`.
+pub fn print_orphans(orphans: &[OrphanCommit], trackers: &Trackers, quiet: bool) {
+    if orphans.is_empty() {
+        if !quiet {
+            println!("{}", "All commits are contained in a release".dimmed());
+        }
+        return;
+    }
+
+    if !quiet {
+        println!(
+            "{}",
+            format!("{} commit(s) not contained in any release", orphans.len())
+                .yellow()
+                .bold()
+        );
+    }
+    trackers.prefetch_details(orphans.iter().flat_map(|orphan| &orphan.tickets));
+    let mut table = Table::new();
+    for orphan in orphans {
+        let tickets = if orphan.tickets.is_empty() {
+            "(no tickets)".dimmed().to_string()
+        } else {
+            orphan
+                .tickets
+                .iter()
+                .map(|ticket| trackers.format(ticket))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        table.push(vec![
+            format!("{} {}", orphan.sha[..7].yellow(), tickets),
+            orphan.summary.clone(),
+        ]);
+    }
+    table.print(2);
+}
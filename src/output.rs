@@ -0,0 +1,79 @@
+use std::{fs, path::Path};
+
+use crate::{
+    cli::{Args, OutputFormat},
+    json::{release_jsonl, releases_json},
+    markdown::{markdown_document, releases_markdown},
+    releases::{TagCommits, TagCommitsError},
+    serve::releases_html,
+    tickets::Trackers,
+};
+
+/// The formats releases can be written to a file in with `--output`.
+enum FileFormat {
+    Markdown,
+    Json,
+    Jsonl,
+    Html,
+}
+
+/// The format to write a file in, from its extension, or `--format` if the extension isn't
+/// one rels knows.
+fn file_format(path: &Path, format: OutputFormat) -> Result<FileFormat, TagCommitsError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match (extension.as_deref(), format) {
+        (Some("md" | "markdown"), _) => Ok(FileFormat::Markdown),
+        (Some("json"), _) => Ok(FileFormat::Json),
+        (Some("jsonl" | "ndjson"), _) => Ok(FileFormat::Jsonl),
+        (Some("html" | "htm"), _) => Ok(FileFormat::Html),
+        (_, OutputFormat::Markdown) => Ok(FileFormat::Markdown),
+        (_, OutputFormat::Json) => Ok(FileFormat::Json),
+        (_, OutputFormat::Jsonl) => Ok(FileFormat::Jsonl),
+        _ => Err(TagCommitsError::UnknownOutputFormat(path.to_owned())),
+    }
+}
+
+/// Write every release to a file instead of printing it, in the format its extension is for,
+/// creating the directories it is in if needed. Returns whether any releases matched
+/// `--filter`.
+pub fn write_output(
+    path: &Path,
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+) -> Result<bool, TagCommitsError> {
+    let (contents, matched) = match file_format(path, args.format)? {
+        FileFormat::Markdown => {
+            let releases = releases_markdown(args, trackers, tag_commits);
+            (markdown_document(&releases), !releases.is_empty())
+        }
+        FileFormat::Json => {
+            let releases = releases_json(args, trackers, tag_commits);
+            let json =
+                serde_json::to_string_pretty(&releases).expect("releases should serialize to JSON");
+            (json + "\n", !releases.is_empty())
+        }
+        FileFormat::Jsonl => {
+            let releases = releases_json(args, trackers, tag_commits);
+            let jsonl = releases.iter().map(release_jsonl).collect();
+            (jsonl, !releases.is_empty())
+        }
+        FileFormat::Html => {
+            let releases = releases_json(args, trackers, tag_commits);
+            let matched = !releases.is_empty();
+            (releases_html(releases), matched)
+        }
+    };
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(|err| TagCommitsError::Io("create directory", err))?;
+    }
+    fs::write(path, contents).map_err(|err| TagCommitsError::Io("write output", err))?;
+    Ok(matched)
+}
@@ -0,0 +1,73 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use git2::Repository;
+
+use crate::{
+    cli::Args,
+    json::releases_json,
+    releases::{get_tag_commits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// Run a plugin, an executable named `rels-<name>` on the PATH, as cargo does for its
+/// subcommands, so teams can add their own formats and publishing steps. The plugin is given
+/// the arguments after its name, and the releases on stdin as the JSON `--format json` prints,
+/// read with the global options given before its name, e.g. `rels --age 30d confluence --space
+/// OPS`. `RELS_VERSION` and `RELS_GIT_DIR` are set for it. Returns the plugin's exit code.
+pub fn run_plugin(
+    repo: &Repository,
+    max_age: Duration,
+    trackers: &Trackers,
+    args: &Args,
+    plugin_args: &[String],
+) -> Result<i32, TagCommitsError> {
+    let (name, plugin_args) = plugin_args
+        .split_first()
+        .expect("clap should give the plugin's name");
+    // Start the plugin before reading the releases, so a missing plugin fails straight away
+    let mut plugin = Command::new(format!("rels-{}", name))
+        .args(plugin_args)
+        .env("RELS_VERSION", env!("CARGO_PKG_VERSION"))
+        .env("RELS_GIT_DIR", repo.path())
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => TagCommitsError::PluginNotFound(name.clone()),
+            _ => TagCommitsError::Io("run plugin", err),
+        })?;
+
+    let tag_commits = match get_tag_commits(repo, max_age, trackers, args) {
+        Ok(tag_commits) => tag_commits,
+        Err(err) => {
+            // Don't leave the plugin waiting for releases which aren't coming
+            let _ = plugin.kill();
+            let _ = plugin.wait();
+            return Err(err);
+        }
+    };
+    let releases = releases_json(args, trackers, &tag_commits);
+    let mut stdin = plugin
+        .stdin
+        .take()
+        .expect("the plugin's stdin should be piped");
+    let written = serde_json::to_writer(&mut stdin, &releases)
+        .map_err(io::Error::from)
+        .and_then(|_| stdin.write_all(b"\n"));
+    // Close stdin so the plugin sees the end of the releases
+    drop(stdin);
+    let status = plugin
+        .wait()
+        .map_err(|err| TagCommitsError::Io("run plugin", err))?;
+    // Plugins don't have to read the releases, in which case they may exit before they are
+    // written
+    match written {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            Err(TagCommitsError::Io("write releases to plugin", err))
+        }
+        _ => Ok(status.code().unwrap_or(1)),
+    }
+}
@@ -0,0 +1,133 @@
+use git2::Oid;
+
+use crate::{
+    cli::Args,
+    releases::{commits_by_tag, release_commits, CommitTagInfo, TagCommits},
+    tickets::Trackers,
+};
+
+/// Replace the tabs separating fields in a value, so values can't be mistaken for more fields.
+fn field(value: &str) -> String {
+    value.replace('\t', " ")
+}
+
+/// A commit as a `-z` record: its tag, sha, commit time as a Unix timestamp, author, tickets
+/// separated by commas, and release note, separated by tabs and ended with a NUL byte. Release
+/// notes are kept whole, as they can't contain NUL bytes, so may span lines.
+fn commit_record(tag_name: &str, info: &CommitTagInfo) -> String {
+    let tickets = info
+        .tickets
+        .iter()
+        .map(|ticket| ticket.id.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\0",
+        field(tag_name),
+        info.id,
+        info.time,
+        field(&info.author),
+        tickets,
+        field(&info.release_note)
+    )
+}
+
+/// Print the records for the commits of a release as soon as it has been searched, returning
+/// whether it matched `--filter`.
+pub fn print_release_porcelain<'a>(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    tag_name: &str,
+    ids: impl IntoIterator<Item = &'a Oid>,
+) -> bool {
+    let Some(commits) = release_commits(args, trackers, tag_commits, tag_name, ids) else {
+        return false;
+    };
+    for info in commits {
+        print!("{}", commit_record(tag_name, info));
+    }
+    true
+}
+
+/// Print a record for each commit of every release for scripts, with a fixed field order which
+/// won't change between versions. Returns whether any releases matched `--filter`.
+pub fn print_releases_porcelain(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+) -> bool {
+    let tag_to_commits = commits_by_tag(tag_commits);
+    let mut printed = false;
+    for tag_name in &tag_commits.tag_names {
+        let ids = tag_to_commits
+            .get(tag_name.as_str())
+            .cloned()
+            .unwrap_or_default();
+        printed |= print_release_porcelain(args, trackers, tag_commits, tag_name, ids);
+    }
+    printed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tickets::Ticket;
+
+    fn commit(author: &str, release_note: &str) -> CommitTagInfo {
+        CommitTagInfo {
+            id: Oid::from_str("1111111111111111111111111111111111111111")
+                .expect("the OID should be valid"),
+            time: 1_700_000_000,
+            author_time: 1_700_000_000,
+            depth: 0,
+            tag_name: "v1.0".to_owned(),
+            tickets: vec![
+                Ticket {
+                    id: "ABC-1".to_owned(),
+                    tracker: 0,
+                },
+                Ticket {
+                    id: "ABC-2".to_owned(),
+                    tracker: 0,
+                },
+            ],
+            author: author.to_owned(),
+            co_authors: Vec::new(),
+            reviewers: Vec::new(),
+            release_note: release_note.to_owned(),
+            message: "ABC-1 ABC-2 fix".to_owned(),
+            environments: Vec::new(),
+            git_note: None,
+            pr_number: None,
+            pr_title: None,
+            cherry_picked_in: Vec::new(),
+            is_cherry_pick: false,
+            reverts: None,
+            reverted_by: None,
+        }
+    }
+
+    #[test]
+    fn records_have_fixed_fields_ended_by_nul() {
+        let record = commit_record("v1.0", &commit("Alice", "Fixed a bug"));
+
+        assert_eq!(
+            record,
+            "v1.0\t1111111111111111111111111111111111111111\t1700000000\tAlice\tABC-1,ABC-2\tFixed a bug\0"
+        );
+    }
+
+    #[test]
+    fn tabs_in_values_are_replaced_but_newlines_are_kept() {
+        let record = commit_record("v1.0", &commit("Alice\tSmith", "Fixed\ta bug\nand more"));
+        let fields = record
+            .trim_end_matches('\0')
+            .split('\t')
+            .collect::<Vec<_>>();
+
+        assert_eq!(fields.len(), 6);
+        assert_eq!(fields[3], "Alice Smith");
+        assert_eq!(fields[5], "Fixed a bug\nand more");
+    }
+}
@@ -0,0 +1,244 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use git2::Repository;
+
+use crate::{
+    auth::{token, AuthService},
+    cli::Args,
+    config::{ConfigError, TrackerConfig},
+    forge::origin_remote,
+    http::HttpClient,
+    jira::JiraApi,
+    lookups::TicketApi,
+};
+
+pub const DEFAULT_JIRA_REGEX: &str = "[A-Z]+-[0-9]+";
+
+/// The environment variable with the email of the Jira Cloud account `JIRA_API_TOKEN` belongs to.
+/// Tokens for Jira Data Center are personal access tokens, which don't need it.
+const JIRA_EMAIL_VAR: &str = "JIRA_EMAIL";
+
+/// The root of a Jira instance from its ticket URL, e.g. `https://jira.example.com` for
+/// `https://jira.example.com/browse/{ticket}`.
+fn jira_base_url(url: &str) -> Option<String> {
+    if let Some(index) = url.find("/browse/") {
+        return Some(url[..index].to_owned());
+    }
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest.split('/').next()?;
+    Some(format!("{}://{}", scheme, host))
+}
+
+/// Built-in issue tracker configurations, selected with `--tracker` or `preset` in the config.
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrackerPreset {
+    /// Jira tickets such as ABC-123, linked with --jira-url
+    Jira,
+    /// GitHub issues and pull requests such as #123, linked to the origin remote
+    Github,
+    /// GitLab issues such as #123 and merge requests such as !123, linked to the origin remote
+    Gitlab,
+    /// Azure Boards work items such as AB#123, linked to the configured organization and project
+    AzureBoards,
+    /// Linear issues such as ENG-123, linked to the configured workspace
+    Linear,
+    /// Shortcut stories such as sc-123, linked to the configured workspace
+    Shortcut,
+    /// YouTrack issues such as ABC-123, linked to the configured base_url
+    Youtrack,
+}
+
+impl fmt::Display for TrackerPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self
+            .to_possible_value()
+            .expect("presets should not be skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+impl TrackerPreset {
+    /// The tracker configurations for the preset. URLs for forge presets are built from the
+    /// `base_url` setting if set (e.g. for self-hosted forges), otherwise the `origin` remote, so
+    /// are only set if one of them exists.
+    pub fn tracker_configs(
+        self,
+        repo: &Repository,
+        args: &Args,
+        settings: &TrackerConfig,
+    ) -> Vec<TrackerConfig> {
+        let base_url = settings
+            .base_url
+            .as_ref()
+            .map(|base_url| base_url.trim_end_matches('/').to_owned());
+        let origin = origin_remote(repo, args);
+        let tracker = |name: &str, regex: &str, url: Option<String>| TrackerConfig {
+            name: Some(name.to_owned()),
+            regex: Some(regex.to_owned()),
+            url,
+            preset: Some(self),
+            ..Default::default()
+        };
+
+        match self {
+            TrackerPreset::Jira => vec![tracker(
+                "jira",
+                DEFAULT_JIRA_REGEX,
+                args.jira_url.first().cloned(),
+            )],
+            TrackerPreset::Github => vec![tracker(
+                "github",
+                r"\B#[0-9]+\b",
+                // GitHub redirects issue URLs to pull requests where needed
+                base_url
+                    .or_else(|| origin.map(|remote| remote.web_url()))
+                    .map(|base_url| format!("{}/issues/{{number}}", base_url)),
+            )],
+            TrackerPreset::Gitlab => {
+                let base_url = base_url.or_else(|| origin.map(|remote| remote.web_url()));
+                vec![
+                    tracker(
+                        "gitlab",
+                        r"\B#[0-9]+\b",
+                        base_url
+                            .as_ref()
+                            .map(|base_url| format!("{}/-/issues/{{number}}", base_url)),
+                    ),
+                    tracker(
+                        "gitlab-mr",
+                        r"\B![0-9]+\b",
+                        base_url
+                            .map(|base_url| format!("{}/-/merge_requests/{{number}}", base_url)),
+                    ),
+                ]
+            }
+            TrackerPreset::AzureBoards => {
+                let project_url = match (&settings.organization, &settings.project) {
+                    (Some(organization), Some(project)) => Some(format!(
+                        "https://dev.azure.com/{}/{}",
+                        organization, project
+                    )),
+                    _ => None,
+                };
+                vec![tracker(
+                    "azure-boards",
+                    r"\bAB#[0-9]+\b",
+                    base_url
+                        .or(project_url)
+                        .or_else(|| origin.and_then(|remote| remote.azure_devops_project_url()))
+                        .map(|base_url| format!("{}/_workitems/edit/{{number}}", base_url)),
+                )]
+            }
+            TrackerPreset::Linear => vec![tracker(
+                "linear",
+                r"\b[A-Z][A-Z0-9]*-[0-9]+\b",
+                settings
+                    .workspace
+                    .as_ref()
+                    .map(|workspace| format!("https://linear.app/{}/issue/{{ticket}}", workspace)),
+            )],
+            // Also matches references in branch names and commit messages like [sc-123]
+            TrackerPreset::Shortcut => vec![tracker(
+                "shortcut",
+                r"\bsc-[0-9]+\b",
+                settings.workspace.as_ref().map(|workspace| {
+                    format!("https://app.shortcut.com/{}/story/{{number}}", workspace)
+                }),
+            )],
+            TrackerPreset::Youtrack => vec![tracker(
+                "youtrack",
+                r"\b[A-Z][A-Z0-9]*-[0-9]+\b",
+                base_url.map(|base_url| format!("{}/issue/{{ticket}}", base_url)),
+            )],
+        }
+    }
+
+    /// The environment variable holding the API key for looking up the preset's tickets, if it
+    /// has an API. The key can also be stored in the OS keyring with `rels auth login`.
+    pub fn api_key_var(self) -> Option<&'static str> {
+        match self {
+            TrackerPreset::Jira => Some("JIRA_API_TOKEN"),
+            TrackerPreset::Linear => Some("LINEAR_API_KEY"),
+            TrackerPreset::Youtrack => Some("YOUTRACK_TOKEN"),
+            _ => None,
+        }
+    }
+
+    /// The service the preset's API key is stored under with `rels auth login`.
+    fn auth_service(self) -> Option<AuthService> {
+        match self {
+            TrackerPreset::Jira => Some(AuthService::Jira),
+            TrackerPreset::Linear => Some(AuthService::Linear),
+            TrackerPreset::Youtrack => Some(AuthService::Youtrack),
+            _ => None,
+        }
+    }
+
+    /// The API for looking up the preset's tickets, or `None` if it doesn't have one. Fails if
+    /// the API key or another setting the API needs is missing.
+    pub fn api(
+        self,
+        settings: &TrackerConfig,
+        http: &HttpClient,
+    ) -> Result<Option<TicketApi>, ConfigError> {
+        let (Some(var), Some(service)) = (self.api_key_var(), self.auth_service()) else {
+            return Ok(None);
+        };
+        let api_key = token(var, service).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{} must be set, or a token stored with rels auth login {}, to look up {} tickets",
+                var, self, self
+            ))
+        })?;
+
+        match self {
+            TrackerPreset::Jira => {
+                let base_url = settings
+                    .base_url
+                    .clone()
+                    .or_else(|| settings.url.as_deref().and_then(jira_base_url))
+                    .ok_or_else(|| {
+                        ConfigError::Invalid(
+                            "base_url or --jira-url must be set to look up jira tickets".to_owned(),
+                        )
+                    })?;
+                // Jira Cloud uses basic auth with the account's email, and Jira Data Center
+                // uses personal access tokens
+                let authorization = match std::env::var(JIRA_EMAIL_VAR) {
+                    Ok(email) => format!(
+                        "Basic {}",
+                        BASE64_STANDARD.encode(format!("{}:{}", email, api_key))
+                    ),
+                    Err(_) => format!("Bearer {}", api_key),
+                };
+                Ok(Some(TicketApi::Jira(JiraApi::new(
+                    http.clone(),
+                    &base_url,
+                    authorization,
+                ))))
+            }
+            TrackerPreset::Linear => Ok(Some(TicketApi::Linear {
+                http: http.clone(),
+                api_key,
+            })),
+            TrackerPreset::Youtrack => {
+                let base_url = settings.base_url.as_ref().ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "base_url must be set to look up {} tickets",
+                        self
+                    ))
+                })?;
+                Ok(Some(TicketApi::Youtrack {
+                    http: http.clone(),
+                    base_url: base_url.trim_end_matches('/').to_owned(),
+                    token: api_key,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
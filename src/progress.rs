@@ -0,0 +1,18 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{io::IsTerminal, time::Duration};
+
+/// A progress bar on stderr for searching tags, which is hidden when stderr isn't a terminal.
+pub fn tag_progress(tag_count: usize) -> ProgressBar {
+    if !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let progress =
+        ProgressBar::with_draw_target(Some(tag_count as u64), ProgressDrawTarget::stderr());
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} {pos}/{len} tags, {msg} [{elapsed}]")
+            .expect("progress template should be valid"),
+    );
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
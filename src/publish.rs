@@ -0,0 +1,162 @@
+use colored::Colorize;
+use std::fs;
+
+use git2::Repository;
+
+use crate::{
+    cli::{Args, PublishArgs},
+    config::ConfigError,
+    forge_api::Forge,
+    forge_releases::{AssetUpload, ForgeRelease, ReleaseApi},
+    markdown::release_notes,
+    releases::{TagCommits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// The releases API of the origin remote, failing unless it is on `forge`.
+fn release_api(
+    repo: &Repository,
+    args: &Args,
+    forge: Forge,
+) -> Result<ReleaseApi, TagCommitsError> {
+    let command = match forge {
+        Forge::Github => "rels publish github",
+        Forge::Gitlab => "rels publish gitlab",
+    };
+    let api = ReleaseApi::required(repo, args, command)?;
+    if api.forge() != forge {
+        return Err(ConfigError::Invalid(format!(
+            "{} needs an origin remote on {}, not {}",
+            command,
+            forge.name(),
+            api.forge().name()
+        ))
+        .into());
+    }
+    Ok(api)
+}
+
+/// Read the files matched by each `--asset`, which is a path or glob pattern optionally
+/// followed by `:name` to upload a single file under another name.
+fn read_assets(assets: &[String]) -> Result<Vec<AssetUpload>, TagCommitsError> {
+    let mut uploads: Vec<AssetUpload> = Vec::new();
+    for asset in assets {
+        let invalid = |reason: String| TagCommitsError::InvalidAsset(asset.clone(), reason);
+        // A colon followed by a path is part of the path, e.g. a Windows drive
+        let (pattern, name) = match asset.rsplit_once(':') {
+            Some((pattern, name))
+                if !pattern.is_empty() && !name.is_empty() && !name.contains(['/', '\\']) =>
+            {
+                (pattern, Some(name))
+            }
+            _ => (asset.as_str(), None),
+        };
+        let paths = glob::glob(pattern)
+            .map_err(|err| invalid(err.to_string()))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        match (paths.len(), name) {
+            (0, _) => return Err(invalid("no files match it".to_owned())),
+            (1, _) | (_, None) => {}
+            (count, Some(_)) => {
+                return Err(invalid(format!(
+                    "{} files match it, which can't all have the same name",
+                    count
+                )))
+            }
+        }
+
+        for path in paths {
+            let name = match name {
+                Some(name) => name.to_owned(),
+                None => path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            if uploads.iter().any(|upload| upload.name == name) {
+                return Err(invalid(format!("another asset is already named {}", name)));
+            }
+            let contents = fs::read(&path).map_err(|err| TagCommitsError::Io("read asset", err))?;
+            uploads.push(AssetUpload { name, contents });
+        }
+    }
+    Ok(uploads)
+}
+
+/// Create a release on `forge` for the tag given, or the latest release, with its release notes
+/// as `--format markdown` prints them and any `--asset` files uploaded to it. With `draft` it is
+/// only visible to collaborators on GitHub until it is published with `--finalize`.
+pub fn publish_release(
+    repo: &Repository,
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    forge: Forge,
+    publish_args: &PublishArgs,
+    draft: bool,
+) -> Result<ForgeRelease, TagCommitsError> {
+    let api = release_api(repo, args, forge)?;
+    let tag = match &publish_args.tag {
+        Some(tag) => tag.clone(),
+        None => tag_commits
+            .tag_names
+            .iter()
+            .max_by_key(|tag_name| tag_commits.release_order(tag_name))
+            .cloned()
+            .ok_or(TagCommitsError::NoTags)?,
+    };
+    if !tag_commits.tag_names.contains(&tag) {
+        return Err(TagCommitsError::NotARelease(tag));
+    }
+    // Check every asset can be read before anything is created
+    let assets = read_assets(&publish_args.asset_files)?;
+    let notes = release_notes(args, trackers, tag_commits, &tag).unwrap_or_default();
+    api.create(&tag, &notes, draft, &assets).map_err(|err| {
+        let action = match forge {
+            Forge::Github => "create GitHub release",
+            Forge::Gitlab => "create GitLab release",
+        };
+        TagCommitsError::Api(action, err)
+    })
+}
+
+/// Publish the draft release of a tag on GitHub, after its notes have been reviewed.
+pub fn finalize_release(
+    repo: &Repository,
+    args: &Args,
+    tag: &str,
+) -> Result<ForgeRelease, TagCommitsError> {
+    release_api(repo, args, Forge::Github)?
+        .publish_draft(tag)
+        .map_err(|err| TagCommitsError::Api("publish GitHub release", err))?
+        .ok_or_else(|| TagCommitsError::NoDraftRelease(tag.to_owned()))
+}
+
+pub fn print_published(release: &ForgeRelease) {
+    let action = match release.draft {
+        true => "Created a draft release for",
+        false => "Published",
+    };
+    match &release.url {
+        Some(url) => println!("{} {}: {}", action, release.tag.bold(), url),
+        None => println!("{} {}", action, release.tag.bold()),
+    }
+    for asset in &release.assets {
+        match asset.format_size() {
+            Some(size) => println!("  {} {}", asset.name, format!("({})", size).dimmed()),
+            None => println!("  {}", asset.name),
+        }
+    }
+    if release.draft {
+        println!(
+            "{}",
+            format!(
+                "Review it, then publish it with rels publish github --finalize {}",
+                release.tag
+            )
+            .dimmed()
+        );
+    }
+}
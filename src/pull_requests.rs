@@ -0,0 +1,113 @@
+use serde::Deserialize;
+use std::cell::Cell;
+
+use git2::Repository;
+
+use crate::{
+    cli::Args,
+    forge_api::{warn, Forge, ForgeApi},
+    http::HttpClient,
+    lookups::LookupError,
+    releases::TagCommitsError,
+};
+
+/// The number of the pull request a commit was merged from: `(#123)` at the end of the summary
+/// of a GitHub squash merge, `Merge pull request #123 from ...` for a GitHub merge commit, or
+/// `See merge request group/project!123` (or just `!123`) for GitLab.
+pub fn pr_number(message: &str) -> Option<u64> {
+    let summary = message.lines().next()?.trim_end();
+    if let Some(rest) = summary.strip_suffix(')') {
+        if let Some((_, number)) = rest.rsplit_once("(#") {
+            return number.parse().ok();
+        }
+    }
+    if let Some(rest) = summary.strip_prefix("Merge pull request #") {
+        return rest.split_whitespace().next()?.parse().ok();
+    }
+    message.lines().find_map(|line| {
+        let reference = line.trim().strip_prefix("See merge request ")?;
+        reference.rsplit_once('!')?.1.parse().ok()
+    })
+}
+
+/// A pull request on GitHub, or a merge request on GitLab.
+pub struct PullRequest {
+    pub title: String,
+    pub body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubPullRequest {
+    title: String,
+    body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitlabMergeRequest {
+    title: String,
+    description: Option<String>,
+}
+
+/// The pull request API of the forge hosting the `origin` remote, for `--fetch-prs`.
+pub struct PullRequestApi {
+    api: ForgeApi,
+    /// Whether a request has failed, after which no more are made to avoid repeating the error
+    failed: Cell<bool>,
+}
+
+impl PullRequestApi {
+    /// The API of the origin remote, if it is on GitHub or GitLab and there is a token for it,
+    /// as described in [`ForgeApi::new`].
+    pub fn new(
+        repo: &Repository,
+        args: &Args,
+        http: Option<&HttpClient>,
+    ) -> Result<Option<PullRequestApi>, TagCommitsError> {
+        Ok(
+            ForgeApi::new(repo, args, http, "--fetch-prs")?.map(|api| PullRequestApi {
+                api,
+                failed: Cell::new(false),
+            }),
+        )
+    }
+
+    fn request(&self, number: u64) -> Result<Option<PullRequest>, LookupError> {
+        Ok(match self.api.forge {
+            Forge::Github => self
+                .api
+                .get::<GithubPullRequest>(&format!("pulls/{}", number))?
+                .map(|pull| PullRequest {
+                    title: pull.title,
+                    body: pull.body,
+                }),
+            Forge::Gitlab => self
+                .api
+                .get::<GitlabMergeRequest>(&format!("merge_requests/{}", number))?
+                .map(|merge| PullRequest {
+                    title: merge.title,
+                    body: merge.description,
+                }),
+        })
+    }
+
+    /// Fetch a pull request, returning `None` if it doesn't exist or a request has failed. The
+    /// first failure is reported as a warning.
+    pub fn fetch(&self, number: u64) -> Option<PullRequest> {
+        if self.failed.get() {
+            return None;
+        }
+        match self.request(number) {
+            Ok(pull_request) => pull_request,
+            Err(err) => {
+                self.failed.set(true);
+                warn(&format!("Failed to fetch pull requests: {}", err));
+                None
+            }
+        }
+    }
+
+    /// Whether fetching a pull request has failed.
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+}
@@ -0,0 +1,115 @@
+use clap::ValueEnum;
+
+use std::ops::Range;
+
+use crate::releases::{InvalidInput, TagCommitsError};
+
+/// The regex engine used for ticket regexes.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RegexEngine {
+    /// The regex crate, which guarantees linear time matching
+    #[default]
+    Standard,
+    /// fancy-regex, which supports lookaround and backreferences
+    Fancy,
+}
+
+/// The message and location of a syntax error in a pattern for the regex crate, if it has one
+/// rather than e.g. being too big.
+fn syntax_error(pattern: &str) -> Option<(String, Range<usize>)> {
+    let span = |span: &regex_syntax::ast::Span| {
+        span.start.offset..span.end.offset.max(span.start.offset + 1)
+    };
+    let ast = match regex_syntax::ast::parse::Parser::new().parse(pattern) {
+        Ok(ast) => ast,
+        Err(err) => return Some((err.kind().to_string(), span(err.span()))),
+    };
+    match regex_syntax::hir::translate::Translator::new().translate(pattern, &ast) {
+        Ok(_) => None,
+        Err(err) => Some((err.kind().to_string(), span(err.span()))),
+    }
+}
+
+/// A ticket regex compiled with either engine.
+pub enum TicketRegex {
+    Standard(regex::Regex),
+    Fancy(fancy_regex::Regex),
+}
+
+impl TicketRegex {
+    pub fn new(
+        pattern: &str,
+        engine: RegexEngine,
+        ignore_case: bool,
+    ) -> Result<TicketRegex, TagCommitsError> {
+        let flags = if ignore_case { "(?i)" } else { "" };
+        let full_pattern = format!("{}{}", flags, pattern);
+        let invalid = |message: String, span: Option<Range<usize>>| {
+            // Spans are relative to the pattern with the flags, which the user didn't write
+            let span = span.map(|span| {
+                span.start.saturating_sub(flags.len())..span.end.saturating_sub(flags.len())
+            });
+            TagCommitsError::InvalidRegex(InvalidInput {
+                input: pattern.to_owned(),
+                message,
+                span,
+            })
+        };
+
+        match engine {
+            RegexEngine::Standard => match regex::Regex::new(&full_pattern) {
+                Ok(regex) => Ok(TicketRegex::Standard(regex)),
+                Err(err) => Err(match syntax_error(&full_pattern) {
+                    Some((message, span)) => invalid(message, Some(span)),
+                    None => invalid(err.to_string(), None),
+                }),
+            },
+            RegexEngine::Fancy => match fancy_regex::Regex::new(&full_pattern) {
+                Ok(regex) => Ok(TicketRegex::Fancy(regex)),
+                Err(fancy_regex::Error::ParseError(position, err)) => {
+                    Err(invalid(err.to_string(), Some(position..position + 1)))
+                }
+                Err(err) => Err(invalid(err.to_string(), None)),
+            },
+        }
+    }
+
+    /// The text of every match in `text`. Matches which fail, e.g. by exceeding the backtrack
+    /// limit of fancy-regex, are skipped.
+    pub fn find_iter<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match self {
+            TicketRegex::Standard(regex) => regex
+                .find_iter(text)
+                .map(|regex_match| regex_match.as_str())
+                .collect(),
+            TicketRegex::Fancy(regex) => regex
+                .find_iter(text)
+                .filter_map(Result::ok)
+                .map(|regex_match| regex_match.as_str())
+                .collect(),
+        }
+    }
+
+    /// The names of the named capture groups.
+    pub fn capture_names(&self) -> Vec<&str> {
+        match self {
+            TicketRegex::Standard(regex) => regex.capture_names().flatten().collect(),
+            TicketRegex::Fancy(regex) => regex.capture_names().flatten().collect(),
+        }
+    }
+
+    /// The value of a named capture group in the first match in `text`.
+    pub fn capture<'a>(&self, text: &'a str, name: &str) -> Option<&'a str> {
+        match self {
+            TicketRegex::Standard(regex) => regex
+                .captures(text)?
+                .name(name)
+                .map(|capture| capture.as_str()),
+            TicketRegex::Fancy(regex) => regex
+                .captures(text)
+                .ok()??
+                .name(name)
+                .map(|capture| capture.as_str()),
+        }
+    }
+}
@@ -0,0 +1,1026 @@
+use colored::Colorize;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::PathBuf,
+};
+
+use git2::{Commit, Oid, Repository};
+use tracing::{debug, info, trace};
+
+use crate::{
+    cache::{release_key, Cache},
+    cherry_picks::link_cherry_picks,
+    cli::{Args, CommitColumn, OutputFormat},
+    config::ConfigError,
+    diffstat::{diff_stat, DiffStat},
+    environments::{link_environments, resolve_environments},
+    git::{
+        boundaries_are_linear, commit_is_within_duration, get_likely_ancestors, get_release_tags,
+        git_note, previous_release, tag_boundaries, tag_name_lossy, tag_time, tagged_boundaries,
+        walk_release, CommitDepthInfo, ReleaseWalk,
+    },
+    graph::{graph_parents, graph_rows, GraphRow},
+    lookups::{LookupError, TicketDetails},
+    metrics::format_date,
+    progress::tag_progress,
+    pull_requests::pr_number,
+    reverts::{link_reverts, reverted_sha},
+    self_update::UpdateError,
+    table::Table,
+    tickets::{Ticket, Trackers},
+    trailers::{trailer_names, trailer_values},
+};
+
+pub enum TagCommitsError {
+    NotARepository(PathBuf),
+    /// No annotated tags were found, or none matched `--tag-pattern`
+    NoTags,
+    /// A tag whose name isn't valid UTF-8, holding the name with invalid bytes replaced
+    InvalidTagName(String),
+    NoBranches(Vec<String>),
+    RefNotFound(String),
+    /// A tag given to a command which isn't one of the releases found
+    NotARelease(String),
+    Git(git2::Error),
+    /// A file operation failed, described by what was being done, e.g. "clear cache"
+    Io(&'static str, std::io::Error),
+    /// A ticket regex which doesn't compile
+    InvalidRegex(InvalidInput),
+    /// An --age which isn't a valid duration
+    InvalidDuration(InvalidInput),
+    Config(ConfigError),
+    /// A request to a tracker API failed, described by what was being done, e.g. "create Jira
+    /// version"
+    Api(&'static str, LookupError),
+    /// An `--asset` to publish which doesn't match a file to upload, holding the argument and
+    /// the reason
+    InvalidAsset(String, String),
+    /// `rels publish github --finalize` was given a tag without a draft release
+    NoDraftRelease(String),
+    /// A command which isn't built in, and isn't a plugin on the PATH, holding its name
+    PluginNotFound(String),
+    /// A hook command from the config file failed, holding the hook, e.g. "pre", and the reason
+    HookFailed(&'static str, String),
+    /// `rels jira transition` was run without previewing the same transitions with `--dry-run`
+    /// first, holding the tag
+    PreviewRequired(String),
+    /// `rels jira transition` was given a release which isn't a released version of a Jira
+    /// project with tickets in it, holding the tag and project
+    UnreleasedVersion(String, String),
+    /// A command was run with a `--format` it doesn't support, holding the command and format
+    UnsupportedFormat(&'static str, OutputFormat),
+    /// A file given to `--output` with an extension which isn't for a format releases can be
+    /// written in, when `--format` isn't one either
+    UnknownOutputFormat(PathBuf),
+    /// The OS keyring storing API tokens couldn't be accessed
+    Keyring(keyring::Error),
+    /// `rels self-update` failed to download or install the latest release
+    SelfUpdate(UpdateError),
+}
+
+/// Input which couldn't be parsed, with the part which failed if it is known.
+pub struct InvalidInput {
+    pub input: String,
+    pub message: String,
+    pub span: Option<Range<usize>>,
+}
+
+impl From<git2::Error> for TagCommitsError {
+    fn from(err: git2::Error) -> Self {
+        TagCommitsError::Git(err)
+    }
+}
+
+impl From<keyring::Error> for TagCommitsError {
+    fn from(err: keyring::Error) -> Self {
+        TagCommitsError::Keyring(err)
+    }
+}
+
+impl From<UpdateError> for TagCommitsError {
+    fn from(err: UpdateError) -> Self {
+        TagCommitsError::SelfUpdate(err)
+    }
+}
+
+impl From<ConfigError> for TagCommitsError {
+    fn from(err: ConfigError) -> Self {
+        TagCommitsError::Config(err)
+    }
+}
+
+pub struct TagCommits {
+    pub commit_to_tag: HashMap<Oid, CommitTagInfo>,
+    pub tag_names: Vec<String>,
+    pub tag_times: HashMap<String, i64>,
+    /// The commit each tag points at
+    pub tag_targets: HashMap<String, Oid>,
+    /// The git note attached to each tag, with `--git-notes`
+    pub tag_notes: HashMap<String, String>,
+    /// The environments each release has been deployed to, from `--environment`
+    pub tag_environments: HashMap<String, Vec<String>>,
+    /// The release each release follows, with `--diffstat` or `--compare-links`
+    pub previous_releases: HashMap<String, String>,
+    /// The changes since the previous release, with `--diffstat`
+    pub tag_diffstats: HashMap<String, DiffStat>,
+    /// The parents each commit is drawn connected to, with `--graph`
+    pub commit_parents: HashMap<Oid, Vec<Oid>>,
+}
+
+pub fn get_tag_commits(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    trackers: &Trackers,
+    args: &Args,
+) -> Result<TagCommits, TagCommitsError> {
+    search_tag_commits(repo, max_age, trackers, args, None)
+}
+
+/// Find the commits in each release, calling `on_release` with the SHAs of the commits in each
+/// release in order of tag name, as soon as they can no longer change.
+///
+/// A commit found from more than one tag belongs to the release it is closest to, so for tags on
+/// diverging branches a commit may move to a release searched later. Releases are then only
+/// passed to `on_release` once every tag has been searched, so they match `get_tag_commits`.
+pub fn stream_tag_commits(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    trackers: &Trackers,
+    args: &Args,
+    mut on_release: impl FnMut(&TagCommits, &str, &[Oid]),
+) -> Result<TagCommits, TagCommitsError> {
+    search_tag_commits(repo, max_age, trackers, args, Some(&mut on_release))
+}
+
+/// A callback passed the SHAs of the commits in a release once it has been searched.
+type OnRelease<'a> = &'a mut dyn FnMut(&TagCommits, &str, &[Oid]);
+
+fn search_tag_commits(
+    repo: &Repository,
+    max_age: std::time::Duration,
+    trackers: &Trackers,
+    args: &Args,
+    mut on_release: Option<OnRelease>,
+) -> Result<TagCommits, TagCommitsError> {
+    let mut tag_commits = TagCommits {
+        commit_to_tag: HashMap::new(),
+        tag_names: Vec::new(),
+        tag_times: HashMap::new(),
+        tag_targets: HashMap::new(),
+        tag_notes: HashMap::new(),
+        tag_environments: HashMap::new(),
+        previous_releases: HashMap::new(),
+        tag_diffstats: HashMap::new(),
+        commit_parents: HashMap::new(),
+    };
+    let environments = resolve_environments(repo, &args.environments);
+
+    let mut tags = get_release_tags(repo, args);
+    if tags.is_empty() {
+        return Err(TagCommitsError::NoTags);
+    }
+    tags.sort_by(|a, b| a.name_bytes().cmp(b.name_bytes()));
+    let boundaries = tag_boundaries(repo, &tags);
+    // Tags on one line of history each stop the search of the others, so no commit can move
+    let stream = on_release.is_some() && boundaries_are_linear(repo, &boundaries);
+    let mut pending = Vec::new();
+    let tagged = if args.show_diffstat() || args.compare_links {
+        tagged_boundaries(repo, &tags)
+    } else {
+        Vec::new()
+    };
+
+    let mut cache = if args.no_cache {
+        Cache::default()
+    } else {
+        Cache::load(repo)
+    };
+
+    info!("found {} tags", tags.len());
+    let progress = tag_progress(tags.len());
+    let mut commits_walked = 0;
+
+    for tag in tags {
+        progress.inc(1);
+        let tag_name = tag
+            .name()
+            .ok_or_else(|| TagCommitsError::InvalidTagName(tag_name_lossy(&tag)))?
+            .to_owned();
+        tag_commits.tag_names.push(tag_name.clone());
+
+        let commit = repo.find_commit(tag.target()?.id())?;
+        tag_commits
+            .tag_times
+            .insert(tag_name.clone(), tag_time(&tag, &commit));
+        tag_commits
+            .tag_targets
+            .insert(tag_name.clone(), commit.id());
+        if let Some(note) = args
+            .git_notes
+            .then(|| git_note(repo, args.notes_ref(), tag.id()))
+            .flatten()
+        {
+            tag_commits.tag_notes.insert(tag_name.clone(), note);
+        }
+        if !commit_is_within_duration(&commit, max_age) {
+            debug!(tag = tag_name, "skipping tag older than --age");
+            match (stream, on_release.as_mut()) {
+                (true, Some(on_release)) => {
+                    progress.suspend(|| on_release(&tag_commits, &tag_name, &[]))
+                }
+                _ => pending.push((tag_name, Vec::new())),
+            }
+            continue;
+        }
+
+        // The key is built from the likely ancestors, which are the boundaries a walk hides
+        // unless clock skew makes it fall back to checking every boundary. Releases walked that
+        // way are saved under the boundaries they hid, so are walked again rather than reused.
+        let key = release_key(
+            &get_likely_ancestors(repo, &commit, &boundaries),
+            args.depth,
+            args.first_parent,
+        );
+        let release_commits = match cache.get(commit.id(), &key) {
+            Some(release_commits) => {
+                debug!(tag = tag_name, "using cached commits");
+                release_commits
+            }
+            None => {
+                let ReleaseWalk { commits, hidden } =
+                    walk_release(repo, &commit, &boundaries, args.depth, args.first_parent)?;
+                if !args.no_cache {
+                    let key = release_key(&hidden, args.depth, args.first_parent);
+                    cache.insert(commit.id(), key, &commits);
+                }
+                commits
+            }
+        };
+        debug!(
+            tag = tag_name,
+            commits = release_commits.len(),
+            "searched release"
+        );
+        commits_walked += release_commits.len();
+        progress.set_message(format!("{} commits", commits_walked));
+
+        let release_ids = match args.graph {
+            true => release_commits.iter().map(|info| info.id).collect(),
+            false => HashSet::new(),
+        };
+        let mut added = Vec::new();
+        for release_commit in release_commits {
+            let CommitDepthInfo { id, depth } = release_commit;
+
+            if let Some(existing) = tag_commits.commit_to_tag.get(&id) {
+                if existing.depth < depth {
+                    trace!(
+                        commit = %id,
+                        tag = tag_name,
+                        closer_tag = existing.tag_name,
+                        "skipping commit closer to another tag"
+                    );
+                    continue;
+                }
+            }
+
+            if add_if_matches_regex(
+                repo,
+                &repo.find_commit(id)?,
+                &mut tag_commits.commit_to_tag,
+                depth,
+                &tag_name,
+                trackers,
+                args,
+            )? {
+                added.push(id);
+            }
+        }
+
+        if let Some((previous_tag, previous)) = previous_release(repo, &commit, &tagged) {
+            if args.show_diffstat() {
+                let stat = diff_stat(repo, previous.id, previous_tag, &commit)?;
+                tag_commits.tag_diffstats.insert(tag_name.clone(), stat);
+            }
+            tag_commits
+                .previous_releases
+                .insert(tag_name.clone(), previous_tag.clone());
+        }
+        if args.graph {
+            for id in &added {
+                let parents = graph_parents(
+                    repo,
+                    &tag_commits.commit_to_tag,
+                    &release_ids,
+                    *id,
+                    args.first_parent,
+                )?;
+                tag_commits.commit_parents.insert(*id, parents);
+            }
+        }
+        link_reverts(&mut tag_commits, &added);
+        link_environments(repo, &environments, &mut tag_commits, &tag_name, &added);
+        match (stream, on_release.as_mut()) {
+            (true, Some(on_release)) => {
+                progress.suspend(|| on_release(&tag_commits, &tag_name, &added))
+            }
+            _ => pending.push((tag_name, added)),
+        }
+    }
+
+    progress.finish_and_clear();
+
+    if !args.no_cache {
+        // The cache only speeds up future runs, so failing to write it isn't an error
+        let tagged = boundaries.iter().map(|boundary| boundary.id).collect();
+        let _ = cache.save(repo, &tagged);
+    }
+
+    // Link reverts again now every release is known, since a reverted commit may be found after
+    // the commit reverting it
+    let all_commits = tag_commits
+        .commit_to_tag
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    link_reverts(&mut tag_commits, &all_commits);
+    if args.cherry_picks {
+        link_cherry_picks(repo, &mut tag_commits)?;
+    }
+
+    if let Some(on_release) = on_release.filter(|_| !stream) {
+        for (tag_name, added) in pending {
+            // Leave out commits which moved to a release searched later
+            let ids = added
+                .into_iter()
+                .filter(|id| {
+                    tag_commits
+                        .commit_to_tag
+                        .get(id)
+                        .is_some_and(|info| info.tag_name == tag_name)
+                })
+                .collect::<Vec<_>>();
+            on_release(&tag_commits, &tag_name, &ids);
+        }
+    }
+    Ok(tag_commits)
+}
+
+/// Add a commit to a release if it references a ticket (or all commits are shown), returning
+/// whether it was added.
+fn add_if_matches_regex(
+    repo: &Repository,
+    commit: &Commit,
+    commit_to_tag: &mut HashMap<Oid, CommitTagInfo>,
+    depth: usize,
+    tag_name: &str,
+    trackers: &Trackers,
+    args: &Args,
+) -> Result<bool, TagCommitsError> {
+    if !args.includes_commit(commit) {
+        trace!(commit = %commit.id(), "skipping commit excluded by merge filters");
+        return Ok(false);
+    }
+
+    let Some(message) = commit.message() else {
+        return Ok(false);
+    };
+
+    let (tickets, pr_title) = trackers.find_commit_tickets(message);
+
+    let matches = !tickets.is_empty() || args.all;
+    if !matches {
+        trace!(commit = %commit.id(), "skipping commit without tickets");
+    }
+    if matches {
+        // Resolve the reverted commit now, while the repository is at hand
+        let reverts = reverted_sha(message)
+            .and_then(|sha| repo.revparse_single(sha).ok())
+            .map(|object| object.id());
+
+        commit_to_tag.insert(
+            commit.id(),
+            CommitTagInfo {
+                id: commit.id(),
+                time: commit.time().seconds(),
+                author_time: commit.author().when().seconds(),
+                depth,
+                tag_name: tag_name.to_owned(),
+                tickets,
+                author: commit.author().name().unwrap_or_default().to_owned(),
+                co_authors: trailer_names(message, "Co-authored-by"),
+                reviewers: trailer_names(message, "Reviewed-by"),
+                release_note: trailer_values(message, args.release_note_trailer())
+                    .into_iter()
+                    .next()
+                    .or(pr_title.clone())
+                    .unwrap_or_else(|| commit.summary().unwrap_or_default().to_owned()),
+                message: message.to_owned(),
+                environments: Vec::new(),
+                git_note: args
+                    .git_notes
+                    .then(|| git_note(repo, args.notes_ref(), commit.id()))
+                    .flatten(),
+                pr_number: pr_number(message),
+                pr_title,
+                cherry_picked_in: Vec::new(),
+                is_cherry_pick: false,
+                reverts,
+                reverted_by: None,
+            },
+        );
+    }
+    Ok(matches)
+}
+
+/// A lightweight summary of a commit in a release, which doesn't keep the commit itself loaded.
+pub struct CommitTagInfo {
+    pub id: Oid,
+    pub time: i64,
+    /// When the commit was authored, which unlike `time` is kept when it is rebased or
+    /// cherry-picked
+    pub author_time: i64,
+    pub depth: usize,
+    pub tag_name: String,
+    pub tickets: Vec<Ticket>,
+    pub author: String,
+    /// The people credited with `Co-authored-by` trailers
+    pub co_authors: Vec<String>,
+    /// The people credited with `Reviewed-by` trailers
+    pub reviewers: Vec<String>,
+    /// The human-facing description of the commit, from its release note trailer, or else the
+    /// title of its pull request or its summary
+    pub release_note: String,
+    /// The full commit message, which `--exclude` matches as well as the release note
+    pub message: String,
+    /// The environments the commit has been deployed to, from `--environment`
+    pub environments: Vec<String>,
+    /// The git note attached to the commit, with `--git-notes`
+    pub git_note: Option<String>,
+    /// The number of the pull or merge request the commit was merged from, if its message says
+    pub pr_number: Option<u64>,
+    /// The title of the pull request of a squash-merged commit, if it was fetched with
+    /// `--fetch-prs`
+    pub pr_title: Option<String>,
+    /// Other releases containing a cherry-pick of the same change
+    pub cherry_picked_in: Vec<String>,
+    /// Whether this commit is a cherry-pick of a change first released in another release
+    pub is_cherry_pick: bool,
+    /// The commit reverted by this commit
+    pub reverts: Option<Oid>,
+    /// The commit reverting this commit
+    pub reverted_by: Option<Oid>,
+}
+
+impl CommitTagInfo {
+    pub fn tickets_match(&self, filter: &str) -> bool {
+        self.tickets.iter().any(|ticket| ticket.id.contains(filter))
+    }
+
+    /// Whether `--exclude` leaves out the commit, because its tickets, message, release note, or
+    /// author contain the text given.
+    pub fn is_excluded_by(&self, exclude: &str) -> bool {
+        self.tickets_match(exclude)
+            || self.message.contains(exclude)
+            || self.release_note.contains(exclude)
+            || self.author.contains(exclude)
+    }
+}
+
+impl TagCommits {
+    /// Key to order releases by time, falling back to the tag name for tags created at the
+    /// same time.
+    pub fn release_order<'b>(&self, tag_name: &'b str) -> (i64, &'b str) {
+        let time = *self.tag_times.get(tag_name).unwrap_or(&i64::MAX);
+        (time, tag_name)
+    }
+
+    /// The seconds from a commit being authored to its release being tagged.
+    pub fn lead_time(&self, info: &CommitTagInfo) -> Option<i64> {
+        let tag_time = self.tag_times.get(&info.tag_name)?;
+        Some((tag_time - info.author_time).max(0))
+    }
+}
+
+/// Find the earliest release (by tag time) containing each ticket.
+pub fn get_first_releases(tag_commits: &TagCommits) -> HashMap<&str, &str> {
+    let mut first_releases: HashMap<&str, &str> = HashMap::new();
+    for info in tag_commits.commit_to_tag.values() {
+        for ticket in &info.tickets {
+            let is_earlier = match first_releases.get(ticket.id.as_str()) {
+                Some(existing) => {
+                    tag_commits.release_order(&info.tag_name) < tag_commits.release_order(existing)
+                }
+                None => true,
+            };
+            if is_earlier {
+                first_releases.insert(&ticket.id, &info.tag_name);
+            }
+        }
+    }
+    first_releases
+}
+
+pub fn format_tickets(
+    info: &CommitTagInfo,
+    trackers: &Trackers,
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> String {
+    if info.tickets.is_empty() {
+        return "(no tickets)".dimmed().to_string();
+    }
+
+    info.tickets
+        .iter()
+        .map(|ticket| {
+            let formatted = trackers.format(ticket);
+            match first_releases.and_then(|releases| releases.get(ticket.id.as_str())) {
+                Some(release) => format!(
+                    "{} {}",
+                    formatted,
+                    format!("(first released in {})", release).dimmed()
+                ),
+                None => formatted,
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Annotations describing how a commit relates to other commits, and with `--credits`, who
+/// wrote and reviewed it. Commits deployed to more environments than their release, e.g. by
+/// deploying a branch between releases, are annotated with them.
+pub fn commit_notes(args: &Args, tag_commits: &TagCommits, info: &CommitTagInfo) -> Vec<String> {
+    let mut notes = Vec::new();
+    let release_environments = tag_commits
+        .tag_environments
+        .get(&info.tag_name)
+        .map(Vec::len)
+        .unwrap_or_default();
+    if info.environments.len() > release_environments {
+        notes.push(format!("(in {})", info.environments.join(", ")));
+    }
+    if args.credits {
+        let mut credits = format!(
+            "by {}",
+            std::iter::once(&info.author)
+                .chain(&info.co_authors)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if !info.reviewers.is_empty() {
+            credits = format!("{}, reviewed by {}", credits, info.reviewers.join(", "));
+        }
+        notes.push(format!("({})", credits));
+    }
+    if let Some(title) = &info.pr_title {
+        notes.push(format!("({})", title));
+    }
+    if let Some(note) = &info.git_note {
+        notes.push(format!("(note: {})", one_line(note)));
+    }
+    if !info.cherry_picked_in.is_empty() {
+        notes.push(format!(
+            "(cherry-picked, also in {})",
+            info.cherry_picked_in.join(", ")
+        ));
+    }
+    if let Some(id) = &info.reverts {
+        notes.push(format!("(reverts {})", short_sha(id)));
+    }
+    if let Some(id) = &info.reverted_by {
+        notes.push(format!("(reverted by {})", short_sha(id)));
+    }
+    notes
+}
+
+/// The authors and co-authors of the commits in a release, with how many commits each
+/// contributed to, most first.
+pub fn contributors<'a>(commits: &[&'a CommitTagInfo]) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for commit in commits {
+        let mut people = std::iter::once(&commit.author)
+            .chain(&commit.co_authors)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        people.sort_unstable();
+        people.dedup();
+        for name in people {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    let mut contributors = counts.into_iter().collect::<Vec<_>>();
+    contributors.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then(a_name.cmp(b_name))
+    });
+    contributors
+}
+
+/// Join the lines of a multi-line note, so it can be shown next to a commit.
+pub fn one_line(note: &str) -> String {
+    note.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn short_sha(id: &Oid) -> String {
+    id.to_string()[..7].to_owned()
+}
+
+/// Print every release, returning whether any were printed.
+pub fn print_releases(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> bool {
+    if args.summary {
+        return print_release_summaries(args, trackers, tag_commits);
+    }
+    let tag_to_commits = commits_by_tag(tag_commits);
+
+    let first_releases = args.first_release.then(|| get_first_releases(tag_commits));
+
+    let mut printed = false;
+    for tag_name in &tag_commits.tag_names {
+        let ids = tag_to_commits
+            .get(tag_name.as_str())
+            .cloned()
+            .unwrap_or_default();
+        printed |= print_release(
+            args,
+            trackers,
+            tag_commits,
+            tag_name,
+            ids,
+            first_releases.as_ref(),
+        );
+    }
+    printed
+}
+
+/// Print a line for each release with `--summary`: its tag, the date it was tagged, and how
+/// many commits and different tickets it has, aligned across releases. Returns whether any
+/// releases matched `--filter`.
+fn print_release_summaries(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> bool {
+    let tag_to_commits = commits_by_tag(tag_commits);
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    };
+
+    let mut printed = false;
+    let mut table = Table::new();
+    for tag_name in &tag_commits.tag_names {
+        let ids = tag_to_commits
+            .get(tag_name.as_str())
+            .cloned()
+            .unwrap_or_default();
+        let Some(commits) = release_commits(args, trackers, tag_commits, tag_name, ids) else {
+            continue;
+        };
+        printed = true;
+        if args.quiet && commits.is_empty() {
+            continue;
+        }
+        let tickets = commits
+            .iter()
+            .flat_map(|commit| &commit.tickets)
+            .map(|ticket| ticket.id.as_str())
+            .collect::<HashSet<_>>();
+        let date = tag_commits
+            .tag_times
+            .get(tag_name)
+            .map(|time| format_date(*time))
+            .unwrap_or_default();
+        table.push(vec![
+            tag_name.green().bold().to_string(),
+            date,
+            plural(commits.len(), "commit"),
+            plural(tickets.len(), "ticket"),
+        ]);
+    }
+    table.print(0);
+    printed
+}
+
+/// Whether a commit has a ticket in one of the `--ticket-status` statuses, and no tickets in any
+/// of the `--exclude-ticket-status` statuses. Tickets which couldn't be looked up aren't in any
+/// status.
+fn matches_ticket_status(args: &Args, trackers: &Trackers, commit: &CommitTagInfo) -> bool {
+    let details = commit
+        .tickets
+        .iter()
+        .map(|ticket| trackers.details(ticket))
+        .collect::<Vec<_>>();
+    let has_status = |details: &Option<TicketDetails>, statuses: &[String]| {
+        details
+            .as_ref()
+            .is_some_and(|details| details.has_status(statuses))
+    };
+
+    (args.ticket_statuses.is_empty()
+        || details
+            .iter()
+            .any(|details| has_status(details, &args.ticket_statuses)))
+        && (args.excluded_ticket_statuses.is_empty()
+            || !details
+                .iter()
+                .any(|details| has_status(details, &args.excluded_ticket_statuses)))
+}
+
+/// The commits in a single release matching `--filter` and the ticket status options, and not
+/// `--exclude`, closest to the tag first. Commits which have since moved to another release are
+/// skipped. Returns `None` if the release doesn't match `--filter` at all, or is excluded.
+pub fn release_commits<'a, 'b>(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &'a TagCommits,
+    tag_name: &str,
+    ids: impl IntoIterator<Item = &'b Oid>,
+) -> Option<Vec<&'a CommitTagInfo>> {
+    let mut commits = ids
+        .into_iter()
+        .filter(|id| !(args.exclude_reverts && tag_commits.is_reverted_pair(id)))
+        .filter_map(|id| tag_commits.commit_to_tag.get(id))
+        .filter(|info| info.tag_name == tag_name)
+        .collect::<Vec<_>>();
+    // Show the commits closest to the tag first
+    commits.sort_by_key(|info| (info.depth, -info.time, info.id));
+
+    if args
+        .excludes
+        .iter()
+        .any(|exclude| tag_name.contains(exclude.as_str()))
+    {
+        return None;
+    }
+    let tag_matches_filter = args.filters_match(|filter| tag_name.contains(filter));
+
+    let mut filtered_commits = commits
+        .into_iter()
+        .filter(|commit| {
+            tag_matches_filter
+                || args.filters_match(|filter| {
+                    tag_name.contains(filter) || commit.tickets_match(filter)
+                })
+        })
+        .filter(|commit| {
+            !args
+                .excludes
+                .iter()
+                .any(|exclude| commit.is_excluded_by(exclude))
+        })
+        .collect::<Vec<_>>();
+
+    if !tag_matches_filter && filtered_commits.is_empty() {
+        return None;
+    }
+    if !args.ticket_statuses.is_empty() || !args.excluded_ticket_statuses.is_empty() {
+        trackers.prefetch_details(filtered_commits.iter().flat_map(|commit| &commit.tickets));
+        filtered_commits.retain(|commit| matches_ticket_status(args, trackers, commit));
+    }
+    Some(filtered_commits)
+}
+
+/// The tickets referenced by the commits in a release which match `--filter` and the ticket
+/// status options, in the order they are shown. Fails if the tag isn't one of the releases.
+pub fn release_tickets<'a>(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &'a TagCommits,
+    tag_name: &str,
+) -> Result<Vec<&'a Ticket>, TagCommitsError> {
+    if !tag_commits.tag_names.iter().any(|name| name == tag_name) {
+        return Err(TagCommitsError::NotARelease(tag_name.to_owned()));
+    }
+    let ids = commits_by_tag(tag_commits)
+        .remove(tag_name)
+        .unwrap_or_default();
+    let commits = release_commits(args, trackers, tag_commits, tag_name, ids).unwrap_or_default();
+
+    let mut tickets = Vec::new();
+    for ticket in commits.into_iter().flat_map(|commit| &commit.tickets) {
+        if !tickets.contains(&ticket) {
+            tickets.push(ticket);
+        }
+    }
+    Ok(tickets)
+}
+
+/// The commit IDs in each release.
+pub fn commits_by_tag(tag_commits: &TagCommits) -> HashMap<&str, Vec<&Oid>> {
+    tag_commits.commit_to_tag.iter().fold(
+        HashMap::new(),
+        |mut map: HashMap<&str, Vec<&Oid>>, (id, info)| {
+            map.entry(&info.tag_name).or_default().push(id);
+            map
+        },
+    )
+}
+
+/// The `--columns` chosen for a commit.
+fn commit_columns(
+    args: &Args,
+    trackers: &Trackers,
+    commit: &CommitTagInfo,
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> Vec<String> {
+    args.columns
+        .iter()
+        .map(|column| match column {
+            CommitColumn::Tag => commit.tag_name.clone(),
+            CommitColumn::Sha => short_sha(&commit.id).yellow().to_string(),
+            CommitColumn::Date => format_date(commit.time),
+            CommitColumn::Author => commit.author.clone(),
+            CommitColumn::Tickets => format_tickets(commit, trackers, first_releases),
+            CommitColumn::Subject => commit.release_note.clone(),
+            CommitColumn::Url => commit
+                .tickets
+                .iter()
+                .filter_map(|ticket| trackers.link(ticket))
+                .collect::<Vec<_>>()
+                .join(", "),
+            CommitColumn::Pr => commit
+                .pr_number
+                .and_then(|number| trackers.pr_url(number))
+                .unwrap_or_default(),
+            CommitColumn::Depth => commit.depth.to_string(),
+        })
+        .collect()
+}
+
+/// The columns shown for a commit: its tickets with any notes, and the links to them and its
+/// pull request, or else the `--columns` chosen.
+fn commit_row(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    commit: &CommitTagInfo,
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> Vec<String> {
+    if !args.columns.is_empty() {
+        return commit_columns(args, trackers, commit, first_releases);
+    }
+    let mut formatted_tickets = format_tickets(commit, trackers, first_releases);
+    if args.notes {
+        formatted_tickets = format!("{} {}", formatted_tickets, commit.release_note);
+    }
+    for note in commit_notes(args, tag_commits, commit) {
+        formatted_tickets = format!("{} {}", formatted_tickets, note.dimmed());
+    }
+    let mut columns = vec![formatted_tickets];
+    if args.show_depth {
+        columns.push(format!("depth {}", commit.depth).dimmed().to_string());
+    }
+    if trackers.has_urls() {
+        columns.push(
+            commit
+                .tickets
+                .iter()
+                .map(|ticket| trackers.url(ticket))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+    if let Some(url) = commit
+        .pr_number
+        .filter(|_| args.show_pr)
+        .and_then(|number| trackers.pr_url(number))
+    {
+        columns.push(url);
+    }
+    columns
+}
+
+/// Print the commits in a single release. Returns whether the release was printed, which it
+/// isn't if it doesn't match `--filter`.
+pub fn print_release<'a>(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+    tag_name: &str,
+    ids: impl IntoIterator<Item = &'a Oid>,
+    first_releases: Option<&HashMap<&str, &str>>,
+) -> bool {
+    let Some(filtered_commits) = release_commits(args, trackers, tag_commits, tag_name, ids) else {
+        return false;
+    };
+    if args.quiet && filtered_commits.is_empty() {
+        return true;
+    }
+    trackers.prefetch_details(filtered_commits.iter().flat_map(|commit| &commit.tickets));
+
+    match filtered_commits.is_empty() {
+        true => {
+            println!("{}", format!("{} (no entries)", tag_name).dimmed())
+        }
+        false => {
+            println!("{}", tag_name.green().bold())
+        }
+    }
+    match tag_commits.tag_environments.get(tag_name) {
+        Some(environments) if environments.is_empty() => {
+            println!("  {}", "Not deployed".dimmed())
+        }
+        Some(environments) => println!(
+            "  {}",
+            format!("Deployed to {}", environments.join(", ")).dimmed()
+        ),
+        None => {}
+    }
+    if let Some(note) = tag_commits.tag_notes.get(tag_name) {
+        println!("  {}", format!("Note: {}", one_line(note)).dimmed());
+    }
+    if let Some(url) = trackers
+        .compare_url(tag_commits, tag_name)
+        .filter(|_| args.compare_links)
+    {
+        println!("  {}", format!("Compare: {}", url).dimmed());
+    }
+    match trackers.release_assets(tag_name) {
+        Some(assets) if assets.is_empty() => println!("  {}", "No assets".dimmed()),
+        Some(assets) => {
+            println!("  {}", "Assets:".dimmed());
+            for asset in assets {
+                let size = asset
+                    .format_size()
+                    .map(|size| format!(" ({})", size))
+                    .unwrap_or_default();
+                println!(
+                    "    {}",
+                    format!("{}{} {}", asset.name, size, asset.url).dimmed()
+                );
+            }
+        }
+        None => {}
+    }
+    if let Some(stat) = tag_commits.tag_diffstats.get(tag_name) {
+        println!("  {}", stat.summary().dimmed());
+        if args.dirstat {
+            for (percentage, dir) in stat.top_directories() {
+                println!(
+                    "  {}",
+                    format!("{: >6.1}% {}", percentage, dir.path).dimmed()
+                );
+            }
+        }
+    }
+
+    let mut rows = match args.graph {
+        true => graph_rows(&filtered_commits, &tag_commits.commit_parents),
+        false => filtered_commits
+            .iter()
+            .map(|commit| GraphRow {
+                graph: String::new(),
+                commit: Some(commit),
+            })
+            .collect(),
+    };
+    let cutoff = args.max_commits.and_then(|max_commits| {
+        let mut commit_rows = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.commit.is_some());
+        commit_rows.nth(max_commits).map(|(index, _)| index)
+    });
+    if let Some(cutoff) = cutoff {
+        rows.truncate(cutoff);
+        // Don't leave lines of the graph leading to commits which aren't shown
+        while rows.last().is_some_and(|row| row.commit.is_none()) {
+            rows.pop();
+        }
+    }
+    let shown = rows.iter().filter(|row| row.commit.is_some()).count();
+
+    let mut table = Table::new();
+    for row in rows {
+        let Some(commit) = row.commit else {
+            table.push(vec![row.graph]);
+            continue;
+        };
+        let mut columns = commit_row(args, trackers, tag_commits, commit, first_releases);
+        if !row.graph.is_empty() {
+            columns[0] = format!("{} {}", row.graph, columns[0]);
+        }
+        table.push(columns);
+    }
+    table.print(2);
+    if shown < filtered_commits.len() {
+        println!(
+            "  {}",
+            format!("… and {} more", filtered_commits.len() - shown).dimmed()
+        );
+    }
+    if args.contributors && !filtered_commits.is_empty() {
+        let contributors = contributors(&filtered_commits)
+            .into_iter()
+            .map(|(name, commits)| format!("{} ({})", name, commits))
+            .collect::<Vec<_>>();
+        println!(
+            "  {}",
+            format!("Contributors: {}", contributors.join(", ")).dimmed()
+        );
+    }
+    true
+}
@@ -0,0 +1,53 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use git2::Oid;
+
+use crate::releases::TagCommits;
+
+/// Find the SHA of the commit reverted by a commit, from the message generated by `git revert`.
+pub fn reverted_sha(message: &str) -> Option<&str> {
+    static REVERT_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = REVERT_REGEX.get_or_init(|| {
+        Regex::new(r"This reverts commit ([0-9a-f]{7,40})").expect("revert regex should be valid")
+    });
+
+    if !message.starts_with("Revert \"") {
+        return None;
+    }
+    regex
+        .captures(message)
+        .and_then(|captures| captures.get(1))
+        .map(|sha| sha.as_str())
+}
+
+/// Mark the commits reverted by the given commits.
+pub fn link_reverts(tag_commits: &mut TagCommits, ids: &[Oid]) {
+    for id in ids {
+        let Some(target) = tag_commits
+            .commit_to_tag
+            .get(id)
+            .and_then(|info| info.reverts)
+        else {
+            continue;
+        };
+        if let Some(target) = tag_commits.commit_to_tag.get_mut(&target) {
+            target.reverted_by = Some(*id);
+        }
+    }
+}
+
+impl TagCommits {
+    /// Whether a commit is one half of a revert pair within a single release, meaning the net
+    /// change to that release is nothing.
+    pub fn is_reverted_pair(&self, id: &Oid) -> bool {
+        let Some(info) = self.commit_to_tag.get(id) else {
+            return false;
+        };
+        info.reverts
+            .iter()
+            .chain(info.reverted_by.iter())
+            .filter_map(|other| self.commit_to_tag.get(other))
+            .any(|other| other.tag_name == info.tag_name)
+    }
+}
@@ -0,0 +1,205 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{env, fmt, fs, io};
+
+use crate::{
+    auth::{token, AuthService},
+    http::HttpClient,
+};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/matt-winfield/rels/releases/latest";
+
+/// The largest binary a release can have, to stop a bad download filling up memory.
+const MAX_BINARY_SIZE: u64 = 200 * 1024 * 1024;
+
+/// The name of the release asset listing the SHA-256 checksum of each binary, in the format
+/// written by `sha256sum`.
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+pub enum UpdateError {
+    Http(ureq::Error),
+    Io(io::Error),
+    /// The latest release has no binary for this platform, named by the binary it looked for
+    NoBinary(String),
+    NoChecksum(String),
+    ChecksumMismatch(String),
+}
+
+impl From<ureq::Error> for UpdateError {
+    fn from(err: ureq::Error) -> Self {
+        UpdateError::Http(err)
+    }
+}
+
+impl From<io::Error> for UpdateError {
+    fn from(err: io::Error) -> Self {
+        UpdateError::Io(err)
+    }
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Http(err) => write!(f, "{}", err),
+            UpdateError::Io(err) => write!(f, "{}", err),
+            UpdateError::NoBinary(name) => {
+                write!(f, "the latest release has no {} binary", name)
+            }
+            UpdateError::NoChecksum(name) => {
+                write!(f, "the latest release has no checksum for {}", name)
+            }
+            UpdateError::ChecksumMismatch(name) => {
+                write!(f, "the checksum of the downloaded {} didn't match", name)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl GithubRelease {
+    fn download(&self, http: &HttpClient, name: &str) -> Option<Result<Vec<u8>, UpdateError>> {
+        let asset = self.assets.iter().find(|asset| asset.name == name)?;
+        Some(download(http, &asset.browser_download_url))
+    }
+}
+
+pub enum UpdateStatus {
+    /// Already the latest version
+    UpToDate(String),
+    /// A newer version was found, but not installed since only checking was asked for
+    Available(String),
+    Updated(String),
+}
+
+/// The name of the release binary for this platform, e.g. `rels-x86_64-linux` or
+/// `rels-aarch64-macos`.
+fn binary_name() -> String {
+    format!(
+        "rels-{}-{}{}",
+        env::consts::ARCH,
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+/// The numeric parts of a version, e.g. `[1, 2, 3]` for `v1.2.3`, ignoring any pre-release
+/// suffix.
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn download(http: &HttpClient, url: &str) -> Result<Vec<u8>, UpdateError> {
+    Ok(http
+        .send(|agent| agent.get(url).call())?
+        .body_mut()
+        .with_config()
+        .limit(MAX_BINARY_SIZE)
+        .read_to_vec()?)
+}
+
+/// Find the checksum of a binary in a `sha256sum` checksums file, where each line is a checksum
+/// followed by a file name, which is prefixed with `*` for binary mode.
+fn find_checksum<'a>(checksums: &'a str, name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (checksum, file_name) = line.split_once(char::is_whitespace)?;
+        (file_name.trim_start().trim_start_matches('*') == name).then_some(checksum)
+    })
+}
+
+/// Check the latest GitHub release of rels, and replace this binary with it if it is newer,
+/// after checking it against the release's checksums.
+pub fn self_update(http: &HttpClient, check_only: bool) -> Result<UpdateStatus, UpdateError> {
+    // Unauthenticated requests share a low rate limit, e.g. on CI runners
+    let token = token("GITHUB_TOKEN", AuthService::Github);
+    let release: GithubRelease = http
+        .send(|agent| {
+            let mut request = agent
+                .get(LATEST_RELEASE_URL)
+                .header("Accept", "application/vnd.github+json");
+            if let Some(token) = &token {
+                request = request.header("Authorization", &format!("Bearer {}", token));
+            }
+            request.call()
+        })?
+        .body_mut()
+        .read_json()?;
+
+    let latest = release.tag_name.trim_start_matches('v').to_owned();
+    if parse_version(&latest) <= parse_version(env!("CARGO_PKG_VERSION")) {
+        return Ok(UpdateStatus::UpToDate(latest));
+    }
+    if check_only {
+        return Ok(UpdateStatus::Available(latest));
+    }
+
+    let name = binary_name();
+    let binary = release
+        .download(http, &name)
+        .ok_or_else(|| UpdateError::NoBinary(name.clone()))??;
+    let checksums = release
+        .download(http, CHECKSUMS_ASSET)
+        .ok_or_else(|| UpdateError::NoChecksum(name.clone()))??;
+    let checksums = String::from_utf8_lossy(&checksums);
+    let expected =
+        find_checksum(&checksums, &name).ok_or_else(|| UpdateError::NoChecksum(name.clone()))?;
+    let actual = Sha256::digest(&binary)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(UpdateError::ChecksumMismatch(name));
+    }
+
+    let download_path = env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    fs::write(&download_path, binary)?;
+    let replaced = self_replace::self_replace(&download_path);
+    let _ = fs::remove_file(&download_path);
+    replaced?;
+    Ok(UpdateStatus::Updated(latest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKSUMS: &str = "\
+1111  rels-x86_64-linux
+2222 *rels-aarch64-macos
+3333  rels-x86_64-windows.exe
+";
+
+    #[test]
+    fn checksums_are_found_by_file_name_in_text_or_binary_mode() {
+        assert_eq!(find_checksum(CHECKSUMS, "rels-x86_64-linux"), Some("1111"));
+        assert_eq!(find_checksum(CHECKSUMS, "rels-aarch64-macos"), Some("2222"));
+    }
+
+    #[test]
+    fn checksums_are_not_found_for_other_files() {
+        assert_eq!(find_checksum(CHECKSUMS, "rels-x86_64"), None);
+        assert_eq!(find_checksum(CHECKSUMS, "rels-aarch64-linux"), None);
+        assert_eq!(find_checksum("", "rels-x86_64-linux"), None);
+    }
+
+    #[test]
+    fn versions_are_compared_by_their_numeric_parts() {
+        assert_eq!(parse_version("v1.2.3"), [1, 2, 3]);
+        assert_eq!(parse_version("1.10.0-beta.1"), [1, 10, 0]);
+        assert!(parse_version("v1.10.0") > parse_version("v1.9.2"));
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::DateTime;
+use serde::Serialize;
+use serde_json::json;
+use std::{io, time::Duration};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::debug;
+
+use git2::Repository;
+
+use crate::{
+    cli::{Args, ServeArgs},
+    json::{releases_json, ticket_releases_json, ReleaseJson, TicketReleasesJson},
+    releases::{get_tag_commits, TagCommitsError},
+    tickets::Trackers,
+};
+
+const STYLE: &str = "body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5 } \
+    h2 { margin-bottom: 0 } .dim { color: #777 } code { font-size: 0.9em }";
+
+/// A response to a request, before it is rendered as JSON or HTML.
+enum Page<'a> {
+    Releases(Vec<ReleaseJson<'a>>),
+    Release(ReleaseJson<'a>),
+    Ticket(TicketReleasesJson<'a>),
+    NotFound(String),
+    Error(String),
+}
+
+/// Escape the characters which are special in HTML text and attribute values.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Decode the percent-encoded characters of a URL path, e.g. in a tag like `app%2Fv1.5.0`.
+fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn format_time(time: Option<i64>) -> String {
+    time.and_then(|time| DateTime::from_timestamp(time, 0))
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// A release as HTML, linking its tickets to their pages on the server.
+fn release_html(release: &ReleaseJson) -> String {
+    let mut html = format!(
+        "<h2><a href=\"/releases/{}\">{}</a></h2>\n<p class=\"dim\">{}</p>\n",
+        escape_html(release.tag),
+        escape_html(release.tag),
+        format_time(release.time)
+    );
+    if release.commits.is_empty() {
+        html.push_str("<p class=\"dim\">No entries</p>\n");
+        return html;
+    }
+    html.push_str("<ul>\n");
+    for commit in &release.commits {
+        let tickets = commit
+            .tickets
+            .iter()
+            .map(|ticket| {
+                format!(
+                    "<a href=\"/tickets/{}\">{}</a>",
+                    escape_html(ticket.id),
+                    escape_html(ticket.id)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        html.push_str(&format!(
+            "<li><code>{}</code> {} {} <span class=\"dim\">{}</span></li>\n",
+            &commit.sha[..7],
+            tickets,
+            escape_html(commit.release_note),
+            escape_html(commit.author)
+        ));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Render a page as HTML, returning its title and body.
+fn page_html(page: &Page) -> (String, String) {
+    match page {
+        Page::Releases(releases) => (
+            "Releases".to_owned(),
+            releases.iter().map(release_html).collect(),
+        ),
+        Page::Release(release) => (release.tag.to_owned(), release_html(release)),
+        Page::Ticket(ticket) => {
+            let heading = match &ticket.url {
+                Some(url) => format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_html(url),
+                    escape_html(ticket.ticket)
+                ),
+                None => escape_html(ticket.ticket),
+            };
+            let mut html = format!("<h1>{}</h1>\n", heading);
+            if let Some(title) = &ticket.title {
+                html.push_str(&format!("<p>{}</p>\n", escape_html(title)));
+            }
+            for release in &ticket.releases {
+                html.push_str(&release_html(release));
+            }
+            (ticket.ticket.to_owned(), html)
+        }
+        Page::NotFound(message) | Page::Error(message) => (
+            "Error".to_owned(),
+            format!("<p>{}</p>", escape_html(message)),
+        ),
+    }
+}
+
+/// Render a page as a complete HTML document.
+fn html_document(page: &Page) -> String {
+    let (title, body) = page_html(page);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&title),
+        STYLE,
+        body
+    )
+}
+
+/// The releases as the HTML page the server shows for `/releases`, for `--output` to write.
+pub fn releases_html(releases: Vec<ReleaseJson>) -> String {
+    html_document(&Page::Releases(releases))
+}
+
+/// Render a page as JSON: the same as `--format json` prints for releases, or an error with a
+/// message.
+fn page_json(page: &Page) -> String {
+    fn to_json(value: &impl Serialize) -> String {
+        serde_json::to_string_pretty(value).expect("pages should serialize to JSON")
+    }
+    match page {
+        Page::Releases(releases) => to_json(releases),
+        Page::Release(release) => to_json(release),
+        Page::Ticket(ticket) => to_json(ticket),
+        Page::NotFound(message) | Page::Error(message) => {
+            to_json(&json!({ "error": { "message": message } }))
+        }
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("headers should be valid")
+}
+
+/// Respond to a request, reading the releases again so tags created since the server started
+/// are included, with HTML if the client asks for it (as browsers do) and JSON otherwise.
+fn respond(
+    repo: &Repository,
+    max_age: Duration,
+    trackers: &Trackers,
+    args: &Args,
+    request: Request,
+) -> io::Result<()> {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return request.respond(Response::empty(405).with_header(header("Allow", "GET, HEAD")));
+    }
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let path = decode(path.trim_end_matches('/'));
+    let html = match query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="))
+    {
+        Some(format) => format == "html",
+        None => request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Accept"))
+            .is_some_and(|header| header.value.as_str().contains("text/html")),
+    };
+
+    let tag_commits = get_tag_commits(repo, max_age, trackers, args);
+    let page = match &tag_commits {
+        Err(err) => Page::Error(err.message()),
+        Ok(tag_commits) => {
+            if path.is_empty() || path == "/releases" {
+                Page::Releases(releases_json(args, trackers, tag_commits))
+            } else if let Some(tag) = path.strip_prefix("/releases/") {
+                releases_json(args, trackers, tag_commits)
+                    .into_iter()
+                    .find(|release| release.tag == tag)
+                    .map_or_else(
+                        || Page::NotFound(format!("{} is not a release", tag)),
+                        Page::Release,
+                    )
+            } else if let Some(id) = path.strip_prefix("/tickets/") {
+                ticket_releases_json(args, trackers, tag_commits, id).map_or_else(
+                    || Page::NotFound(format!("{} isn't in any release", id)),
+                    Page::Ticket,
+                )
+            } else {
+                Page::NotFound(format!(
+                    "{} isn't a page, try /releases, /releases/<tag>, or /tickets/<id>",
+                    path
+                ))
+            }
+        }
+    };
+
+    let status = match page {
+        Page::NotFound(_) => 404,
+        Page::Error(_) => 500,
+        _ => 200,
+    };
+    let response = if html {
+        Response::from_string(html_document(&page))
+            .with_header(header("Content-Type", "text/html; charset=utf-8"))
+    } else {
+        Response::from_string(page_json(&page))
+            .with_header(header("Content-Type", "application/json"))
+    };
+    request.respond(response.with_status_code(status))
+}
+
+/// Serve the releases over HTTP until rels is stopped, for dashboards and chat bots to query
+/// without running rels themselves. Requests are handled one at a time.
+pub fn serve(
+    repo: &Repository,
+    max_age: Duration,
+    trackers: &Trackers,
+    args: &Args,
+    serve_args: &ServeArgs,
+) -> Result<(), TagCommitsError> {
+    let server = Server::http(&serve_args.address)
+        .map_err(|err| TagCommitsError::Io("start server", io::Error::other(err)))?;
+    eprintln!("Serving releases on http://{}", serve_args.address);
+
+    for request in server.incoming_requests() {
+        debug!("{} {}", request.method(), request.url());
+        if let Err(err) = respond(repo, max_age, trackers, args, request) {
+            debug!("Failed to respond: {}", err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use colored::Colorize;
+use std::collections::HashMap;
+
+use crate::{releases::TagCommits, table::Table};
+
+/// A ticket whose commits are contained in more than one release.
+pub struct SpanningTicket<'a> {
+    pub ticket: &'a str,
+    /// The releases containing the ticket, earliest first.
+    pub releases: Vec<&'a str>,
+}
+
+/// Find tickets whose commits are split across more than one release, which is often a sign
+/// of incomplete cherry-picks or long-running work.
+pub fn get_spanning_tickets<'a>(tag_commits: &'a TagCommits) -> Vec<SpanningTicket<'a>> {
+    let mut ticket_releases: HashMap<&str, Vec<&str>> = HashMap::new();
+    // Cherry-picks are copies of a change already released elsewhere, so don't count them
+    for info in tag_commits
+        .commit_to_tag
+        .values()
+        .filter(|info| !info.is_cherry_pick)
+    {
+        for ticket in &info.tickets {
+            let releases = ticket_releases.entry(&ticket.id).or_default();
+            if !releases.contains(&info.tag_name.as_str()) {
+                releases.push(&info.tag_name);
+            }
+        }
+    }
+
+    let mut spanning = ticket_releases
+        .into_iter()
+        .filter(|(_, releases)| releases.len() > 1)
+        .map(|(ticket, mut releases)| {
+            releases.sort_by_key(|tag_name| tag_commits.release_order(tag_name));
+            SpanningTicket { ticket, releases }
+        })
+        .collect::<Vec<_>>();
+    spanning.sort_by_key(|spanning_ticket| spanning_ticket.ticket);
+    spanning
+}
+
+pub fn print_spanning_tickets(spanning: &[SpanningTicket], quiet: bool) {
+    if spanning.is_empty() {
+        if !quiet {
+            println!("{}", "No tickets span multiple releases".dimmed());
+        }
+        return;
+    }
+
+    let mut table = Table::new();
+    for spanning_ticket in spanning {
+        table.push(vec![
+            spanning_ticket.ticket.bold().italic().to_string(),
+            format!(
+                "{} releases: {}",
+                spanning_ticket.releases.len().to_string().yellow(),
+                spanning_ticket.releases.join(", ")
+            ),
+        ]);
+    }
+    table.print(2);
+}
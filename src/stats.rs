@@ -0,0 +1,173 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    cli::OutputFormat,
+    metrics::{format_date, format_duration, percentile, releases_within},
+    releases::TagCommits,
+};
+
+/// The size of a release, and how long after the previous release it was made.
+#[derive(Serialize)]
+pub struct ReleaseStats {
+    pub tag: String,
+    /// When the release was tagged, as a Unix timestamp
+    pub time: i64,
+    pub commits: usize,
+    pub tickets: usize,
+    /// The authors and co-authors of the commits in the release
+    pub authors: usize,
+    /// The seconds since the previous release, unless this is the first within `--age`
+    pub since_previous: Option<i64>,
+    /// The shortest time from a commit being authored to the release, in seconds
+    pub lead_time_min: Option<i64>,
+    pub lead_time_median: Option<i64>,
+    pub lead_time_p90: Option<i64>,
+}
+
+/// The stats of each release within `--age`, oldest first, with totals across them all.
+#[derive(Serialize)]
+pub struct Stats {
+    pub releases: Vec<ReleaseStats>,
+    pub commits: usize,
+    /// The tickets across all releases, counting tickets in several releases once
+    pub tickets: usize,
+    pub authors: usize,
+    /// The mean number of seconds between releases
+    pub mean_interval: Option<i64>,
+}
+
+/// Count the commits, tickets and authors of each release tagged within `--age` of now.
+pub fn get_stats(tag_commits: &TagCommits, max_age: Duration) -> Stats {
+    let mut all_tickets = HashSet::new();
+    let mut all_authors = HashSet::new();
+    let mut releases = Vec::new();
+    let mut previous_time = None;
+    for tag_name in releases_within(tag_commits, max_age) {
+        let mut commits = 0;
+        let mut tickets = HashSet::new();
+        let mut authors = HashSet::new();
+        let mut lead_times = Vec::new();
+        for info in tag_commits
+            .commit_to_tag
+            .values()
+            .filter(|info| info.tag_name == tag_name)
+        {
+            commits += 1;
+            tickets.extend(info.tickets.iter().map(|ticket| &ticket.id));
+            authors.insert(info.author.as_str());
+            authors.extend(info.co_authors.iter().map(String::as_str));
+            lead_times.extend(tag_commits.lead_time(info));
+        }
+        lead_times.sort_unstable();
+        all_tickets.extend(tickets.iter().copied());
+        all_authors.extend(authors.iter().copied());
+
+        let time = tag_commits.tag_times[tag_name];
+        releases.push(ReleaseStats {
+            tag: tag_name.to_owned(),
+            time,
+            commits,
+            tickets: tickets.len(),
+            authors: authors.len(),
+            since_previous: previous_time.map(|previous: i64| (time - previous).max(0)),
+            lead_time_min: lead_times.first().copied(),
+            lead_time_median: percentile(&lead_times, 0.5),
+            lead_time_p90: percentile(&lead_times, 0.9),
+        });
+        previous_time = Some(time);
+    }
+
+    let intervals = releases
+        .iter()
+        .filter_map(|release| release.since_previous)
+        .collect::<Vec<_>>();
+    Stats {
+        commits: releases.iter().map(|release| release.commits).sum(),
+        tickets: all_tickets.len(),
+        authors: all_authors.len(),
+        mean_interval: (!intervals.is_empty())
+            .then(|| intervals.iter().sum::<i64>() / intervals.len() as i64),
+        releases,
+    }
+}
+
+fn format_lead_times(release: &ReleaseStats) -> String {
+    match (
+        release.lead_time_min,
+        release.lead_time_median,
+        release.lead_time_p90,
+    ) {
+        (Some(min), Some(median), Some(p90)) => format!(
+            "{} / {} / {}",
+            format_duration(min),
+            format_duration(median),
+            format_duration(p90)
+        ),
+        _ => String::new(),
+    }
+}
+
+pub fn print_stats(stats: &Stats, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(stats).expect("stats should serialize to JSON")
+        );
+        return;
+    }
+
+    let tag_width = stats
+        .releases
+        .iter()
+        .map(|release| release.tag.len())
+        .chain(["Release".len(), "Total".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{}",
+        format!(
+            "{: <tag_width$}  {: <10}  {: >7}  {: >7}  {: >7}  {: <14}  {}",
+            "Release",
+            "Date",
+            "Commits",
+            "Tickets",
+            "Authors",
+            "Since previous",
+            "Lead time (min / median / p90)"
+        )
+        .bold()
+    );
+    for release in &stats.releases {
+        println!(
+            "{: <tag_width$}  {: <10}  {: >7}  {: >7}  {: >7}  {: <14}  {}",
+            release.tag,
+            format_date(release.time),
+            release.commits,
+            release.tickets,
+            release.authors,
+            release
+                .since_previous
+                .map(format_duration)
+                .unwrap_or_default(),
+            format_lead_times(release)
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "{: <tag_width$}  {: <10}  {: >7}  {: >7}  {: >7}  {}",
+            "Total",
+            format!("{} releases", stats.releases.len()),
+            stats.commits,
+            stats.tickets,
+            stats.authors,
+            stats
+                .mean_interval
+                .map(|mean| format!("{} mean", format_duration(mean)))
+                .unwrap_or_default()
+        )
+        .bold()
+    );
+}
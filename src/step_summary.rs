@@ -0,0 +1,91 @@
+use std::{env, fs::OpenOptions, io::Write};
+
+use crate::{
+    cli::Args,
+    markdown::releases_markdown,
+    metrics::format_date,
+    releases::{TagCommits, TagCommitsError},
+    tickets::Trackers,
+};
+
+/// The file GitHub Actions shows as the summary of a job, which each step appends to.
+const STEP_SUMMARY_VAR: &str = "GITHUB_STEP_SUMMARY";
+
+/// A summary of the releases for the job summary panel of GitHub Actions: a table of the
+/// releases, then the notes of each in a collapsed section so long releases don't bury the
+/// rest. Returns `None` if no releases matched `--filter`.
+fn release_summary(args: &Args, trackers: &Trackers, tag_commits: &TagCommits) -> Option<String> {
+    let releases = releases_markdown(args, trackers, tag_commits);
+    if releases.is_empty() {
+        return None;
+    }
+
+    let mut lines = vec![
+        "## Releases".to_owned(),
+        String::new(),
+        "| Release | Date | Commits | Tickets |".to_owned(),
+        "| --- | --- | ---: | ---: |".to_owned(),
+    ];
+    for release in &releases {
+        let mut tickets = release
+            .commits
+            .iter()
+            .flat_map(|info| &info.tickets)
+            .map(|ticket| ticket.id.as_str())
+            .collect::<Vec<_>>();
+        tickets.sort_unstable();
+        tickets.dedup();
+        let date = tag_commits
+            .tag_times
+            .get(release.tag_name)
+            .map(|time| format_date(*time))
+            .unwrap_or_default();
+        lines.push(format!(
+            "| `{}` | {} | {} | {} |",
+            release.tag_name,
+            date,
+            release.commits.len(),
+            tickets.len()
+        ));
+    }
+    for release in &releases {
+        let plural = if release.commits.len() == 1 { "" } else { "s" };
+        lines.push(String::new());
+        lines.push("<details>".to_owned());
+        lines.push(format!(
+            "<summary><strong>{}</strong> ({} commit{})</summary>",
+            release.tag_name,
+            release.commits.len(),
+            plural
+        ));
+        // Markdown is only rendered inside HTML blocks after a blank line
+        lines.push(String::new());
+        lines.push(release.body.clone());
+        lines.push(String::new());
+        lines.push("</details>".to_owned());
+    }
+    Some(lines.join("\n") + "\n")
+}
+
+/// Append a summary of the releases to the job summary in `GITHUB_STEP_SUMMARY`, or print it
+/// outside of GitHub Actions so it can be previewed. Returns whether any releases matched
+/// `--filter`.
+pub fn write_step_summary(
+    args: &Args,
+    trackers: &Trackers,
+    tag_commits: &TagCommits,
+) -> Result<bool, TagCommitsError> {
+    let Some(summary) = release_summary(args, trackers, tag_commits) else {
+        return Ok(false);
+    };
+    match env::var_os(STEP_SUMMARY_VAR) {
+        Some(path) => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(summary.as_bytes()))
+            .map_err(|err| TagCommitsError::Io("write job summary", err))?,
+        None => print!("{}", summary),
+    }
+    Ok(true)
+}
@@ -0,0 +1,158 @@
+use terminal_size::{terminal_size, Width};
+use unicode_width::UnicodeWidthChar;
+
+const SEPARATOR: &str = " | ";
+/// Columns aren't narrowed further than this to fit the terminal, so they stay readable.
+const MIN_WIDTH: usize = 10;
+
+/// The characters of some text, with ANSI escape sequences such as colours kept together as
+/// one item so they can be skipped when measuring it.
+fn segments(text: &str) -> Vec<(&str, bool)> {
+    let mut segments = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        let mut end = start + char.len_utf8();
+        let escape = char == '\x1b' && chars.peek().is_some_and(|(_, next)| *next == '[');
+        if escape {
+            // A control sequence ends with a byte in the range @ to ~
+            for (index, next) in chars.by_ref() {
+                end = index + next.len_utf8();
+                if index > start + 1 && ('@'..='~').contains(&next) {
+                    break;
+                }
+            }
+        }
+        segments.push((&text[start..end], escape));
+    }
+    segments
+}
+
+/// The number of columns some text takes up in a terminal, ignoring colours and counting wide
+/// characters such as CJK and emoji as two.
+pub fn display_width(text: &str) -> usize {
+    segments(text)
+        .into_iter()
+        .filter(|(_, escape)| !escape)
+        .filter_map(|(char, _)| char.chars().next()?.width())
+        .sum()
+}
+
+/// Shorten some text to fit in a width, ending it with `…` if it is cut, and keeping its
+/// colours.
+fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    let mut coloured = false;
+    for (segment, escape) in segments(text) {
+        if escape {
+            truncated.push_str(segment);
+            coloured = true;
+            continue;
+        }
+        let char_width = segment.chars().next().and_then(char::width).unwrap_or(0);
+        // Leave room for the ellipsis
+        if used + char_width >= width {
+            break;
+        }
+        truncated.push_str(segment);
+        used += char_width;
+    }
+    truncated.push('…');
+    if coloured {
+        truncated.push_str("\x1b[0m");
+    }
+    truncated
+}
+
+/// The width of the terminal output is going to, or `None` if it isn't going to a terminal,
+/// in which case lines aren't shortened so scripts see everything.
+fn terminal_width() -> Option<usize> {
+    terminal_size().map(|(Width(width), _)| width as usize)
+}
+
+/// Rows of text laid out in aligned columns separated by `|`, which may be coloured. Rows can
+/// have fewer cells than others, e.g. commits without a link.
+#[derive(Default)]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// The widths of the columns, narrowing the widest columns until every row fits within
+    /// `max_width` if one is given.
+    fn column_widths(&self, indent: usize, max_width: Option<usize>) -> Vec<usize> {
+        let mut widths = Vec::<usize>::new();
+        for row in &self.rows {
+            for (index, cell) in row.iter().enumerate() {
+                let width = display_width(cell);
+                match widths.get_mut(index) {
+                    Some(column_width) => *column_width = (*column_width).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        let Some(max_width) = max_width else {
+            return widths;
+        };
+
+        let available =
+            max_width.saturating_sub(indent + SEPARATOR.len() * widths.len().saturating_sub(1));
+        while widths.iter().sum::<usize>() > available {
+            let Some(widest) = widths
+                .iter_mut()
+                .max_by_key(|width| **width)
+                .filter(|width| **width > MIN_WIDTH)
+            else {
+                break;
+            };
+            *widest -= 1;
+        }
+        widths
+    }
+
+    /// The rows as lines, each starting with `indent` spaces, shortened to fit the terminal if
+    /// output is going to one.
+    pub fn lines(&self, indent: usize) -> Vec<String> {
+        let widths = self.column_widths(indent, terminal_width());
+        self.rows
+            .iter()
+            .map(|row| {
+                let last = row.len().saturating_sub(1);
+                let cells = row
+                    .iter()
+                    .zip(&widths)
+                    .enumerate()
+                    .map(|(index, (cell, width))| {
+                        let cell = truncate(cell, *width);
+                        // Don't pad the last cell, so lines don't end in spaces
+                        match index == last {
+                            true => cell,
+                            false => {
+                                let padding = width.saturating_sub(display_width(&cell));
+                                format!("{}{}", cell, " ".repeat(padding))
+                            }
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("{}{}", " ".repeat(indent), cells.join(SEPARATOR))
+            })
+            .collect()
+    }
+
+    pub fn print(&self, indent: usize) {
+        for line in self.lines(indent) {
+            println!("{}", line);
+        }
+    }
+}
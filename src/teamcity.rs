@@ -0,0 +1,33 @@
+/// Escape a value of a TeamCity service message, where `|` is the escape character.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|char| match char {
+            '|' => "||".to_owned(),
+            '\'' => "|'".to_owned(),
+            '\n' => "|n".to_owned(),
+            '\r' => "|r".to_owned(),
+            '[' => "|[".to_owned(),
+            ']' => "|]".to_owned(),
+            _ => char.to_string(),
+        })
+        .collect()
+}
+
+/// Print a TeamCity service message, which TeamCity reads from the build log, e.g.
+/// `##teamcity[buildStatisticValue key='rels.releases' value='4']`.
+pub fn print_service_message(name: &str, attributes: &[(&str, &str)]) {
+    let attributes = attributes
+        .iter()
+        .map(|(key, value)| format!(" {}='{}'", key, escape(value)))
+        .collect::<String>();
+    println!("##teamcity[{}{}]", name, attributes);
+}
+
+/// Report a value as a statistic of the build, which TeamCity charts across builds.
+pub fn print_statistic(key: &str, value: impl ToString) {
+    print_service_message(
+        "buildStatisticValue",
+        &[("key", key), ("value", &value.to_string())],
+    );
+}
@@ -0,0 +1,590 @@
+use colored::Colorize;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use git2::Repository;
+
+#[cfg(feature = "async")]
+use crate::lookups::lookup_all;
+
+use crate::{
+    cli::{Args, TicketColumn},
+    config::{Config, ConfigError, TrackerConfig},
+    forge::{origin_remote, Remote},
+    forge_releases::{ReleaseApi, ReleaseAsset},
+    http::HttpClient,
+    lookups::{LookupError, StatusCategory, TicketApi, TicketDetails},
+    presets::TrackerPreset,
+    pull_requests::{pr_number, PullRequestApi},
+    regex_engine::TicketRegex,
+    releases::{TagCommits, TagCommitsError},
+};
+
+/// The number at the end of a ticket reference, e.g. 123 for #123 or ABC-123.
+fn ticket_number(id: &str) -> &str {
+    let start = id
+        .rfind(|char: char| !char.is_ascii_digit())
+        .map_or(0, |index| index + 1);
+    &id[start..]
+}
+
+/// The project key of a ticket reference without a `project` capture group, e.g. ABC for
+/// ABC-123.
+fn ticket_prefix(id: &str) -> &str {
+    id[..id.len() - ticket_number(id).len()].trim_end_matches(['-', '#', '_'])
+}
+
+/// A ticket referenced by a commit.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Ticket {
+    pub id: String,
+    /// The index of the tracker the ticket belongs to
+    pub tracker: usize,
+}
+
+pub struct Tracker {
+    pub name: String,
+    pub regex: TicketRegex,
+    pub url: Option<String>,
+    /// URL templates for tickets in specific projects, overriding `url`
+    pub project_urls: BTreeMap<String, String>,
+    /// The API to look up ticket details with, if `--lookup` is enabled
+    pub api: Option<TicketApi>,
+    /// The built-in tracker this tracker is configured from, if any
+    pub preset: Option<TrackerPreset>,
+}
+
+impl Tracker {
+    /// The project key of a ticket, from the `project` capture group of the regex if it has one,
+    /// otherwise everything before the number, e.g. ABC for ABC-123.
+    fn project_key<'a>(&self, id: &'a str) -> &'a str {
+        self.regex
+            .capture(id, "project")
+            .unwrap_or_else(|| ticket_prefix(id))
+    }
+
+    /// The placeholders to replace in URL templates for a ticket: `{ticket}`, `{number}`, and
+    /// any named capture groups in the regex, which take precedence over the built-in ones.
+    fn placeholders(&self, id: &str) -> Vec<(String, String)> {
+        let mut placeholders = vec![
+            ("{ticket}".to_owned(), id.to_owned()),
+            ("{number}".to_owned(), ticket_number(id).to_owned()),
+        ];
+        for name in self.regex.capture_names() {
+            if let Some(value) = self.regex.capture(id, name) {
+                let placeholder = format!("{{{}}}", name);
+                placeholders.retain(|(existing, _)| *existing != placeholder);
+                placeholders.push((placeholder, value.to_owned()));
+            }
+        }
+        placeholders
+    }
+
+    /// The URL template for a ticket, preferring the URL for its project.
+    fn url_template(&self, id: &str) -> Option<&String> {
+        self.project_urls
+            .get(self.project_key(id))
+            .or(self.url.as_ref())
+    }
+}
+
+/// The issue trackers to find ticket references for, with their regexes compiled.
+pub struct Trackers {
+    trackers: Vec<Tracker>,
+    /// Whether tickets are matched case-insensitively and normalized to uppercase
+    ignore_case: bool,
+    /// Placeholder tickets to leave out, normalized in the same way as matched tickets
+    ignored_tickets: HashSet<String>,
+    /// Ticket details which have already been looked up, including tickets which weren't found
+    details: RefCell<HashMap<Ticket, Option<TicketDetails>>>,
+    /// Trackers whose APIs have failed, which aren't retried to avoid repeating the error
+    failed_apis: RefCell<HashSet<usize>>,
+    /// The details of looked up tickets to show, from `--show`
+    columns: Vec<TicketColumn>,
+    /// The most tickets to look up at the same time
+    #[cfg(feature = "async")]
+    max_concurrent_requests: u32,
+    /// The forge to fetch the pull requests of squash-merged commits from, for `--fetch-prs`
+    pull_requests: Option<PullRequestApi>,
+    /// The releases API of the forge, for `--assets`
+    releases: Option<ReleaseApi>,
+    /// The forge hosting the `origin` remote, which pull requests are linked to
+    origin: Option<Remote>,
+    /// The time spent matching ticket regexes, for `--timings`
+    match_time: Cell<Duration>,
+}
+
+/// The settings of each tracker, from the configuration and `--tracker` presets. The default Jira
+/// tracker from the command line arguments is kept alongside `--tracker` presets, unless the
+/// config file has trackers, which replace it. `--jira-regex` and `--jira-url` override the
+/// tracker named `jira`, adding it if there isn't one.
+pub fn resolve_tracker_configs(
+    config: &Config,
+    args: &Args,
+    repo: &Repository,
+) -> Result<Vec<TrackerConfig>, TagCommitsError> {
+    let mut tracker_configs = Vec::new();
+    for tracker in &config.trackers {
+        // A tracker named jira is Jira, so it can be used by --lookup and the rels jira commands
+        let named_jira = tracker.name.as_deref() == Some("jira");
+        let Some(preset) = tracker.preset.or(named_jira.then_some(TrackerPreset::Jira)) else {
+            tracker_configs.push(TrackerConfig::default().merge(tracker));
+            continue;
+        };
+
+        let preset_configs = preset.tracker_configs(repo, args, tracker);
+        if preset_configs.len() > 1 && tracker.has_tracker_settings() {
+            return Err(ConfigError::Invalid(format!(
+                "the {} preset configures multiple trackers, so name, regex, and url can't be set",
+                preset
+            ))
+            .into());
+        }
+        tracker_configs.extend(
+            preset_configs
+                .into_iter()
+                .map(|config| config.merge(tracker)),
+        );
+    }
+    for preset in &args.trackers {
+        tracker_configs.extend(preset.tracker_configs(repo, args, &TrackerConfig::default()));
+    }
+
+    let jira_overridden = !args.jira_regex.is_empty() || !args.jira_url.is_empty();
+    let has_jira = tracker_configs
+        .iter()
+        .any(|tracker| tracker.name.as_deref() == Some("jira"));
+    if !has_jira && (config.trackers.is_empty() || jira_overridden) {
+        tracker_configs.extend(TrackerPreset::Jira.tracker_configs(
+            repo,
+            args,
+            &TrackerConfig::default(),
+        ));
+    }
+
+    if let Some(jira) = tracker_configs
+        .iter_mut()
+        .find(|tracker| tracker.name.as_deref() == Some("jira"))
+    {
+        jira.regex = args.jira_regex.first().cloned().or(jira.regex.take());
+        jira.url = args.jira_url.first().cloned().or(jira.url.take());
+
+        // Further regexes get their own trackers, linked with the --jira-url in the same
+        // position, or the first one
+        let extra_trackers = (1..args.jira_regex.len())
+            .map(|index| TrackerConfig {
+                name: Some(format!("jira-{}", index + 1)),
+                regex: Some(args.jira_regex[index].clone()),
+                url: args.jira_url.get(index).cloned().or(jira.url.clone()),
+                ..jira.clone()
+            })
+            .collect::<Vec<_>>();
+        tracker_configs.extend(extra_trackers);
+    }
+    Ok(tracker_configs)
+}
+
+impl Trackers {
+    /// Build the trackers from the configuration, command line arguments, and presets, as
+    /// resolved by [`resolve_tracker_configs`].
+    pub fn new(
+        config: &Config,
+        args: &Args,
+        repo: &Repository,
+    ) -> Result<Trackers, TagCommitsError> {
+        let tracker_configs = resolve_tracker_configs(config, args, repo)?;
+        let http = match args.lookup {
+            true => Some(HttpClient::new(args)?),
+            false => None,
+        };
+        let pull_requests = match args.fetch_prs {
+            true => PullRequestApi::new(repo, args, http.as_ref())?,
+            false => None,
+        };
+        let releases = match args.assets {
+            true => ReleaseApi::new(repo, args, http.as_ref(), "--assets")?,
+            false => None,
+        };
+
+        let mut trackers = Vec::new();
+        for tracker in tracker_configs {
+            let api = match (tracker.preset, &http) {
+                (Some(preset), Some(http)) => preset.api(&tracker, http)?,
+                _ => None,
+            };
+            let (Some(name), Some(regex)) = (tracker.name, tracker.regex) else {
+                return Err(ConfigError::Invalid(
+                    "trackers must have a name and regex, or a preset".to_owned(),
+                )
+                .into());
+            };
+
+            trackers.push(Tracker {
+                name,
+                regex: TicketRegex::new(&regex, args.regex_engine, args.ignore_case)?,
+                url: tracker.url,
+                project_urls: tracker.project_urls.unwrap_or_default(),
+                api,
+                preset: tracker.preset,
+            });
+        }
+
+        Ok(Trackers {
+            trackers,
+            ignore_case: args.ignore_case,
+            ignored_tickets: config
+                .ignored_tickets
+                .iter()
+                .chain(&args.ignored_tickets)
+                .map(|id| {
+                    if args.ignore_case {
+                        id.to_uppercase()
+                    } else {
+                        id.clone()
+                    }
+                })
+                .collect(),
+            details: RefCell::new(HashMap::new()),
+            failed_apis: RefCell::new(HashSet::new()),
+            columns: args.show.clone(),
+            #[cfg(feature = "async")]
+            max_concurrent_requests: args.max_concurrent_requests,
+            pull_requests,
+            releases,
+            origin: origin_remote(repo, args),
+            match_time: Cell::new(Duration::ZERO),
+        })
+    }
+
+    /// Find all tickets referenced in a commit message, normalized to uppercase if matching
+    /// case-insensitively so different spellings are the same ticket. A ticket matched by more
+    /// than one tracker belongs to the first. Ignored placeholder tickets are left out.
+    pub fn find_tickets(&self, message: &str) -> Vec<Ticket> {
+        let start = Instant::now();
+        let mut tickets = Vec::new();
+        for (index, tracker) in self.trackers.iter().enumerate() {
+            for id in tracker.regex.find_iter(message) {
+                let id = if self.ignore_case {
+                    id.to_uppercase()
+                } else {
+                    id.to_owned()
+                };
+                if self.ignored_tickets.contains(&id) {
+                    continue;
+                }
+                // Trackers with overlapping regexes, e.g. the linear and youtrack presets, find
+                // the same tickets, which belong to the first
+                if !tickets.iter().any(|ticket: &Ticket| ticket.id == id) {
+                    tickets.push(Ticket { id, tracker: index });
+                }
+            }
+        }
+        self.match_time.set(self.match_time.get() + start.elapsed());
+        tickets
+    }
+
+    /// The total time spent matching ticket regexes so far.
+    pub fn match_time(&self) -> Duration {
+        self.match_time.get()
+    }
+
+    /// Find the tickets referenced in a commit message as [`Trackers::find_tickets`] does. With
+    /// `--fetch-prs`, merged commits which don't reference a ticket, e.g. `Squashed commit
+    /// (#482)`, are given the tickets referenced in the title and description of their
+    /// pull request instead, along with its title.
+    pub fn find_commit_tickets(&self, message: &str) -> (Vec<Ticket>, Option<String>) {
+        let tickets = self.find_tickets(message);
+        if !tickets.is_empty() {
+            return (tickets, None);
+        }
+        let Some(pull_request) = self
+            .pull_requests
+            .as_ref()
+            .zip(pr_number(message))
+            .and_then(|(api, number)| api.fetch(number))
+        else {
+            return (tickets, None);
+        };
+        let tickets = self.find_tickets(&format!(
+            "{}\n\n{}",
+            pull_request.title,
+            pull_request.body.unwrap_or_default()
+        ));
+        (tickets, Some(pull_request.title))
+    }
+
+    /// The assets published with the release of a tag on the forge, with `--assets`. `None` if
+    /// they weren't fetched, or there isn't a published release.
+    pub fn release_assets(&self, tag_name: &str) -> Option<Vec<ReleaseAsset>> {
+        Some(self.releases.as_ref()?.fetch(tag_name)?.assets)
+    }
+
+    /// The URL of the diff between a release and the release it follows, on the forge hosting
+    /// the `origin` remote, if it is known.
+    pub fn compare_url(&self, tag_commits: &TagCommits, tag_name: &str) -> Option<String> {
+        let previous = tag_commits.previous_releases.get(tag_name)?;
+        self.origin.as_ref()?.compare_url(previous, tag_name)
+    }
+
+    /// The URL of a pull request on the forge hosting the `origin` remote, if it is on GitHub
+    /// or GitLab.
+    pub fn pr_url(&self, number: u64) -> Option<String> {
+        self.origin.as_ref()?.pull_request_url(number)
+    }
+
+    /// The names of the trackers, in the order they are matched.
+    pub fn names(&self) -> Vec<&str> {
+        self.trackers
+            .iter()
+            .map(|tracker| tracker.name.as_str())
+            .collect()
+    }
+
+    /// Whether any tracker has a URL configured, so tickets can be linked.
+    pub fn has_urls(&self) -> bool {
+        self.trackers
+            .iter()
+            .any(|tracker| tracker.url.is_some() || !tracker.project_urls.is_empty())
+    }
+
+    /// Build the URL for a ticket, using the URL for its project if the tracker has one. If
+    /// `{ticket}` is included in the URL it is replaced with the ticket number, `{number}` with
+    /// the number at the end of the ticket reference, and `{name}` with the capture group of
+    /// the regex with that name. Otherwise the ticket number is appended to the end of the URL.
+    /// Returns `None` for tickets without a URL.
+    pub fn link(&self, ticket: &Ticket) -> Option<String> {
+        let tracker = &self.trackers[ticket.tracker];
+        let url = tracker.url_template(&ticket.id)?;
+        let placeholders = tracker.placeholders(&ticket.id);
+        if placeholders
+            .iter()
+            .any(|(placeholder, _)| url.contains(placeholder))
+        {
+            Some(
+                placeholders
+                    .iter()
+                    .fold(url.clone(), |url, (placeholder, value)| {
+                        url.replace(placeholder, value)
+                    }),
+            )
+        } else {
+            Some(format!("{}{}", url, ticket.id))
+        }
+    }
+
+    /// The URL for a ticket as described in [`Trackers::link`], or the ticket as is if it
+    /// doesn't have a URL.
+    pub fn url(&self, ticket: &Ticket) -> String {
+        self.link(ticket).unwrap_or_else(|| ticket.id.clone())
+    }
+
+    /// The project key of a ticket, e.g. ABC for ABC-123, as described in
+    /// [`Tracker::project_key`].
+    pub fn project_key<'a>(&self, ticket: &'a Ticket) -> &'a str {
+        self.trackers[ticket.tracker].project_key(&ticket.id)
+    }
+
+    /// The built-in tracker a ticket's tracker is configured from, if any.
+    pub fn preset(&self, ticket: &Ticket) -> Option<TrackerPreset> {
+        self.trackers[ticket.tracker].preset
+    }
+
+    /// The name of the tracker a ticket belongs to.
+    pub fn tracker_name(&self, ticket: &Ticket) -> &str {
+        &self.trackers[ticket.tracker].name
+    }
+
+    /// Look up the details of a ticket from its tracker's API, if it has one. Failures are
+    /// reported as warnings, so tickets are still shown without their details.
+    pub fn details(&self, ticket: &Ticket) -> Option<TicketDetails> {
+        let api = self.trackers[ticket.tracker].api.as_ref()?;
+        if let Some(details) = self.details.borrow().get(ticket) {
+            return details.clone();
+        }
+        if self.failed_apis.borrow().contains(&ticket.tracker) {
+            return None;
+        }
+        self.store_details(ticket, api.lookup(&ticket.id))
+    }
+
+    /// Remember the result of looking up a ticket, warning about the first failure for each
+    /// tracker and not looking up any more of its tickets after it.
+    fn store_details(
+        &self,
+        ticket: &Ticket,
+        result: Result<Option<TicketDetails>, LookupError>,
+    ) -> Option<TicketDetails> {
+        let details = match result {
+            Ok(details) => details,
+            Err(err) => {
+                if self.failed_apis.borrow_mut().insert(ticket.tracker) {
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "Failed to look up {} tickets: {}",
+                            self.trackers[ticket.tracker].name, err
+                        )
+                        .yellow()
+                    );
+                }
+                None
+            }
+        };
+        self.details
+            .borrow_mut()
+            .insert(ticket.clone(), details.clone());
+        details
+    }
+
+    /// Look up the details of tickets which are about to be shown all at once, rather than one
+    /// at a time as they are shown. Without the `async` feature, they're still looked up one at
+    /// a time by [`Trackers::details`].
+    #[cfg(feature = "async")]
+    pub fn prefetch_details<'a>(&self, tickets: impl IntoIterator<Item = &'a Ticket>) {
+        let mut pending = Vec::new();
+        for ticket in tickets {
+            let Some(api) = &self.trackers[ticket.tracker].api else {
+                continue;
+            };
+            if self.details.borrow().contains_key(ticket)
+                || self.failed_apis.borrow().contains(&ticket.tracker)
+                || pending.iter().any(|(pending, _)| *pending == ticket)
+            {
+                continue;
+            }
+            pending.push((ticket, api));
+        }
+        if pending.is_empty() {
+            return;
+        }
+
+        let results = lookup_all(
+            pending
+                .iter()
+                .map(|(ticket, api)| ((*api).clone(), ticket.id.clone()))
+                .collect(),
+            self.max_concurrent_requests,
+        );
+        for ((ticket, _), result) in pending.iter().zip(results) {
+            self.store_details(ticket, result);
+        }
+    }
+
+    #[cfg(not(feature = "async"))]
+    pub fn prefetch_details<'a>(&self, _tickets: impl IntoIterator<Item = &'a Ticket>) {}
+
+    /// Whether any tracker API lookups have failed.
+    pub fn had_api_errors(&self) -> bool {
+        !self.failed_apis.borrow().is_empty()
+            || self
+                .pull_requests
+                .as_ref()
+                .is_some_and(|pull_requests| pull_requests.failed())
+            || self
+                .releases
+                .as_ref()
+                .is_some_and(|releases| releases.failed())
+    }
+
+    /// Format a ticket for display, labelled with its tracker if there is more than one, and
+    /// followed by its title and status if they have been looked up.
+    pub fn format(&self, ticket: &Ticket) -> String {
+        let mut formatted = ticket.id.bold().italic().to_string();
+        if self.trackers.len() > 1 {
+            let label = format!("[{}]", self.trackers[ticket.tracker].name);
+            formatted = format!("{} {}", formatted, label.dimmed());
+        }
+        if let Some(details) = self.details(ticket) {
+            if self.columns.contains(&TicketColumn::Type) {
+                if let Some(ticket_type) = &details.ticket_type {
+                    formatted = format!("{} [{}]", formatted, ticket_type);
+                }
+            }
+            formatted = format!("{} {}", formatted, details.title);
+            if let Some(status) = details.status {
+                let status = format!("({})", status);
+                // Coloured by category, so unfinished tickets stand out in a release
+                let status = match details.status_category {
+                    Some(StatusCategory::ToDo) => status.blue(),
+                    Some(StatusCategory::InProgress) => status.yellow(),
+                    Some(StatusCategory::Done) => status.green(),
+                    None => status.dimmed(),
+                };
+                formatted = format!("{} {}", formatted, status);
+            }
+            if self.columns.contains(&TicketColumn::Assignee) {
+                if let Some(assignee) = &details.assignee {
+                    formatted = format!("{} {}", formatted, format!("@{}", assignee).dimmed());
+                }
+            }
+        }
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex_engine::RegexEngine;
+
+    fn tracker(regex: &str) -> Tracker {
+        Tracker {
+            name: "jira".to_owned(),
+            regex: TicketRegex::new(regex, RegexEngine::Standard, false)
+                .unwrap_or_else(|_| panic!("the regex should be valid")),
+            url: Some("https://jira.example.com/browse/".to_owned()),
+            project_urls: BTreeMap::from([(
+                "OPS".to_owned(),
+                "https://ops.example.com/{ticket}".to_owned(),
+            )]),
+            api: None,
+            preset: None,
+        }
+    }
+
+    #[test]
+    fn placeholders_include_the_ticket_and_its_number() {
+        let placeholders = tracker(r"[A-Z]+-[0-9]+").placeholders("ABC-123");
+
+        assert_eq!(
+            placeholders,
+            [
+                ("{ticket}".to_owned(), "ABC-123".to_owned()),
+                ("{number}".to_owned(), "123".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn capture_groups_replace_built_in_placeholders() {
+        let tracker = tracker(r"(?<project>[A-Z]+)-(?<number>[0-9]+)");
+        let placeholders = tracker.placeholders("ABC-123");
+
+        assert_eq!(
+            placeholders,
+            [
+                ("{ticket}".to_owned(), "ABC-123".to_owned()),
+                ("{project}".to_owned(), "ABC".to_owned()),
+                ("{number}".to_owned(), "123".to_owned()),
+            ]
+        );
+        assert_eq!(tracker.project_key("ABC-123"), "ABC");
+    }
+
+    #[test]
+    fn project_urls_take_precedence_over_the_tracker_url() {
+        let tracker = tracker(r"[A-Z]+-[0-9]+");
+
+        assert_eq!(
+            tracker.url_template("OPS-1").map(String::as_str),
+            Some("https://ops.example.com/{ticket}")
+        );
+        assert_eq!(
+            tracker.url_template("ABC-1").map(String::as_str),
+            Some("https://jira.example.com/browse/")
+        );
+    }
+}
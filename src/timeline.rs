@@ -0,0 +1,110 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{
+    cadence::{date, format_period, period_start, periods},
+    cli::{OutputFormat, Period},
+    metrics::releases_within,
+    releases::TagCommits,
+};
+
+/// Runs of at least this many periods without releases are collapsed onto one line.
+const MIN_GAP_TO_COLLAPSE: usize = 3;
+
+/// The releases tagged in a day, week or month.
+#[derive(Serialize)]
+pub struct TimelinePeriod<'a> {
+    /// The first day of the period, e.g. `2024-03-01`
+    pub start: String,
+    pub releases: Vec<&'a str>,
+}
+
+/// Place the releases tagged within `--age` of now in each period, including periods without
+/// any releases, oldest first.
+pub fn get_timeline(
+    tag_commits: &TagCommits,
+    max_age: Duration,
+    period: Period,
+) -> Vec<TimelinePeriod<'_>> {
+    let releases = releases_within(tag_commits, max_age)
+        .into_iter()
+        .map(|tag_name| {
+            let start = period_start(date(tag_commits.tag_times[tag_name]), period);
+            (start, tag_name)
+        })
+        .collect::<Vec<_>>();
+
+    periods(max_age, period)
+        .into_iter()
+        .map(|start| TimelinePeriod {
+            start: start.format("%Y-%m-%d").to_string(),
+            releases: releases
+                .iter()
+                .filter(|(release_start, _)| *release_start == start)
+                .map(|(_, tag_name)| *tag_name)
+                .collect(),
+        })
+        .collect()
+}
+
+fn period_name(period: Period, count: usize) -> String {
+    let name = match period {
+        Period::Day => "day",
+        Period::Week => "week",
+        Period::Month => "month",
+    };
+    if count == 1 {
+        name.to_owned()
+    } else {
+        format!("{}s", name)
+    }
+}
+
+/// Print a line for each period with the releases in it. Long runs of periods without releases
+/// are collapsed into one line saying how long the gap was.
+pub fn print_timeline(timeline: &[TimelinePeriod], period: Period, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(timeline).expect("timeline should serialize to JSON")
+        );
+        return;
+    }
+
+    let label_width = timeline
+        .first()
+        .map(|first| format_period(&first.start, period).len())
+        .unwrap_or_default();
+    let mut index = 0;
+    while index < timeline.len() {
+        let gap = timeline[index..]
+            .iter()
+            .take_while(|entry| entry.releases.is_empty())
+            .count();
+        if gap >= MIN_GAP_TO_COLLAPSE {
+            println!(
+                "{: <label_width$} {} {}",
+                "",
+                "┆".dimmed(),
+                format!("{} {} without a release", gap, period_name(period, gap)).dimmed()
+            );
+            index += gap;
+            continue;
+        }
+
+        let entry = &timeline[index];
+        let label = format_period(&entry.start, period);
+        if entry.releases.is_empty() {
+            println!("{} {}", label.dimmed(), "│".dimmed());
+        } else {
+            println!(
+                "{} {} {}",
+                label,
+                "●".green(),
+                entry.releases.join(", ").green().bold()
+            );
+        }
+        index += 1;
+    }
+}
@@ -0,0 +1,59 @@
+use colored::Colorize;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Records how long each phase of a run takes, for `--timings`.
+pub struct Timings {
+    enabled: bool,
+    phase_start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+    /// Work spread across phases, whose time is included in theirs
+    breakdown: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        Timings {
+            enabled,
+            phase_start: Instant::now(),
+            phases: Vec::new(),
+            breakdown: Vec::new(),
+        }
+    }
+
+    /// Mark the end of a phase, which started at the end of the previous phase.
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        debug!(phase = name, duration = ?now - self.phase_start, "finished phase");
+        self.phases.push((name, now - self.phase_start));
+        self.phase_start = now;
+    }
+
+    /// Record the time spent on work spread across phases, e.g. matching ticket regexes while
+    /// searching tags.
+    pub fn breakdown(&mut self, name: &'static str, duration: Duration) {
+        debug!(work = name, ?duration, "finished work across phases");
+        self.breakdown.push((name, duration));
+    }
+
+    /// Print the phase timings to stderr, if enabled.
+    pub fn print(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let total: Duration = self.phases.iter().map(|(_, duration)| *duration).sum();
+        eprintln!("{}", "Timings".bold());
+        for (name, duration) in &self.phases {
+            eprintln!("  {: <16} {:>10.2?}", name, duration);
+        }
+        eprintln!("  {: <16} {:>10.2?}", "total", total);
+        for (name, duration) in &self.breakdown {
+            let line = format!(
+                "  {: <16} {:>10.2?} (within the phases above)",
+                name, duration
+            );
+            eprintln!("{}", line.dimmed());
+        }
+    }
+}
@@ -0,0 +1,25 @@
+/// The values of the trailers with a key at the end of a commit message, e.g. the people in
+/// `Co-authored-by: Name <email>` lines. Keys are matched ignoring case, as git does.
+pub fn trailer_values(message: &str, key: &str) -> Vec<String> {
+    let Ok(trailers) = git2::message_trailers_strs(message) else {
+        return Vec::new();
+    };
+    trailers
+        .iter()
+        .filter(|(trailer_key, _)| trailer_key.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.trim().to_owned())
+        .collect()
+}
+
+/// The names of the people in trailers with a key, leaving out their emails, e.g. `Ada` for
+/// `Co-authored-by: Ada <ada@example.com>`.
+pub fn trailer_names(message: &str, key: &str) -> Vec<String> {
+    trailer_values(message, key)
+        .into_iter()
+        .map(|value| match value.split_once(" <") {
+            Some((name, _)) => name.trim().to_owned(),
+            None => value,
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
@@ -0,0 +1,111 @@
+use chrono::Local;
+use colored::Colorize;
+use std::{
+    io::{self, IsTerminal, Write},
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use git2::Repository;
+
+use crate::forge_api::warn;
+
+/// How often the tags are checked for changes between refreshes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Parse the `--interval` of `--watch`, e.g. `30s` or `5m`.
+pub fn parse_interval(interval: &str) -> Result<Duration, String> {
+    match duration_str::parse(interval) {
+        Ok(interval) if interval.is_zero() => Err("the interval must be longer than 0s".to_owned()),
+        Ok(interval) => Ok(interval),
+        Err(_) => {
+            Err("expected a number followed by a unit such as h, m, or s, e.g. 5m".to_owned())
+        }
+    }
+}
+
+/// The tags in the repository and the objects they point at, to tell when tags have been
+/// created, deleted, or moved.
+fn tag_refs(repo: &Repository) -> Vec<(String, Option<git2::Oid>)> {
+    let mut tags = repo
+        .references_glob("refs/tags/*")
+        .map(|references| {
+            references
+                .flatten()
+                .filter_map(|reference| Some((reference.name()?.to_owned(), reference.target())))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    tags.sort_unstable();
+    tags
+}
+
+fn fetch_tags(repo: &Repository) {
+    let status = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo.path())
+        .args(["fetch", "--tags", "--quiet"])
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn(&format!("git fetch --tags failed ({})", status)),
+        Err(err) => warn(&format!("Failed to run git fetch --tags: {}", err)),
+    }
+}
+
+/// Run `refresh` again and again until rels is stopped, every `interval` or as soon as a tag
+/// is created, deleted, or moved. With `fetch`, tags are fetched from the remotes before each
+/// timed refresh. The screen is cleared before each refresh when printing to a terminal, so
+/// the output can be left up, e.g. on a release dashboard. A header says when it was last
+/// refreshed, unless `quiet`.
+pub fn watch(
+    repo: &Repository,
+    interval: Duration,
+    fetch: bool,
+    quiet: bool,
+    mut refresh: impl FnMut(),
+) -> ! {
+    let clear = io::stdout().is_terminal();
+    loop {
+        if fetch {
+            fetch_tags(repo);
+        }
+        let tags = tag_refs(repo);
+        if clear {
+            // Clear the screen and move the cursor to the top left
+            print!("\x1b[2J\x1b[H");
+        }
+        if !quiet {
+            println!(
+                "{}",
+                format!(
+                    "Updated at {}, refreshing every {} or when tags change",
+                    Local::now().format("%H:%M:%S"),
+                    format_interval(interval)
+                )
+                .dimmed()
+            );
+            println!();
+        }
+        refresh();
+        // Flush output which doesn't end in a newline before waiting
+        let _ = io::stdout().flush();
+
+        let refreshed = Instant::now();
+        while refreshed.elapsed() < interval && tag_refs(repo) == tags {
+            thread::sleep(POLL_INTERVAL.min(interval.saturating_sub(refreshed.elapsed())));
+        }
+    }
+}
+
+/// An interval as the largest whole unit it is a multiple of, e.g. `5m` rather than `300s`.
+fn format_interval(interval: Duration) -> String {
+    let seconds = interval.as_secs();
+    match seconds {
+        0 => format!("{}ms", interval.as_millis()),
+        _ if seconds.is_multiple_of(3600) => format!("{}h", seconds / 3600),
+        _ if seconds.is_multiple_of(60) => format!("{}m", seconds / 60),
+        _ => format!("{}s", seconds),
+    }
+}